@@ -38,7 +38,8 @@ export const createLockupSchedule = async (
     const periodDurationBytes = new BN(periodDuration).toArray("le", 8);
     const lockupQuantityBytes = new BN(lockupQuantity).toArray("le", 8);
 
-    const data = Buffer.from(Uint8Array.of(0, ...startTimestampBytes, ...unlockPeriodsBytes, ...periodDurationBytes, ...lockupQuantityBytes));
+    // time unit, period type and curve of None: linear over fixed periods of unix seconds
+    const data = Buffer.from(Uint8Array.of(0, ...startTimestampBytes, ...unlockPeriodsBytes, ...periodDurationBytes, ...lockupQuantityBytes, 0, 0, 0));
 
     const createLockupScheduleIx = new TransactionInstruction({
         programId: programId,
@@ -77,7 +78,11 @@ export const createLockupSchedule = async (
         numberPeriods: new BN(decodedLockupScheduleState.numberPeriods, 10, "le").toNumber(),
         periodDuration: new BN(decodedLockupScheduleState.periodDuration, 10, "le").toNumber(),
         totalTokenQuantity: new BN(decodedLockupScheduleState.totalTokenQuantity, 10, "le").toNumber(),
-        tokenQuantityLocked: new BN(decodedLockupScheduleState.tokenQuantityLocked, 10, "le").toNumber()
+        tokenQuantityLocked: new BN(decodedLockupScheduleState.tokenQuantityLocked, 10, "le").toNumber(),
+        paused: decodedLockupScheduleState.paused === 1,
+        timeUnit: decodedLockupScheduleState.timeUnit,
+        periodType: decodedLockupScheduleState.periodType,
+        curveType: decodedLockupScheduleState.curveType
     }
 
     return lockupScheduleStateObj;
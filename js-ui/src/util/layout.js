@@ -14,7 +14,22 @@ const uint64 = (property = "uint64") => {
   return BufferLayout.blob(8, property);
 };
 
+/**
+ * Layout for a point of a vesting curve
+ */
+const curvePoint = (property = "curvePoint") => {
+  return BufferLayout.struct([
+    BufferLayout.u16("period"),
+    BufferLayout.u16("basisPoints")
+  ], property);
+};
+
+const MAX_CURVE_POINTS = 16;
+
+// 268 bytes, see LockupSchedule in rust-app/src/state.rs
 export const LOCKUP_SCHEDULE_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+    BufferLayout.u8("accountType"),
+    BufferLayout.u8("version"),
     BufferLayout.u8("isInitialized"),
     publicKey("initializer"),
     publicKey("tokenMint"),
@@ -22,14 +37,31 @@ export const LOCKUP_SCHEDULE_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
     uint64("numberPeriods"),
     uint64("periodDuration"),
     uint64("totalTokenQuantity"),
-    uint64("tokenQuantityLocked")
+    uint64("tokenQuantityLocked"),
+    publicKey("pauseAuthority"),
+    BufferLayout.u8("paused"),
+    publicKey("complianceAuthority"),
+    BufferLayout.u8("timeUnit"),
+    BufferLayout.u8("periodType"),
+    BufferLayout.u8("curveType"),
+    BufferLayout.u8("curvePointsLen"),
+    BufferLayout.seq(curvePoint(), MAX_CURVE_POINTS, "curvePoints"),
+    BufferLayout.blob(28, "reserved")
   ]);
 
+  // 307 bytes, see Lockup in rust-app/src/state.rs
   export const LOCKUP_ACCOUNT_DATA_LAYOUT = BufferLayout.struct([
+    BufferLayout.u8("accountType"),
+    BufferLayout.u8("version"),
     BufferLayout.u8("isInitialized"),
     publicKey("lockupScheduleState"),
     publicKey("receivingAccount"),
     publicKey("lockupTokenAccount"),
     uint64("tokenQuantity"),
-    uint64("periodsRedeemed")
-  ]);
\ No newline at end of file
+    uint64("periodsRedeemed"),
+    BufferLayout.u8("frozen"),
+    publicKey("claimDelegate"),
+    publicKey("delegateDestination"),
+    uint64("redeemedAmount"),
+    BufferLayout.blob(119, "reserved")
+  ]);
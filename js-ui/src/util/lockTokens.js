@@ -31,25 +31,24 @@ export const lockTokens = async (
     });
     // 3. [] token receiver main Solana account
     const receiverPubkey = new PublicKey(receiverPubkeyString);
-    // 4. [writable] temporary lockup token account (create new)
-    const tempTokenAccount = new Keypair();
-    const createTempTokenAccountIx = SystemProgram.createAccount({
+    // 4. [writable] lockup token account (create new, empty and owned by the program-derived address)
+    const PDA = await PublicKey.findProgramAddress([Buffer.from("tokenDistributor")], programId);
+    const lockupTokenAccount = new Keypair();
+    const createLockupTokenAccountIx = SystemProgram.createAccount({
         programId: TOKEN_PROGRAM_ID,
         space: AccountLayout.span,
-        lamports: await connection.getMinimumBalanceForRentExemption(AccountLayout.span, 'singleGossip'),
+        lamports: await connection.getMinimumBalanceForRentExemption(AccountLayout.span, 'confirmed'),
         fromPubkey: wallet.publicKey,
-        newAccountPubkey: tempTokenAccount.publicKey
+        newAccountPubkey: lockupTokenAccount.publicKey
     });
-    // initialize temp token account
     const tokenMintAccountPubkey = new PublicKey(tokenMint);
-    const initTempAccountIx = Token.createInitAccountInstruction(TOKEN_PROGRAM_ID, tokenMintAccountPubkey, tempTokenAccount.publicKey, wallet.publicKey);
-    // send tokens to temp account
-    const initializerTokenPubkey = new PublicKey(initializerTokenAccount);
-    const transferTokensToTempAccIx = Token.createTransferInstruction(TOKEN_PROGRAM_ID, initializerTokenPubkey, tempTokenAccount.publicKey, wallet.publicKey, [], quantity);
-
-    // 5. [] token program (transfer ownership of temp token account to PDA) - TOKEN_PROGRAM_ID
+    const initLockupTokenAccountIx = Token.createInitAccountInstruction(TOKEN_PROGRAM_ID, tokenMintAccountPubkey, lockupTokenAccount.publicKey, PDA[0]);
+    // 5. [] token program - TOKEN_PROGRAM_ID
     // 6. [] clock sysvar - SYSVAR_CLOCK_PUBKEY
     // 7. [] rent sysvar - SYSVAR_RENT_PUBKEY
+    // 8. [writable] source token account owned by the initializer (the program moves the tokens with transfer_checked)
+    const initializerTokenPubkey = new PublicKey(initializerTokenAccount);
+    // 9. [] token mint
 
     const quantityBytes = new BN(quantity).toArray("le", 8);
 
@@ -62,26 +61,27 @@ export const lockTokens = async (
             { pubkey: lockupScheduleStatePubkey, isSigner: false, isWritable: true },
             { pubkey: lockupStateAccount.publicKey, isSigner: false, isWritable: true },
             { pubkey: receiverPubkey, isSigner: false, isWritable: false },
-            { pubkey: tempTokenAccount.publicKey, isSigner: false, isWritable: true },
+            { pubkey: lockupTokenAccount.publicKey, isSigner: false, isWritable: true },
             { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
             { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false},
-            { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false }
+            { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+            { pubkey: initializerTokenPubkey, isSigner: false, isWritable: true },
+            { pubkey: tokenMintAccountPubkey, isSigner: false, isWritable: false }
         ],
         data: data
     });
 
     const tx = new Transaction().add(
         createLockupStateAccountIx, 
-        createTempTokenAccountIx,
-        initTempAccountIx,
-        transferTokensToTempAccIx,
+        createLockupTokenAccountIx,
+        initLockupTokenAccountIx,
         createLockupIx
     );
 
     let { blockhash } = await connection.getRecentBlockhash();
     tx.recentBlockhash = blockhash;
     tx.feePayer = wallet.publicKey;
-    let partialSigners = [lockupStateAccount, tempTokenAccount];
+    let partialSigners = [lockupStateAccount, lockupTokenAccount];
     tx.partialSign(...partialSigners);
     let signed = await wallet.signTransaction(tx);
     let txid = await connection.sendRawTransaction(signed.serialize());
@@ -98,7 +98,10 @@ export const lockTokens = async (
         receivingAccount: new PublicKey(decodedLockupState.receivingAccount).toBase58(),
         lockupTokenAccount: new PublicKey(decodedLockupState.lockupTokenAccount).toBase58(),
         tokenQuantity: new BN(decodedLockupState.tokenQuantity, 10, "le").toNumber(),
-        periodsRedeemed: new BN(decodedLockupState.periodsRedeemed, 10, "le").toNumber()
+        periodsRedeemed: new BN(decodedLockupState.periodsRedeemed, 10, "le").toNumber(),
+        frozen: decodedLockupState.frozen === 1,
+        claimDelegate: new PublicKey(decodedLockupState.claimDelegate).toBase58(),
+        redeemedAmount: new BN(decodedLockupState.redeemedAmount, 10, "le").toNumber()
     }
 
     return lockupStateObj;
@@ -1,7 +1,7 @@
 import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
 import { Connection, PublicKey,SYSVAR_CLOCK_PUBKEY, Transaction, TransactionInstruction } from "@solana/web3.js";
 import BN from "bn.js";
-import {LOCKUP_ACCOUNT_DATA_LAYOUT} from '../util/layout';
+import {LOCKUP_ACCOUNT_DATA_LAYOUT, LOCKUP_SCHEDULE_ACCOUNT_DATA_LAYOUT} from '../util/layout';
 
 export const redeemTokens = async (
     programIdString,
@@ -28,20 +28,25 @@ export const redeemTokens = async (
     const PDA = await PublicKey.findProgramAddress([Buffer.from("tokenDistributor")], programId);
     // 6. [] token program - TOKEN_PROGRAM_ID
     // 7. [] clock sysvar - SYSVAR_CLOCK_PUBKEY
+    // 8. [] token mint of the lockup schedule
+    const encodedLockupScheduleState = (await connection.getAccountInfo(lockupScheduleStatePubkey, 'confirmed')).data;
+    const tokenMintPubkey = new PublicKey(LOCKUP_SCHEDULE_ACCOUNT_DATA_LAYOUT.decode(encodedLockupScheduleState).tokenMint);
 
     const redeemTokensIx = new TransactionInstruction({
         programId,
         keys: [
-            { pubkey: wallet.publicKey, isSigner: true, isWritable: true },
+            { pubkey: wallet.publicKey, isSigner: true, isWritable: false },
             { pubkey: lockupScheduleStatePubkey, isSigner: false, isWritable: false },
             { pubkey: lockupStatePubkey, isSigner: false, isWritable: true },
             { pubkey: lockupTokenPubkey, isSigner: false, isWritable: true},
             { pubkey: receivingTokenPubkey, isSigner: false, isWritable: true},
             { pubkey: PDA[0], isSigner: false, isWritable: false},
             { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false},
-            { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false}
+            { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false},
+            { pubkey: tokenMintPubkey, isSigner: false, isWritable: false}
         ],
-        data: Buffer.from(Uint8Array.of(2))
+        // RedeemTokens { amount: None } redeems everything unlocked
+        data: Buffer.from(Uint8Array.of(2, 0))
     });
 
     const tx = new Transaction().add(
@@ -66,7 +71,10 @@ export const redeemTokens = async (
         receivingAccount: new PublicKey(decodedLockupState.receivingAccount).toBase58(),
         lockupTokenAccount: new PublicKey(decodedLockupState.lockupTokenAccount).toBase58(),
         tokenQuantity: new BN(decodedLockupState.tokenQuantity, 10, "le").toNumber(),
-        periodsRedeemed: new BN(decodedLockupState.periodsRedeemed, 10, "le").toNumber()
+        periodsRedeemed: new BN(decodedLockupState.periodsRedeemed, 10, "le").toNumber(),
+        frozen: decodedLockupState.frozen === 1,
+        claimDelegate: new PublicKey(decodedLockupState.claimDelegate).toBase58(),
+        redeemedAmount: new BN(decodedLockupState.redeemedAmount, 10, "le").toNumber()
     }
 
     return lockupStateObj;
@@ -0,0 +1,138 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use bpf_program_template::{
+    merkle::{self, MerkleTree},
+    state::MAX_DISTRIBUTOR_NODES,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signer};
+
+use crate::command::{self, CommandResult, Config};
+
+// one row of the input CSV: `wallet,amount`
+#[derive(Deserialize)]
+struct CsvRow {
+    wallet: String,
+    amount: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AirdropClaim {
+    pub index: u64,
+    pub wallet: String,
+    pub amount: u64,
+    // sibling hashes from the leaf up to the root, base58
+    pub proof: Vec<String>,
+}
+
+// a merkle distributor's tree as written by `build-airdrop`: what to create the distributor
+// with, and every wallet's claim
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Airdrop {
+    pub merkle_root: String,
+    pub max_num_nodes: u64,
+    pub total_amount: u64,
+    pub claims: Vec<AirdropClaim>,
+}
+
+impl Airdrop {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn claim_of(&self, wallet: &Pubkey) -> Result<&AirdropClaim, Box<dyn std::error::Error>> {
+        let wallet = wallet.to_string();
+        self.claims.iter()
+            .find(|claim| claim.wallet == wallet)
+            .ok_or_else(|| format!("{} has no claim in the airdrop", wallet).into())
+    }
+}
+
+pub fn default_output_path(csv_path: &Path) -> PathBuf {
+    csv_path.with_extension("airdrop.json")
+}
+
+pub fn parse_proof(proof: &[String]) -> Result<Vec<[u8; 32]>, Box<dyn std::error::Error>> {
+    proof.iter()
+        .map(|hash| Ok(Hash::from_str(hash).map_err(|err| format!("invalid proof hash {}: {}", hash, err))?.to_bytes()))
+        .collect()
+}
+
+// Build the merkle tree of a `wallet,amount` CSV, with claim indexes in row order, and write the
+// root and every wallet's proof as JSON.
+pub fn build_airdrop(csv_path: &Path, output: &Path) -> CommandResult {
+    let mut reader = csv::Reader::from_path(csv_path)?;
+    let mut wallets = HashSet::new();
+    let mut rows = vec![];
+    let mut total_amount: u64 = 0;
+    for (row, record) in reader.deserialize::<CsvRow>().enumerate() {
+        let record = record?;
+        let wallet = Pubkey::from_str(&record.wallet).map_err(|err| format!("row {}: invalid wallet: {}", row, err))?;
+        if record.amount == 0 {
+            return Err(format!("row {}: amount is zero", row).into());
+        }
+        if !wallets.insert(wallet) {
+            return Err(format!("row {}: wallet {} is listed twice", row, wallet).into());
+        }
+        total_amount = total_amount.checked_add(record.amount).ok_or("CSV total overflows u64")?;
+        rows.push((wallet, record.amount));
+    }
+    if rows.is_empty() {
+        return Err("CSV has no rows".into());
+    }
+    if rows.len() as u64 > MAX_DISTRIBUTOR_NODES {
+        return Err(format!("CSV has more than {} rows", MAX_DISTRIBUTOR_NODES).into());
+    }
+
+    let leaves = rows.iter().enumerate()
+        .map(|(index, (wallet, amount))| merkle::leaf_hash(index as u64, wallet, *amount))
+        .collect();
+    let tree = MerkleTree::new(leaves);
+    let claims = rows.iter().enumerate()
+        .map(|(index, (wallet, amount))| AirdropClaim {
+            index: index as u64,
+            wallet: wallet.to_string(),
+            amount: *amount,
+            proof: tree.proof(index).unwrap_or_default()
+                .iter()
+                .map(|hash| Hash::new_from_array(*hash).to_string())
+                .collect(),
+        })
+        .collect();
+    let airdrop = Airdrop {
+        merkle_root: Hash::new_from_array(tree.root()).to_string(),
+        max_num_nodes: rows.len() as u64,
+        total_amount,
+        claims,
+    };
+    fs::write(output, serde_json::to_vec_pretty(&airdrop)?)?;
+
+    println!("Merkle root: {}", airdrop.merkle_root);
+    println!("Claims: {}", airdrop.max_num_nodes);
+    println!("Total amount: {}", airdrop.total_amount);
+    println!("Proofs written to {}", output.display());
+    Ok(())
+}
+
+// check a claim's proof against a merkle root, the same way ClaimTokens does
+pub fn verify_proof(merkle_root: &Hash, index: u64, wallet: &Pubkey, amount: u64, proof: &[[u8; 32]]) -> CommandResult {
+    if !merkle::verify_proof(proof, &merkle_root.to_bytes(), merkle::leaf_hash(index, wallet, amount)) {
+        return Err(format!("proof of claim {} ({} for {}) does not match root {}", index, amount, wallet, merkle_root).into());
+    }
+    println!("Proof of claim {} ({} for {}) is valid", index, amount, wallet);
+    Ok(())
+}
+
+// claim the keypair's leaf of an airdrop written by `build-airdrop`
+pub fn claim_from_airdrop(config: &Config, distributor: &Pubkey, airdrop_path: &Path) -> CommandResult {
+    let airdrop = Airdrop::load(airdrop_path)?;
+    let claim = airdrop.claim_of(&config.signer()?.pubkey())?;
+    command::claim(config, distributor, claim.index, claim.amount, parse_proof(&claim.proof)?)
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use bpf_program_template::{
+    find_token_authority_address,
+    instruction,
+    state::{Lockup, LockupSchedule},
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    hash::hashv,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Signer,
+    system_instruction,
+    transaction::Transaction,
+};
+
+use crate::command::{CommandResult, Config};
+
+// one row of the input CSV: `wallet,amount,schedule`
+#[derive(Deserialize)]
+struct CsvRow {
+    wallet: String,
+    amount: u64,
+    schedule: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum LockStatus {
+    // not sent yet
+    Pending,
+    // transaction sent, confirmation unknown
+    Sent,
+    // lockup confirmed on-chain
+    Confirmed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JournalEntry {
+    pub row: usize,
+    pub wallet: String,
+    pub amount: u64,
+    pub schedule: String,
+    pub lockup: String,
+    pub lockup_token_account: String,
+    pub status: LockStatus,
+    pub signature: Option<String>,
+}
+
+// progress of a bulk lock run, rewritten after every state change so a crash can resume
+#[derive(Serialize, Deserialize, Default)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Journal::default());
+        }
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    // write to a temporary file and rename over the journal so it is never left half written
+    fn save(&self, path: &Path) -> CommandResult {
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    // line the journal up with the CSV: rows already journaled must match it, new rows are added as
+    // pending with their lockup and lockup token account addresses
+    fn sync<F>(&mut self, records: &[CsvRow], mut addresses: F) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: FnMut(usize, &Pubkey, u64, &Pubkey) -> Result<(Pubkey, Pubkey), Box<dyn std::error::Error>>,
+    {
+        if self.entries.len() > records.len() {
+            return Err("journal has more rows than the CSV".into());
+        }
+        for (row, record) in records.iter().enumerate() {
+            let wallet = Pubkey::from_str(&record.wallet).map_err(|err| format!("row {}: invalid wallet: {}", row, err))?;
+            let schedule = Pubkey::from_str(&record.schedule).map_err(|err| format!("row {}: invalid schedule: {}", row, err))?;
+
+            match self.entries.get(row) {
+                Some(entry) if entry.wallet != record.wallet || entry.amount != record.amount || entry.schedule != record.schedule => {
+                    return Err(format!("row {} does not match the journal", row).into());
+                }
+                Some(_) => {}
+                None => {
+                    let (lockup, lockup_token_account) = addresses(row, &wallet, record.amount, &schedule)?;
+                    self.entries.push(JournalEntry {
+                        row,
+                        wallet: record.wallet.clone(),
+                        amount: record.amount,
+                        schedule: record.schedule.clone(),
+                        lockup: lockup.to_string(),
+                        lockup_token_account: lockup_token_account.to_string(),
+                        status: LockStatus::Pending,
+                        signature: None,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // CSV and still pending totals of each schedule
+    fn schedule_totals(&self) -> Result<HashMap<&str, ScheduleTotals>, Box<dyn std::error::Error>> {
+        let mut totals: HashMap<&str, ScheduleTotals> = HashMap::new();
+        for entry in &self.entries {
+            let schedule_totals = totals.entry(&entry.schedule).or_default();
+            schedule_totals.csv_total = schedule_totals.csv_total.checked_add(entry.amount).ok_or("CSV total overflows u64")?;
+            if entry.status == LockStatus::Pending {
+                schedule_totals.pending_total += entry.amount;
+            }
+        }
+        Ok(totals)
+    }
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+struct ScheduleTotals {
+    csv_total: u64,
+    pending_total: u64,
+}
+
+impl ScheduleTotals {
+    // the whole CSV must fit the schedule, and what is left to lock what it has left unlocked
+    fn check(&self, schedule: &str, state: &LockupSchedule) -> CommandResult {
+        if self.csv_total > state.total_token_quantity {
+            return Err(format!(
+                "CSV total {} for schedule {} exceeds its total token quantity {}",
+                self.csv_total, schedule, state.total_token_quantity,
+            ).into());
+        }
+        let remaining = state.total_token_quantity.saturating_sub(state.token_quantity_locked);
+        if self.pending_total > remaining {
+            return Err(format!(
+                "{} tokens still to lock under schedule {} but only {} remain unlocked",
+                self.pending_total, schedule, remaining,
+            ).into());
+        }
+        Ok(())
+    }
+}
+
+// lockup accounts are created with seeds derived from the row, so a resumed run targets the
+// same addresses and a lockup that already landed can never be created twice
+fn row_seed(prefix: &str, row: usize, wallet: &Pubkey, amount: u64, schedule: &Pubkey) -> String {
+    let hash = hashv(&[schedule.as_ref(), wallet.as_ref(), &amount.to_le_bytes(), &(row as u64).to_le_bytes()]);
+    let hex: String = hash.to_bytes()[..12].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}{}", prefix, hex)
+}
+
+// mint, token program, lockup token account size and its rent of a schedule, fetched once per run
+#[derive(Clone, Copy)]
+struct VaultLayout {
+    mint: Pubkey,
+    token_program: Pubkey,
+    space: usize,
+    rent: u64,
+}
+
+fn vault_layout(
+    config: &Config,
+    layouts: &mut HashMap<Pubkey, VaultLayout>,
+    schedule: &Pubkey,
+) -> Result<VaultLayout, Box<dyn std::error::Error>> {
+    if let Some(layout) = layouts.get(schedule) {
+        return Ok(*layout);
+    }
+    let state: LockupSchedule = config.get_state(schedule)?;
+    let (token_program, space) = config.token_account_layout(&state.token_mint)?;
+    let rent = config.rpc_client.get_minimum_balance_for_rent_exemption(space)?;
+    let layout = VaultLayout { mint: state.token_mint, token_program, space, rent };
+    layouts.insert(*schedule, layout);
+    Ok(layout)
+}
+
+pub fn default_journal_path(csv_path: &Path) -> PathBuf {
+    csv_path.with_extension("journal.json")
+}
+
+pub fn bulk_lock(config: &Config, csv_path: &Path, journal_path: &Path, source: &Pubkey) -> CommandResult {
+    let signer = config.signer()?;
+    let base = signer.pubkey();
+    let mut layouts = HashMap::new();
+
+    let mut journal = Journal::load(journal_path)?;
+    let mut reader = csv::Reader::from_path(csv_path)?;
+    let records = reader.deserialize::<CsvRow>().collect::<Result<Vec<_>, _>>()?;
+    journal.sync(&records, |row, wallet, amount, schedule| {
+        let lockup_seed = row_seed("lockup-", row, wallet, amount, schedule);
+        let token_seed = row_seed("vault-", row, wallet, amount, schedule);
+        let layout = vault_layout(config, &mut layouts, schedule)?;
+        Ok((
+            Pubkey::create_with_seed(&base, &lockup_seed, &config.program_id)?,
+            Pubkey::create_with_seed(&base, &token_seed, &layout.token_program)?,
+        ))
+    }).map_err(|err| format!("{} (journal {})", err, journal_path.display()))?;
+    journal.save(journal_path)?;
+
+    // a sent transaction may have landed before a crash - the chain decides. An RPC error is no
+    // answer, so the run stops rather than risk sending the row again
+    for entry in journal.entries.iter_mut().filter(|entry| entry.status == LockStatus::Sent) {
+        let lockup = Pubkey::from_str(&entry.lockup)?;
+        let account = config.rpc_client
+            .get_account_with_commitment(&lockup, config.rpc_client.commitment())
+            .map_err(|err| format!("row {}: failed to check lockup {}: {}", entry.row, lockup, err))?
+            .value;
+        let landed = account.is_some_and(|account| Lockup::unpack(&account.data).is_ok());
+        entry.status = if landed { LockStatus::Confirmed } else { LockStatus::Pending };
+    }
+    journal.save(journal_path)?;
+
+    // check every schedule has room for the rows still to lock before sending anything
+    for (schedule, totals) in &journal.schedule_totals()? {
+        let state: LockupSchedule = config.get_state(&Pubkey::from_str(schedule)?)?;
+        totals.check(schedule, &state)?;
+    }
+
+    let lockup_rent = config.rpc_client.get_minimum_balance_for_rent_exemption(Lockup::LEN)?;
+    let mut confirmed = 0;
+    for i in 0..journal.entries.len() {
+        if journal.entries[i].status == LockStatus::Confirmed {
+            confirmed += 1;
+            continue;
+        }
+        let entry = journal.entries[i].clone();
+        let wallet = Pubkey::from_str(&entry.wallet)?;
+        let schedule = Pubkey::from_str(&entry.schedule)?;
+        let lockup = Pubkey::from_str(&entry.lockup)?;
+        let lockup_token_account = Pubkey::from_str(&entry.lockup_token_account)?;
+        let lockup_seed = row_seed("lockup-", entry.row, &wallet, entry.amount, &schedule);
+        let token_seed = row_seed("vault-", entry.row, &wallet, entry.amount, &schedule);
+        let (token_authority, _bump_seed) = find_token_authority_address(&config.program_id, &lockup);
+
+        let layout = vault_layout(config, &mut layouts, &schedule)?;
+        let instructions = [
+            system_instruction::create_account_with_seed(
+                &base,
+                &lockup_token_account,
+                &base,
+                &token_seed,
+                layout.rent,
+                layout.space as u64,
+                &layout.token_program,
+            ),
+            spl_token_2022::instruction::initialize_account3(&layout.token_program, &lockup_token_account, &layout.mint, &token_authority)?,
+            system_instruction::create_account_with_seed(
+                &base,
+                &lockup,
+                &base,
+                &lockup_seed,
+                lockup_rent,
+                Lockup::LEN as u64,
+                &config.program_id,
+            ),
+            instruction::lock_tokens(
+                &config.program_id,
+                &base,
+                &schedule,
+                &lockup,
+                &wallet,
+                &lockup_token_account,
+                &layout.token_program,
+                source,
+                &layout.mint,
+                entry.amount,
+            )?,
+        ];
+        let blockhash = config.rpc_client.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(&instructions, Some(&base), &[signer], blockhash);
+
+        // record the signature before sending so a crash mid-flight is reconciled on resume
+        journal.entries[i].status = LockStatus::Sent;
+        journal.entries[i].signature = Some(transaction.signatures[0].to_string());
+        journal.save(journal_path)?;
+
+        config.rpc_client.send_and_confirm_transaction(&transaction)
+            .map_err(|err| format!("row {}: {} (rerun to resume)", entry.row, err))?;
+        journal.entries[i].status = LockStatus::Confirmed;
+        journal.save(journal_path)?;
+        confirmed += 1;
+        println!("Row {}: locked {} for {} in {}", entry.row, entry.amount, entry.wallet, entry.lockup);
+    }
+
+    println!("{} of {} lockups confirmed, journal at {}", confirmed, journal.entries.len(), journal_path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::MAX_SEED_LEN;
+
+    fn record(wallet: &Pubkey, amount: u64, schedule: &Pubkey) -> CsvRow {
+        CsvRow { wallet: wallet.to_string(), amount, schedule: schedule.to_string() }
+    }
+
+    fn new_addresses(row: usize, _wallet: &Pubkey, _amount: u64, _schedule: &Pubkey) -> Result<(Pubkey, Pubkey), Box<dyn std::error::Error>> {
+        Ok((Pubkey::new_from_array([row as u8; 32]), Pubkey::new_from_array([row as u8 + 100; 32])))
+    }
+
+    fn schedule_state(total_token_quantity: u64, token_quantity_locked: u64) -> LockupSchedule {
+        let mut state = LockupSchedule::unpack_unchecked(&[0; LockupSchedule::LEN]).unwrap();
+        state.total_token_quantity = total_token_quantity;
+        state.token_quantity_locked = token_quantity_locked;
+        state
+    }
+
+    #[test]
+    fn test_row_seed() {
+        let wallet = Pubkey::new_unique();
+        let schedule = Pubkey::new_unique();
+        let seed = row_seed("lockup-", 3, &wallet, 1_000, &schedule);
+        assert_eq!(seed, row_seed("lockup-", 3, &wallet, 1_000, &schedule));
+        assert!(seed.starts_with("lockup-"));
+        assert!(seed.len() <= MAX_SEED_LEN);
+        // the same wallet and amount on another row is another lockup
+        assert_ne!(seed, row_seed("lockup-", 4, &wallet, 1_000, &schedule));
+        assert_ne!(seed, row_seed("lockup-", 3, &wallet, 1_001, &schedule));
+    }
+
+    #[test]
+    fn test_journal_resume() {
+        let schedule = Pubkey::new_unique();
+        let wallets = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let records: Vec<CsvRow> = wallets.iter().map(|wallet| record(wallet, 1_000, &schedule)).collect();
+
+        let mut journal = Journal::default();
+        journal.sync(&records[..2], new_addresses).unwrap();
+        journal.entries[0].status = LockStatus::Confirmed;
+        journal.entries[1].status = LockStatus::Sent;
+        let path = std::env::temp_dir().join(format!("bulk-lock-{}.journal.json", Pubkey::new_unique()));
+        journal.save(&path).unwrap();
+
+        // rows already journaled keep their addresses and progress, only the new row is added
+        let mut journal = Journal::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        journal.sync(&records, |row, wallet, amount, schedule| {
+            assert_eq!(row, 2);
+            new_addresses(row, wallet, amount, schedule)
+        }).unwrap();
+        let statuses: Vec<LockStatus> = journal.entries.iter().map(|entry| entry.status).collect();
+        assert_eq!(statuses, [LockStatus::Confirmed, LockStatus::Sent, LockStatus::Pending]);
+        assert_eq!(journal.entries[2].lockup, Pubkey::new_from_array([2; 32]).to_string());
+        assert_eq!(journal.entries[2].wallet, wallets[2].to_string());
+
+        // a missing journal starts a new run
+        assert!(Journal::load(&path).unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn test_journal_csv_mismatch() {
+        let schedule = Pubkey::new_unique();
+        let wallet = Pubkey::new_unique();
+        let records = [record(&wallet, 1_000, &schedule), record(&Pubkey::new_unique(), 2_000, &schedule)];
+        let mut journal = Journal::default();
+        journal.sync(&records, new_addresses).unwrap();
+
+        // an edited row
+        let edited = [record(&wallet, 1_500, &schedule), record(&Pubkey::new_unique(), 2_000, &schedule)];
+        let err = journal.sync(&edited, new_addresses).unwrap_err();
+        assert_eq!(err.to_string(), "row 0 does not match the journal");
+
+        // a removed row
+        let err = journal.sync(&records[..1], new_addresses).unwrap_err();
+        assert_eq!(err.to_string(), "journal has more rows than the CSV");
+
+        // a bad address
+        let invalid = [CsvRow { wallet: "not a wallet".to_string(), amount: 1, schedule: schedule.to_string() }];
+        assert!(Journal::default().sync(&invalid, new_addresses).unwrap_err().to_string().starts_with("row 0: invalid wallet"));
+    }
+
+    #[test]
+    fn test_schedule_totals() {
+        let schedule = Pubkey::new_unique();
+        let other_schedule = Pubkey::new_unique();
+        let records = [
+            record(&Pubkey::new_unique(), 1_000, &schedule),
+            record(&Pubkey::new_unique(), 2_000, &schedule),
+            record(&Pubkey::new_unique(), 500, &other_schedule),
+        ];
+        let mut journal = Journal::default();
+        journal.sync(&records, new_addresses).unwrap();
+        journal.entries[0].status = LockStatus::Confirmed;
+
+        // confirmed rows count towards the CSV total but are no longer pending
+        let totals = journal.schedule_totals().unwrap();
+        let schedule_key = schedule.to_string();
+        let other_schedule_key = other_schedule.to_string();
+        assert_eq!(totals[schedule_key.as_str()], ScheduleTotals { csv_total: 3_000, pending_total: 2_000 });
+        assert_eq!(totals[other_schedule_key.as_str()], ScheduleTotals { csv_total: 500, pending_total: 500 });
+
+        let totals = &totals[schedule_key.as_str()];
+        assert!(totals.check(&schedule_key, &schedule_state(3_000, 1_000)).is_ok());
+        // the CSV is larger than the schedule
+        let err = totals.check(&schedule_key, &schedule_state(2_999, 0)).unwrap_err();
+        assert!(err.to_string().starts_with("CSV total 3000"));
+        // other locks left too little for the pending rows
+        let err = totals.check(&schedule_key, &schedule_state(3_000, 1_001)).unwrap_err();
+        assert!(err.to_string().starts_with("2000 tokens still to lock"));
+
+        journal.entries[1].amount = u64::MAX;
+        assert!(journal.schedule_totals().is_err());
+    }
+}
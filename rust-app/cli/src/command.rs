@@ -0,0 +1,605 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use bpf_program_template::{
+    find_token_authority_address,
+    find_sol_vault_address,
+    instruction,
+    state::{AccountType, CurvePoint, CurveType, Lockup, LockupSchedule, LockupV0, MerkleDistributor, PeriodType, TimeUnit, VestingCurve},
+};
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    account::from_account,
+    clock::Clock,
+    instruction::Instruction,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    sysvar,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::{Account as TokenAccount, Mint},
+};
+use token_distributor_decoder::{curve_type_name, decode_account, detect_account_type, period_type_name, time_unit_name, unlock_calendar};
+
+pub type CommandResult = Result<(), Box<dyn std::error::Error>>;
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum ScheduleTimeUnit {
+    UnixTimestamp,
+    Slot,
+    Epoch,
+}
+
+impl From<ScheduleTimeUnit> for TimeUnit {
+    fn from(time_unit: ScheduleTimeUnit) -> Self {
+        match time_unit {
+            ScheduleTimeUnit::UnixTimestamp => TimeUnit::UnixTimestamp,
+            ScheduleTimeUnit::Slot => TimeUnit::Slot,
+            ScheduleTimeUnit::Epoch => TimeUnit::Epoch,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum SchedulePeriodType {
+    Fixed,
+    CalendarMonth,
+    CalendarQuarter,
+}
+
+impl From<SchedulePeriodType> for PeriodType {
+    fn from(period_type: SchedulePeriodType) -> Self {
+        match period_type {
+            SchedulePeriodType::Fixed => PeriodType::Fixed,
+            SchedulePeriodType::CalendarMonth => PeriodType::CalendarMonth,
+            SchedulePeriodType::CalendarQuarter => PeriodType::CalendarQuarter,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum ScheduleCurveType {
+    Linear,
+    Steps,
+    PiecewiseLinear,
+}
+
+impl From<ScheduleCurveType> for CurveType {
+    fn from(curve_type: ScheduleCurveType) -> Self {
+        match curve_type {
+            ScheduleCurveType::Linear => CurveType::Linear,
+            ScheduleCurveType::Steps => CurveType::Steps,
+            ScheduleCurveType::PiecewiseLinear => CurveType::PiecewiseLinear,
+        }
+    }
+}
+
+// parse a PERIOD:BASIS_POINTS curve point
+pub fn parse_curve_point(point: &str) -> Result<CurvePoint, String> {
+    let (period, basis_points) = point.split_once(':').ok_or("expected PERIOD:BASIS_POINTS")?;
+    Ok(CurvePoint {
+        period: period.parse().map_err(|err| format!("invalid period: {}", err))?,
+        basis_points: basis_points.parse().map_err(|err| format!("invalid basis points: {}", err))?,
+    })
+}
+
+// byte offset of `Lockup.lockup_schedule_state` (after the type, version and is_initialized bytes)
+const LOCKUP_SCHEDULE_OFFSET: usize = 3;
+
+pub struct Config {
+    pub rpc_client: RpcClient,
+    pub signer: Option<Keypair>,
+    pub keypair_path: String,
+    pub program_id: Pubkey,
+}
+
+impl Config {
+    pub fn signer(&self) -> Result<&Keypair, Box<dyn std::error::Error>> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| format!("failed to read keypair {}", self.keypair_path).into())
+    }
+
+    // sign with the config keypair (fee payer) plus any new accounts, then send and confirm
+    fn send(&self, instructions: &[Instruction], extra_signers: &[&Keypair]) -> CommandResult {
+        let signer = self.signer()?;
+        let mut signers = vec![signer];
+        signers.extend_from_slice(extra_signers);
+        let blockhash = self.rpc_client.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &signers,
+            blockhash,
+        );
+        let signature = self.rpc_client.send_and_confirm_transaction(&transaction)?;
+        println!("Signature: {}", signature);
+        Ok(())
+    }
+
+    // system instruction creating a rent-exempt account of `space` bytes owned by `owner`
+    fn create_account_ix(&self, account: &Pubkey, space: usize, owner: &Pubkey) -> Result<Instruction, Box<dyn std::error::Error>> {
+        let lamports = self.rpc_client.get_minimum_balance_for_rent_exemption(space)?;
+        Ok(system_instruction::create_account(&self.signer()?.pubkey(), account, lamports, space as u64, owner))
+    }
+
+    // the cluster's Clock sysvar
+    pub fn clock(&self) -> Result<Clock, Box<dyn std::error::Error>> {
+        let account = self.rpc_client.get_account(&sysvar::clock::id())?;
+        Ok(from_account(&account).ok_or("failed to decode the Clock sysvar")?)
+    }
+
+    // token program owning the mint (spl-token or Token-2022) and the size of a token account for
+    // it, with room for any extensions the mint requires, such as withheld transfer fees
+    pub fn token_account_layout(&self, mint: &Pubkey) -> Result<(Pubkey, usize), Box<dyn std::error::Error>> {
+        let account = self.rpc_client.get_account(mint)?;
+        spl_token_2022::check_spl_token_program_account(&account.owner)?;
+        let mint_state = StateWithExtensions::<Mint>::unpack(&account.data)?;
+        let extensions = ExtensionType::get_required_init_account_extensions(&mint_state.get_extension_types()?);
+        Ok((account.owner, ExtensionType::try_calculate_account_len::<TokenAccount>(&extensions)?))
+    }
+
+    pub fn get_state<T: Pack + IsInitialized>(&self, address: &Pubkey) -> Result<T, Box<dyn std::error::Error>> {
+        let account = self.rpc_client.get_account(address)?;
+        if account.owner != self.program_id {
+            return Err(format!("{} is not owned by program {}", address, self.program_id).into());
+        }
+        Ok(T::unpack(&account.data)?)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_schedule(
+    config: &Config,
+    mint: &Pubkey,
+    start_timestamp: u64,
+    periods: u64,
+    period_duration: u64,
+    total_quantity: u64,
+    time_unit: TimeUnit,
+    period_type: PeriodType,
+    curve: VestingCurve,
+) -> CommandResult {
+    let schedule = Keypair::new();
+    let instructions = [
+        config.create_account_ix(&schedule.pubkey(), LockupSchedule::LEN, &config.program_id)?,
+        instruction::create_lockup_schedule(
+            &config.program_id,
+            &config.signer()?.pubkey(),
+            &schedule.pubkey(),
+            mint,
+            start_timestamp,
+            periods,
+            period_duration,
+            total_quantity,
+            time_unit,
+            period_type,
+            curve,
+        )?,
+    ];
+    config.send(&instructions, &[&schedule])?;
+    println!("Lockup schedule: {}", schedule.pubkey());
+    Ok(())
+}
+
+pub fn lock(config: &Config, schedule: &Pubkey, receiver: &Pubkey, source: &Pubkey, amount: u64) -> CommandResult {
+    let schedule_state: LockupSchedule = config.get_state(schedule)?;
+    let (token_program, token_account_len) = config.token_account_layout(&schedule_state.token_mint)?;
+    let lockup = Keypair::new();
+    let lockup_token_account = Keypair::new();
+    let (token_authority, _bump_seed) = find_token_authority_address(&config.program_id, &lockup.pubkey());
+    let owner = config.signer()?.pubkey();
+
+    // an empty token account owned by the lockup's PDA, which the lock instruction funds from `source`
+    let instructions = [
+        config.create_account_ix(&lockup_token_account.pubkey(), token_account_len, &token_program)?,
+        spl_token_2022::instruction::initialize_account3(
+            &token_program,
+            &lockup_token_account.pubkey(),
+            &schedule_state.token_mint,
+            &token_authority,
+        )?,
+        config.create_account_ix(&lockup.pubkey(), Lockup::LEN, &config.program_id)?,
+        instruction::lock_tokens(
+            &config.program_id,
+            &owner,
+            schedule,
+            &lockup.pubkey(),
+            receiver,
+            &lockup_token_account.pubkey(),
+            &token_program,
+            source,
+            &schedule_state.token_mint,
+            amount,
+        )?,
+    ];
+    config.send(&instructions, &[&lockup_token_account, &lockup])?;
+    println!("Lockup: {}", lockup.pubkey());
+    println!("Lockup token account: {}", lockup_token_account.pubkey());
+    Ok(())
+}
+
+pub fn redeem(config: &Config, lockup: &Pubkey, destination: &Pubkey, amount: Option<u64>) -> CommandResult {
+    let lockup_state: Lockup = config.get_state(lockup)?;
+    let schedule_state: LockupSchedule = config.get_state(&lockup_state.lockup_schedule_state)?;
+    let (token_program, _) = config.token_account_layout(&schedule_state.token_mint)?;
+    // version 0 lockups kept their tokens in accounts owned by the PDA they all shared
+    let lockup_token_account = config.rpc_client.get_account(&lockup_state.lockup_token_account)?;
+    let lockup_token_authority = StateWithExtensions::<TokenAccount>::unpack(&lockup_token_account.data)?.base.owner;
+    let ix = instruction::redeem_tokens(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        &lockup_state.lockup_schedule_state,
+        lockup,
+        &lockup_state.lockup_token_account,
+        &lockup_token_authority,
+        destination,
+        &token_program,
+        &schedule_state.token_mint,
+        amount,
+    )?;
+    config.send(&[ix], &[])
+}
+
+// lock lamports of the keypair for a receiver under a schedule of the native mint
+pub fn lock_sol(config: &Config, schedule: &Pubkey, receiver: &Pubkey, lamports: u64) -> CommandResult {
+    let lockup = Keypair::new();
+
+    // the lock instruction creates the SOL vault of the lockup itself
+    let instructions = [
+        config.create_account_ix(&lockup.pubkey(), Lockup::LEN, &config.program_id)?,
+        instruction::lock_lamports(
+            &config.program_id,
+            &config.signer()?.pubkey(),
+            schedule,
+            &lockup.pubkey(),
+            receiver,
+            lamports,
+        )?,
+    ];
+    config.send(&instructions, &[&lockup])?;
+    println!("Lockup: {}", lockup.pubkey());
+    println!("SOL vault: {}", find_sol_vault_address(&config.program_id, &lockup.pubkey()).0);
+    Ok(())
+}
+
+// redeem a native SOL lockup as lamports, or as wrapped SOL to a native token account
+pub fn redeem_sol(config: &Config, lockup: &Pubkey, destination: &Pubkey, wrap: bool, amount: Option<u64>) -> CommandResult {
+    let lockup_state: Lockup = config.get_state(lockup)?;
+    let token_program = if wrap {
+        Some(config.rpc_client.get_account(destination)?.owner)
+    } else {
+        None
+    };
+    let ix = instruction::redeem_lamports(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        &lockup_state.lockup_schedule_state,
+        lockup,
+        destination,
+        token_program.as_ref(),
+        amount,
+    )?;
+    config.send(&[ix], &[])
+}
+
+pub fn create_distributor(
+    config: &Config,
+    schedule: &Pubkey,
+    merkle_root: [u8; 32],
+    max_num_nodes: u64,
+    source: &Pubkey,
+    amount: u64,
+) -> CommandResult {
+    let schedule_state: LockupSchedule = config.get_state(schedule)?;
+    let (token_program, token_account_len) = config.token_account_layout(&schedule_state.token_mint)?;
+    let distributor = Keypair::new();
+    let distributor_token_account = Keypair::new();
+    let (token_authority, _bump_seed) = find_token_authority_address(&config.program_id, &distributor.pubkey());
+
+    let instructions = [
+        config.create_account_ix(&distributor_token_account.pubkey(), token_account_len, &token_program)?,
+        spl_token_2022::instruction::initialize_account3(
+            &token_program,
+            &distributor_token_account.pubkey(),
+            &schedule_state.token_mint,
+            &token_authority,
+        )?,
+        config.create_account_ix(&distributor.pubkey(), MerkleDistributor::space(max_num_nodes), &config.program_id)?,
+        instruction::create_merkle_distributor(
+            &config.program_id,
+            &config.signer()?.pubkey(),
+            schedule,
+            &distributor.pubkey(),
+            &distributor_token_account.pubkey(),
+            &token_program,
+            source,
+            &schedule_state.token_mint,
+            merkle_root,
+            max_num_nodes,
+            amount,
+        )?,
+    ];
+    config.send(&instructions, &[&distributor_token_account, &distributor])?;
+    println!("Merkle distributor: {}", distributor.pubkey());
+    println!("Distributor token account: {}", distributor_token_account.pubkey());
+    Ok(())
+}
+
+pub fn claim(config: &Config, distributor: &Pubkey, index: u64, amount: u64, proof: Vec<[u8; 32]>) -> CommandResult {
+    let account = config.rpc_client.get_account(distributor)?;
+    if account.owner != config.program_id {
+        return Err(format!("{} is not owned by program {}", distributor, config.program_id).into());
+    }
+    let header = account.data.get(..MerkleDistributor::LEN).ok_or("account is too small for a merkle distributor")?;
+    let distributor_state = MerkleDistributor::unpack(header)?;
+    let schedule_state: LockupSchedule = config.get_state(&distributor_state.lockup_schedule_state)?;
+    let (token_program, token_account_len) = config.token_account_layout(&schedule_state.token_mint)?;
+    let lockup = Keypair::new();
+    let lockup_token_account = Keypair::new();
+    let (token_authority, _bump_seed) = find_token_authority_address(&config.program_id, &lockup.pubkey());
+
+    let instructions = [
+        config.create_account_ix(&lockup_token_account.pubkey(), token_account_len, &token_program)?,
+        spl_token_2022::instruction::initialize_account3(
+            &token_program,
+            &lockup_token_account.pubkey(),
+            &schedule_state.token_mint,
+            &token_authority,
+        )?,
+        config.create_account_ix(&lockup.pubkey(), Lockup::LEN, &config.program_id)?,
+        instruction::claim_tokens(
+            &config.program_id,
+            &config.signer()?.pubkey(),
+            distributor,
+            &distributor_state.lockup_schedule_state,
+            &lockup.pubkey(),
+            &lockup_token_account.pubkey(),
+            &distributor_state.distributor_token_account,
+            &token_program,
+            &schedule_state.token_mint,
+            index,
+            amount,
+            proof,
+        )?,
+    ];
+    config.send(&instructions, &[&lockup_token_account, &lockup])?;
+    println!("Lockup: {}", lockup.pubkey());
+    println!("Lockup token account: {}", lockup_token_account.pubkey());
+    Ok(())
+}
+
+pub fn migrate(config: &Config, address: &Pubkey) -> CommandResult {
+    let data = config.rpc_client.get_account_data(address)?;
+    let ix = instruction::migrate_account(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        address,
+        migrated_lockup_schedule(&data)?.as_ref(),
+    )?;
+    config.send(&[ix], &[])
+}
+
+// the schedule a version 0 lockup vests under, which migrating it reads - None for other accounts
+fn migrated_lockup_schedule(data: &[u8]) -> Result<Option<Pubkey>, Box<dyn std::error::Error>> {
+    // only version 0 lockups are migrated, anything else is already current or not a lockup
+    if data.len() != LockupV0::LEN {
+        return Ok(None);
+    }
+    let lockup = LockupV0::unpack(data)?;
+    Ok(Some(lockup.lockup_schedule_state).filter(|_| lockup.is_initialized))
+}
+
+pub fn set_pause_authority(config: &Config, schedule: &Pubkey, pause_authority: Option<&Pubkey>) -> CommandResult {
+    let ix = instruction::set_pause_authority(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        schedule,
+        &pause_authority.copied().unwrap_or_default(),
+    )?;
+    config.send(&[ix], &[])
+}
+
+pub fn set_paused(config: &Config, schedule: &Pubkey, paused: bool) -> CommandResult {
+    let ix = instruction::set_paused(&config.program_id, &config.signer()?.pubkey(), schedule, paused)?;
+    config.send(&[ix], &[])?;
+    println!("Lockup schedule {} {}", schedule, if paused { "paused" } else { "resumed" });
+    Ok(())
+}
+
+pub fn set_compliance_authority(config: &Config, schedule: &Pubkey, compliance_authority: Option<&Pubkey>) -> CommandResult {
+    let ix = instruction::set_compliance_authority(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        schedule,
+        &compliance_authority.copied().unwrap_or_default(),
+    )?;
+    config.send(&[ix], &[])
+}
+
+pub fn set_frozen(config: &Config, lockup: &Pubkey, frozen: bool) -> CommandResult {
+    let lockup_state: Lockup = config.get_state(lockup)?;
+    let ix = instruction::set_frozen(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        &lockup_state.lockup_schedule_state,
+        lockup,
+        frozen,
+    )?;
+    config.send(&[ix], &[])?;
+    println!("Lockup {} {}", lockup, if frozen { "frozen" } else { "thawed" });
+    Ok(())
+}
+
+pub fn set_claim_delegate(config: &Config, lockup: &Pubkey, claim_delegate: Option<&Pubkey>, delegate_destination: Option<&Pubkey>) -> CommandResult {
+    let ix = instruction::set_claim_delegate(
+        &config.program_id,
+        &config.signer()?.pubkey(),
+        lockup,
+        &claim_delegate.copied().unwrap_or_default(),
+        &delegate_destination.copied().unwrap_or_default(),
+    )?;
+    config.send(&[ix], &[])
+}
+
+pub fn show_schedule(config: &Config, address: &Pubkey) -> CommandResult {
+    let state: LockupSchedule = config.get_state(address)?;
+    println!("Lockup schedule: {}", address);
+    println!("Initializer: {}", state.initializer);
+    println!("Token mint: {}", state.token_mint);
+    println!("Time unit: {}", time_unit_name(state.time_unit));
+    println!("Start timestamp: {}", state.start_timestamp);
+    println!("Number of periods: {}", state.number_periods);
+    println!("Period type: {}", period_type_name(state.period_type));
+    println!("Period duration: {}", state.period_duration);
+    println!("Curve: {}", curve_type_name(state.curve_type));
+    for point in state.curve_points() {
+        println!("  period {}: {} bps", point.period, point.basis_points);
+    }
+    println!("Total token quantity: {}", state.total_token_quantity);
+    println!("Token quantity locked: {}", state.token_quantity_locked);
+    if state.pause_authority != Pubkey::default() {
+        println!("Pause authority: {}", state.pause_authority);
+    }
+    println!("Paused: {}", state.paused);
+    if state.compliance_authority != Pubkey::default() {
+        println!("Compliance authority: {}", state.compliance_authority);
+    }
+    Ok(())
+}
+
+pub fn show_lockup(config: &Config, address: &Pubkey) -> CommandResult {
+    let state: Lockup = config.get_state(address)?;
+    println!("Lockup: {}", address);
+    println!("Lockup schedule: {}", state.lockup_schedule_state);
+    println!("Receiving account: {}", state.receiving_account);
+    println!("Lockup token account: {}", state.lockup_token_account);
+    println!("Token quantity: {}", state.token_quantity);
+    println!("Periods redeemed: {}", state.periods_redeemed);
+    println!("Amount redeemed: {}", state.redeemed_amount);
+    println!("Frozen: {}", state.frozen);
+    if state.claim_delegate != Pubkey::default() {
+        println!("Claim delegate: {}", state.claim_delegate);
+    }
+    if state.delegate_destination != Pubkey::default() {
+        println!("Delegate destination: {}", state.delegate_destination);
+    }
+    Ok(())
+}
+
+pub fn decode(config: &Config, address: &Pubkey) -> CommandResult {
+    let account = config.rpc_client.get_account(address)?;
+    if account.owner != config.program_id {
+        return Err(format!("{} is not owned by program {}", address, config.program_id).into());
+    }
+    // a lockup's vesting amounts come from its schedule, a schedule's from its lockups
+    let (schedule_data, lockups) = match detect_account_type(&account.data)? {
+        AccountType::Lockup => {
+            let lockup = Lockup::unpack(&account.data)?;
+            (Some(config.rpc_client.get_account(&lockup.lockup_schedule_state)?.data), vec![])
+        }
+        AccountType::LockupSchedule => {
+            (None, get_lockups(config, Some(address))?.into_iter().map(|(_, state)| state).collect())
+        }
+        _ => (None, vec![]),
+    };
+    let decoded = decode_account(&account.data, schedule_data.as_deref(), &lockups, &config.clock()?)?;
+    println!("{}", decoded.to_json());
+    Ok(())
+}
+
+// every lockup account of the program, optionally only those under one schedule
+fn get_lockups(config: &Config, schedule: Option<&Pubkey>) -> Result<Vec<(Pubkey, Lockup)>, Box<dyn std::error::Error>> {
+    let program_accounts = |filters| -> Result<_, Box<dyn std::error::Error>> {
+        Ok(config.rpc_client.get_program_accounts_with_config(
+            &config.program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(filters),
+                account_config: RpcAccountInfoConfig::default(),
+                ..RpcProgramAccountsConfig::default()
+            },
+        )?)
+    };
+
+    // current lockups start with their tag, whatever their length - any that don't decode are counted
+    let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &[AccountType::Lockup as u8]))];
+    if let Some(schedule) = schedule {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(LOCKUP_SCHEDULE_OFFSET, schedule.as_ref())));
+    }
+    let mut lockups = vec![];
+    let mut undecodable = 0;
+    for (address, account) in program_accounts(filters)? {
+        match Lockup::unpack(&account.data) {
+            Ok(state) => lockups.push((address, state)),
+            Err(_) => undecodable += 1,
+        }
+    }
+
+    // version 0 lockups are untagged, with the schedule right after is_initialized
+    let mut filters = vec![RpcFilterType::DataSize(LockupV0::LEN as u64)];
+    if let Some(schedule) = schedule {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(1, schedule.as_ref())));
+    }
+    let unmigrated = program_accounts(filters)?
+        .iter()
+        .filter(|(_, account)| LockupV0::unpack(&account.data).is_ok_and(|state| state.is_initialized))
+        .count();
+
+    if unmigrated > 0 {
+        eprintln!("warning: skipped {} lockups still in the version 0 layout, run `migrate` on them", unmigrated);
+    }
+    if undecodable > 0 {
+        eprintln!("warning: skipped {} lockup accounts that failed to decode", undecodable);
+    }
+    Ok(lockups)
+}
+
+pub fn list_lockups(config: &Config, schedule: Option<&Pubkey>) -> CommandResult {
+    println!("{:<44}  {:<44}  {:>20}  {:>20}", "Lockup", "Receiving account", "Token quantity", "Redeemed");
+    for (address, state) in get_lockups(config, schedule)? {
+        println!(
+            "{:<44}  {:<44}  {:>20}  {:>20}",
+            address, state.receiving_account, state.token_quantity, state.redeemed_amount,
+        );
+    }
+    Ok(())
+}
+
+pub fn forecast(config: &Config, schedule: &Pubkey, format: ReportFormat, output: Option<&Path>) -> CommandResult {
+    let schedule_state: LockupSchedule = config.get_state(schedule)?;
+    let lockups: Vec<Lockup> = get_lockups(config, Some(schedule))?.into_iter().map(|(_, state)| state).collect();
+    let rows = unlock_calendar(&schedule_state, &lockups)?;
+
+    let report = match format {
+        ReportFormat::Json => serde_json::to_vec_pretty(&rows)?,
+        ReportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for row in &rows {
+                writer.serialize(row)?;
+            }
+            writer.into_inner()?
+        }
+    };
+    match output {
+        Some(path) => fs::write(path, report)?,
+        None => io::stdout().write_all(&report)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,353 @@
+mod airdrop;
+mod bulk;
+mod command;
+
+use std::path::PathBuf;
+
+use bpf_program_template::state::{CurvePoint, VestingCurve};
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::read_keypair_file,
+};
+
+use crate::command::{Config, ReportFormat, ScheduleCurveType, SchedulePeriodType, ScheduleTimeUnit};
+
+#[derive(Parser)]
+#[command(name = "token-distributor", version, about = "Create and operate token distribution lockups")]
+struct Cli {
+    /// JSON RPC URL of the cluster
+    #[arg(long, short = 'u', default_value = "http://localhost:8899")]
+    url: String,
+
+    /// Keypair file that signs and pays for transactions
+    #[arg(long, short = 'k', default_value = "~/.config/solana/id.json")]
+    keypair: String,
+
+    /// Address of the deployed token distributor program
+    #[arg(long, env = "TOKEN_DISTRIBUTOR_PROGRAM_ID")]
+    program_id: Pubkey,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a lockup schedule for a token mint, signed by the keypair as initializer
+    CreateSchedule {
+        /// Mint of the tokens locked under the schedule
+        #[arg(long)]
+        mint: Pubkey,
+        /// Time the first period starts counting from, in the time unit
+        #[arg(long)]
+        start_timestamp: u64,
+        /// Number of unlock periods
+        #[arg(long)]
+        periods: u64,
+        /// Length of each period in the time unit, or in months or quarters for calendar periods
+        #[arg(long)]
+        period_duration: u64,
+        /// Total number of tokens to be locked under the schedule
+        #[arg(long)]
+        total_quantity: u64,
+        /// Clock field the start and period duration are measured in
+        #[arg(long, value_enum, default_value = "unix-timestamp")]
+        time_unit: ScheduleTimeUnit,
+        /// Fixed periods, or periods ending on the UTC month or quarter anniversaries of the start
+        #[arg(long, value_enum, default_value = "fixed")]
+        period_type: SchedulePeriodType,
+        /// How much vests as each period unlocks
+        #[arg(long, value_enum, default_value = "linear")]
+        curve: ScheduleCurveType,
+        /// Point of a steps or piecewise-linear curve as PERIOD:BASIS_POINTS vested in total by then,
+        /// repeated in period order and ending at the last period with 10000
+        #[arg(long = "curve-point", value_parser = command::parse_curve_point)]
+        curve_points: Vec<CurvePoint>,
+    },
+    /// Lock tokens from a token account owned by the keypair for a receiver
+    Lock {
+        /// Lockup schedule the tokens are locked under
+        #[arg(long)]
+        schedule: Pubkey,
+        /// Main account of the receiver allowed to redeem the tokens
+        #[arg(long)]
+        receiver: Pubkey,
+        /// Token account the tokens are taken from
+        #[arg(long)]
+        source: Pubkey,
+        /// Number of tokens to lock, in base units
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Lock tokens for every `wallet,amount,schedule` row of a CSV, resuming from a journal
+    BulkLock {
+        /// CSV file with a `wallet,amount,schedule` header
+        csv: PathBuf,
+        /// Token account owned by the keypair the tokens are taken from
+        #[arg(long)]
+        source: Pubkey,
+        /// Progress journal, defaults to the CSV path with a `.journal.json` extension
+        #[arg(long)]
+        journal: Option<PathBuf>,
+    },
+    /// Redeem unlocked tokens of a lockup, signed by the keypair as receiver or its claim delegate
+    Redeem {
+        /// Lockup state account
+        #[arg(long)]
+        lockup: Pubkey,
+        /// Token account receiving the unlocked tokens
+        #[arg(long)]
+        destination: Pubkey,
+        /// Number of tokens to redeem, in base units, leaving the rest for later - everything unlocked if omitted
+        #[arg(long)]
+        amount: Option<u64>,
+    },
+    /// Lock SOL of the keypair for a receiver under a schedule of the native mint, without wrapping it
+    LockSol {
+        /// Lockup schedule of the native mint the SOL is locked under
+        #[arg(long)]
+        schedule: Pubkey,
+        /// Main account of the receiver allowed to redeem the SOL
+        #[arg(long)]
+        receiver: Pubkey,
+        /// Number of lamports to lock
+        #[arg(long)]
+        lamports: u64,
+    },
+    /// Redeem unlocked SOL of a lockup made by lock-sol, signed by the keypair as receiver or its claim delegate
+    RedeemSol {
+        /// Lockup state account
+        #[arg(long)]
+        lockup: Pubkey,
+        /// Account receiving the unlocked lamports, or a wrapped SOL token account with --wrap
+        #[arg(long)]
+        destination: Pubkey,
+        /// Send wrapped SOL to the destination token account instead of lamports
+        #[arg(long)]
+        wrap: bool,
+        /// Number of lamports to redeem, leaving the rest for later - everything unlocked if omitted
+        #[arg(long)]
+        amount: Option<u64>,
+    },
+    /// Fund a merkle distributor airdropping tokens under a lockup schedule, signed by the keypair as initializer
+    CreateDistributor {
+        /// Lockup schedule every claim vests under
+        #[arg(long)]
+        schedule: Pubkey,
+        /// Merkle root of the claims, base58
+        #[arg(long)]
+        merkle_root: Hash,
+        /// Number of claims in the tree
+        #[arg(long)]
+        max_num_nodes: u64,
+        /// Token account owned by the keypair the tokens are taken from
+        #[arg(long)]
+        source: Pubkey,
+        /// Number of tokens to fund the distributor with, in base units
+        #[arg(long)]
+        amount: u64,
+    },
+    /// Claim a merkle distributor leaf into a new lockup, signed by the keypair as the leaf's wallet
+    Claim {
+        /// Merkle distributor state account
+        #[arg(long)]
+        distributor: Pubkey,
+        /// Airdrop JSON written by build-airdrop to take the keypair's leaf and proof from
+        #[arg(long, conflicts_with_all = ["index", "amount", "proof"], required_unless_present_all = ["index", "amount"])]
+        airdrop: Option<PathBuf>,
+        /// Index of the leaf
+        #[arg(long)]
+        index: Option<u64>,
+        /// Amount of the leaf, in base units
+        #[arg(long)]
+        amount: Option<u64>,
+        /// Sibling hashes from the leaf up to the root, base58, comma separated
+        #[arg(long, value_delimiter = ',')]
+        proof: Vec<Hash>,
+    },
+    /// Build the merkle tree of a `wallet,amount` CSV and write its root and every wallet's proof as JSON
+    BuildAirdrop {
+        /// CSV file with a `wallet,amount` header, claim indexes follow the row order
+        csv: PathBuf,
+        /// JSON output, defaults to the CSV path with a `.airdrop.json` extension
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Check a claim's proof against a merkle root locally
+    VerifyProof {
+        /// Merkle root of the claims, base58
+        #[arg(long)]
+        merkle_root: Hash,
+        #[arg(long)]
+        index: u64,
+        #[arg(long)]
+        wallet: Pubkey,
+        /// Amount of the leaf, in base units
+        #[arg(long)]
+        amount: u64,
+        /// Sibling hashes from the leaf up to the root, base58, comma separated
+        #[arg(long, value_delimiter = ',')]
+        proof: Vec<Hash>,
+    },
+    /// Set the account that can pause a schedule besides the initializer, signed by the keypair as initializer
+    SetPauseAuthority {
+        /// Lockup schedule state account
+        schedule: Pubkey,
+        /// New pause authority, removed if omitted
+        #[arg(long)]
+        pause_authority: Option<Pubkey>,
+    },
+    /// Stop locks, claims and redeems under a schedule, signed by the keypair as initializer or pause authority
+    Pause {
+        /// Lockup schedule state account
+        schedule: Pubkey,
+    },
+    /// Allow locks, claims and redeems under a paused schedule again
+    Resume {
+        /// Lockup schedule state account
+        schedule: Pubkey,
+    },
+    /// Set the account that can freeze and thaw lockups of a schedule, signed by the keypair as initializer
+    SetComplianceAuthority {
+        /// Lockup schedule state account
+        schedule: Pubkey,
+        /// New compliance authority, removed if omitted
+        #[arg(long)]
+        compliance_authority: Option<Pubkey>,
+    },
+    /// Stop redeems of a single lockup, signed by the keypair as the schedule's compliance authority
+    Freeze {
+        /// Lockup state account
+        lockup: Pubkey,
+    },
+    /// Allow redeems of a frozen lockup again
+    Thaw {
+        /// Lockup state account
+        lockup: Pubkey,
+    },
+    /// Let another account redeem a lockup on the receiver's behalf, signed by the keypair as receiver
+    SetClaimDelegate {
+        /// Lockup state account
+        lockup: Pubkey,
+        /// New claim delegate, removed if omitted
+        #[arg(long)]
+        claim_delegate: Option<Pubkey>,
+        /// Token account the delegate may redeem to besides the receiver's own
+        #[arg(long, requires = "claim_delegate")]
+        delegate_destination: Option<Pubkey>,
+    },
+    /// Upgrade a state account in an older layout to the current one, paying any extra rent
+    Migrate {
+        address: Pubkey,
+    },
+    /// Display a lockup schedule state account
+    ShowSchedule {
+        address: Pubkey,
+    },
+    /// Display a lockup state account
+    ShowLockup {
+        address: Pubkey,
+    },
+    /// Print a lockup schedule, lockup or merkle distributor account as JSON, with vested amounts as of the cluster clock
+    Decode {
+        address: Pubkey,
+    },
+    /// List every lockup, optionally only those under one schedule
+    ListLockups {
+        #[arg(long)]
+        schedule: Option<Pubkey>,
+    },
+    /// Print the month-by-month unlock calendar of a schedule's lockups, per beneficiary and in total
+    Forecast {
+        schedule: Pubkey,
+        #[arg(long, value_enum, default_value = "csv")]
+        format: ReportFormat,
+        /// File to write the report to instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    let keypair_path = match (cli.keypair.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(path), Ok(home)) => format!("{}/{}", home, path),
+        _ => cli.keypair.clone(),
+    };
+    let config = Config {
+        rpc_client: RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        // read-only commands work without a keypair, so a missing file only fails on use
+        signer: read_keypair_file(&keypair_path).ok(),
+        keypair_path,
+        program_id: cli.program_id,
+    };
+
+    let result = match cli.command {
+        Command::CreateSchedule { mint, start_timestamp, periods, period_duration, total_quantity, time_unit, period_type, curve, curve_points } => {
+            let curve = VestingCurve { curve_type: curve.into(), points: curve_points };
+            command::create_schedule(&config, &mint, start_timestamp, periods, period_duration, total_quantity, time_unit.into(), period_type.into(), curve)
+        }
+        Command::Lock { schedule, receiver, source, amount } => {
+            command::lock(&config, &schedule, &receiver, &source, amount)
+        }
+        Command::BulkLock { csv, source, journal } => {
+            let journal = journal.unwrap_or_else(|| bulk::default_journal_path(&csv));
+            bulk::bulk_lock(&config, &csv, &journal, &source)
+        }
+        Command::Redeem { lockup, destination, amount } => command::redeem(&config, &lockup, &destination, amount),
+        Command::LockSol { schedule, receiver, lamports } => command::lock_sol(&config, &schedule, &receiver, lamports),
+        Command::RedeemSol { lockup, destination, wrap, amount } => {
+            command::redeem_sol(&config, &lockup, &destination, wrap, amount)
+        }
+        Command::CreateDistributor { schedule, merkle_root, max_num_nodes, source, amount } => {
+            command::create_distributor(&config, &schedule, merkle_root.to_bytes(), max_num_nodes, &source, amount)
+        }
+        Command::Claim { distributor, airdrop, index, amount, proof } => match (airdrop, index, amount) {
+            (Some(airdrop), _, _) => airdrop::claim_from_airdrop(&config, &distributor, &airdrop),
+            (None, Some(index), Some(amount)) => {
+                let proof = proof.iter().map(|hash| hash.to_bytes()).collect();
+                command::claim(&config, &distributor, index, amount, proof)
+            }
+            _ => unreachable!("clap requires --airdrop or --index and --amount"),
+        },
+        Command::BuildAirdrop { csv, output } => {
+            let output = output.unwrap_or_else(|| airdrop::default_output_path(&csv));
+            airdrop::build_airdrop(&csv, &output)
+        }
+        Command::VerifyProof { merkle_root, index, wallet, amount, proof } => {
+            let proof: Vec<[u8; 32]> = proof.iter().map(|hash| hash.to_bytes()).collect();
+            airdrop::verify_proof(&merkle_root, index, &wallet, amount, &proof)
+        }
+        Command::SetPauseAuthority { schedule, pause_authority } => {
+            command::set_pause_authority(&config, &schedule, pause_authority.as_ref())
+        }
+        Command::Pause { schedule } => command::set_paused(&config, &schedule, true),
+        Command::Resume { schedule } => command::set_paused(&config, &schedule, false),
+        Command::SetComplianceAuthority { schedule, compliance_authority } => {
+            command::set_compliance_authority(&config, &schedule, compliance_authority.as_ref())
+        }
+        Command::Freeze { lockup } => command::set_frozen(&config, &lockup, true),
+        Command::Thaw { lockup } => command::set_frozen(&config, &lockup, false),
+        Command::SetClaimDelegate { lockup, claim_delegate, delegate_destination } => {
+            command::set_claim_delegate(&config, &lockup, claim_delegate.as_ref(), delegate_destination.as_ref())
+        }
+        Command::Migrate { address } => command::migrate(&config, &address),
+        Command::ShowSchedule { address } => command::show_schedule(&config, &address),
+        Command::ShowLockup { address } => command::show_lockup(&config, &address),
+        Command::Decode { address } => command::decode(&config, &address),
+        Command::ListLockups { schedule } => command::list_lockups(&config, schedule.as_ref()),
+        Command::Forecast { schedule, format, output } => {
+            command::forecast(&config, &schedule, format, output.as_deref())
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,48 @@
+// Parses the structured events the program logs as `Program log: event: <base64>` back into typed events.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::BorshDeserialize;
+use bpf_program_template::event::{TokenDistributorEvent, EVENT_LOG_PREFIX, EVENT_VERSION};
+use solana_program::pubkey::Pubkey;
+
+use crate::DecodeError;
+
+const PROGRAM_LOG_PREFIX: &str = "Program log: ";
+
+// decode one event from its Borsh encoded bytes
+pub fn decode_event(data: &[u8]) -> Result<TokenDistributorEvent, DecodeError> {
+    let (version, event) = <(u8, TokenDistributorEvent)>::try_from_slice(data)
+        .map_err(|err| DecodeError::InvalidEvent(err.to_string()))?;
+    if version != EVENT_VERSION {
+        return Err(DecodeError::UnsupportedEventVersion(version));
+    }
+    Ok(event)
+}
+
+// Parse the events of a transaction's log messages in order. Lines logged by other programs,
+// including programs this one invokes and programs invoking this one, are skipped.
+pub fn parse_events(program_id: &Pubkey, logs: &[String]) -> Result<Vec<TokenDistributorEvent>, DecodeError> {
+    let program_id = program_id.to_string();
+    // programs currently executing, innermost last
+    let mut invoke_stack: Vec<&str> = vec![];
+    let mut events = vec![];
+    for log in logs {
+        if let Some(data) = log.strip_prefix(PROGRAM_LOG_PREFIX).and_then(|message| message.strip_prefix(EVENT_LOG_PREFIX)) {
+            if invoke_stack.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let data = STANDARD.decode(data).map_err(|err| DecodeError::InvalidEvent(err.to_string()))?;
+            events.push(decode_event(&data)?);
+        } else if let Some(rest) = log.strip_prefix("Program ") {
+            let mut words = rest.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(program), Some("invoke")) => invoke_stack.push(program),
+                (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                    invoke_stack.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(events)
+}
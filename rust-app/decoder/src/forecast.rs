@@ -0,0 +1,106 @@
+// Projects how many tokens unlock in each calendar month (UTC) across the lockups of a schedule,
+// per beneficiary and in aggregate, by running the schedule's vesting math forward.
+
+use std::collections::BTreeMap;
+
+use bpf_program_template::{
+    calendar::{civil_from_days, days_from_civil, SECONDS_PER_DAY},
+    state::{Lockup, LockupSchedule, TimeUnit},
+};
+use serde::Serialize;
+
+use crate::{time_unit_name, DecodeError};
+
+// beneficiary of the rows totalling every lockup of the schedule
+pub const AGGREGATE_BENEFICIARY: &str = "total";
+
+// longest calendar generated, 100 years
+pub const MAX_CALENDAR_MONTHS: usize = 1_200;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlockCalendarRow {
+    // calendar month as YYYY-MM
+    pub month: String,
+    pub month_start: u64,
+    // receiving wallet, or AGGREGATE_BENEFICIARY
+    pub beneficiary: String,
+    // tokens unlocking during the month
+    pub unlocked_amount: u64,
+    // tokens unlocked by the end of the month
+    pub cumulative_unlocked_amount: u64,
+    // tokens still locked at the end of the month
+    pub locked_amount: u64,
+}
+
+// unix timestamp of the start of the month containing `timestamp`
+fn month_start(timestamp: u64) -> (String, u64) {
+    let (year, month, _) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    let start = days_from_civil(year, month, 1) as u64 * SECONDS_PER_DAY;
+    (format!("{:04}-{:02}", year, month), start)
+}
+
+fn next_month_start(timestamp: u64) -> u64 {
+    let (year, month, _) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    let (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    days_from_civil(year, month, 1) as u64 * SECONDS_PER_DAY
+}
+
+// Unlock calendar of a schedule's lockups, one row per beneficiary per month plus an aggregate
+// row per month, from the month the schedule starts through the month its last period unlocks.
+// Each lockup vests on its own and a wallet's lockups are summed. Slot and epoch schedules have no fixed calendar dates.
+pub fn unlock_calendar(schedule: &LockupSchedule, lockups: &[Lockup]) -> Result<Vec<UnlockCalendarRow>, DecodeError> {
+    if schedule.time_unit != TimeUnit::UnixTimestamp {
+        return Err(DecodeError::UnsupportedTimeUnit(time_unit_name(schedule.time_unit)));
+    }
+    // each lockup vests and rounds on its own, so a wallet's lockups are kept apart until summed
+    let mut quantities: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    for lockup in lockups {
+        quantities.entry(lockup.receiving_account.to_string()).or_default().push(lockup.token_quantity);
+    }
+    let end_timestamp = schedule.period_end(schedule.number_periods)?;
+
+    let mut rows = vec![];
+    let (_, mut start) = month_start(schedule.start_timestamp);
+    let mut months = 0;
+    while start <= end_timestamp {
+        months += 1;
+        if months > MAX_CALENDAR_MONTHS {
+            return Err(DecodeError::CalendarTooLong(MAX_CALENDAR_MONTHS));
+        }
+        let (month, _) = month_start(start);
+        let end = next_month_start(start);
+        let mut total = UnlockCalendarRow {
+            month: month.clone(),
+            month_start: start,
+            beneficiary: AGGREGATE_BENEFICIARY.to_string(),
+            unlocked_amount: 0,
+            cumulative_unlocked_amount: 0,
+            locked_amount: 0,
+        };
+        for (beneficiary, wallet_quantities) in &quantities {
+            // vested by the last second of the previous month and of this month, summed over the wallet's lockups
+            let (mut quantity, mut vested_before, mut vested_by_end) = (0u64, 0u64, 0u64);
+            for token_quantity in wallet_quantities {
+                quantity = quantity.saturating_add(*token_quantity);
+                vested_before = vested_before.saturating_add(schedule.vested_amount(*token_quantity, start.saturating_sub(1))?);
+                vested_by_end = vested_by_end.saturating_add(schedule.vested_amount(*token_quantity, end - 1)?);
+            }
+            let row = UnlockCalendarRow {
+                month: month.clone(),
+                month_start: start,
+                beneficiary: beneficiary.clone(),
+                unlocked_amount: vested_by_end - vested_before,
+                cumulative_unlocked_amount: vested_by_end,
+                locked_amount: quantity - vested_by_end,
+            };
+            total.unlocked_amount = total.unlocked_amount.saturating_add(row.unlocked_amount);
+            total.cumulative_unlocked_amount = total.cumulative_unlocked_amount.saturating_add(row.cumulative_unlocked_amount);
+            total.locked_amount = total.locked_amount.saturating_add(row.locked_amount);
+            rows.push(row);
+        }
+        rows.push(total);
+        start = end;
+    }
+    Ok(rows)
+}
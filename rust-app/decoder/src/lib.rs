@@ -0,0 +1,251 @@
+// Decodes token distributor state accounts into JSON-friendly structs, including the
+// vesting amounts derived from the schedule at a given time, parses the program's events
+// out of transaction logs and forecasts a schedule's monthly unlocks.
+
+pub mod events;
+pub mod forecast;
+
+pub use events::{decode_event, parse_events};
+pub use forecast::{unlock_calendar, UnlockCalendarRow};
+
+use bpf_program_template::state::{AccountType, Lockup, LockupSchedule, CurveType, MerkleDistributor, PeriodType, TimeUnit};
+use serde::Serialize;
+use solana_program::{clock::Clock, hash::Hash, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DecodeError {
+    #[error("account type tag {0:?} is not a token distributor account")]
+    UnknownAccountType(Option<u8>),
+    #[error("account data is invalid: {0}")]
+    InvalidData(#[from] ProgramError),
+    #[error("decoding a lockup needs its lockup schedule account data")]
+    MissingSchedule,
+    #[error("event data is invalid: {0}")]
+    InvalidEvent(String),
+    #[error("event version {0} is not supported")]
+    UnsupportedEventVersion(u8),
+    #[error("unlock calendar would span more than {0} months")]
+    CalendarTooLong(usize),
+    #[error("unlock calendar needs a schedule in unix timestamps, not in {0}")]
+    UnsupportedTimeUnit(&'static str),
+}
+
+// state accounts are told apart by the account type tag in their first byte
+pub fn detect_account_type(data: &[u8]) -> Result<AccountType, DecodeError> {
+    match data.first() {
+        Some(tag) if *tag == AccountType::LockupSchedule as u8 => Ok(AccountType::LockupSchedule),
+        Some(tag) if *tag == AccountType::Lockup as u8 => Ok(AccountType::Lockup),
+        Some(tag) if *tag == AccountType::MerkleDistributor as u8 => Ok(AccountType::MerkleDistributor),
+        _ => Err(DecodeError::UnknownAccountType(data.first().copied())),
+    }
+}
+
+// name of a schedule's time unit as shown in JSON
+pub fn time_unit_name(time_unit: TimeUnit) -> &'static str {
+    match time_unit {
+        TimeUnit::UnixTimestamp => "unixTimestamp",
+        TimeUnit::Slot => "slot",
+        TimeUnit::Epoch => "epoch",
+    }
+}
+
+// name of a schedule's curve type as shown in JSON
+pub fn curve_type_name(curve_type: CurveType) -> &'static str {
+    match curve_type {
+        CurveType::Linear => "linear",
+        CurveType::Steps => "steps",
+        CurveType::PiecewiseLinear => "piecewiseLinear",
+    }
+}
+
+// name of a schedule's period type as shown in JSON
+pub fn period_type_name(period_type: PeriodType) -> &'static str {
+    match period_type {
+        PeriodType::Fixed => "fixed",
+        PeriodType::CalendarMonth => "calendarMonth",
+        PeriodType::CalendarQuarter => "calendarQuarter",
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiCurvePoint {
+    pub period: u16,
+    pub basis_points: u16,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiLockupSchedule {
+    pub initializer: String,
+    pub token_mint: String,
+    // unit of the start, duration, end and next unlock fields
+    pub time_unit: String,
+    pub start_timestamp: u64,
+    pub number_periods: u64,
+    // months or quarters per period for calendar periods
+    pub period_type: String,
+    pub period_duration: u64,
+    pub curve_type: String,
+    // empty for linear curves
+    pub curve_points: Vec<UiCurvePoint>,
+    pub end_timestamp: u64,
+    pub total_token_quantity: u64,
+    pub token_quantity_locked: u64,
+    // None when only the initializer can pause
+    pub pause_authority: Option<String>,
+    pub paused: bool,
+    // None when no lockup can be frozen
+    pub compliance_authority: Option<String>,
+    pub periods_unlocked: u64,
+    // amounts across the schedule's lockups, each vested on its own
+    pub vested_amount: u64,
+    pub unvested_amount: u64,
+    pub next_unlock_timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiLockup {
+    pub lockup_schedule_state: String,
+    pub receiving_account: String,
+    pub lockup_token_account: String,
+    pub token_quantity: u64,
+    pub periods_redeemed: u64,
+    pub frozen: bool,
+    // None when only the receiving account can redeem
+    pub claim_delegate: Option<String>,
+    pub delegate_destination: Option<String>,
+    pub vested_amount: u64,
+    pub unvested_amount: u64,
+    pub redeemed_amount: u64,
+    pub redeemable_amount: u64,
+    pub next_unlock_timestamp: Option<u64>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UiMerkleDistributor {
+    pub lockup_schedule_state: String,
+    pub distributor_token_account: String,
+    // base58, like other 32 byte hashes
+    pub merkle_root: String,
+    pub max_num_nodes: u64,
+    pub total_amount: u64,
+    pub num_nodes_claimed: u64,
+    pub amount_claimed: u64,
+    pub amount_unclaimed: u64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "info", rename_all = "camelCase")]
+pub enum DecodedAccount {
+    LockupSchedule(UiLockupSchedule),
+    Lockup(UiLockup),
+    MerkleDistributor(UiMerkleDistributor),
+}
+
+impl DecodedAccount {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+// current_timestamp is in the schedule's time unit. Each lockup vests and rounds on its own, so
+// the vested amount sums the schedule's lockups rather than vesting token_quantity_locked
+pub fn decode_lockup_schedule(schedule: &LockupSchedule, lockups: &[Lockup], current_timestamp: u64) -> Result<UiLockupSchedule, DecodeError> {
+    let mut vested_amount: u64 = 0;
+    for lockup in lockups {
+        vested_amount = vested_amount.saturating_add(schedule.vested_amount(lockup.token_quantity, current_timestamp)?);
+    }
+    Ok(UiLockupSchedule {
+        initializer: schedule.initializer.to_string(),
+        token_mint: schedule.token_mint.to_string(),
+        time_unit: time_unit_name(schedule.time_unit).to_string(),
+        start_timestamp: schedule.start_timestamp,
+        number_periods: schedule.number_periods,
+        period_type: period_type_name(schedule.period_type).to_string(),
+        period_duration: schedule.period_duration,
+        curve_type: curve_type_name(schedule.curve_type).to_string(),
+        curve_points: schedule.curve_points().iter()
+            .map(|point| UiCurvePoint { period: point.period, basis_points: point.basis_points })
+            .collect(),
+        end_timestamp: schedule.period_end(schedule.number_periods)?,
+        total_token_quantity: schedule.total_token_quantity,
+        token_quantity_locked: schedule.token_quantity_locked,
+        pause_authority: (schedule.pause_authority != Pubkey::default()).then(|| schedule.pause_authority.to_string()),
+        paused: schedule.paused,
+        compliance_authority: (schedule.compliance_authority != Pubkey::default()).then(|| schedule.compliance_authority.to_string()),
+        periods_unlocked: schedule.periods_unlocked(current_timestamp)?,
+        vested_amount,
+        unvested_amount: schedule.token_quantity_locked.saturating_sub(vested_amount),
+        next_unlock_timestamp: schedule.next_unlock_timestamp(current_timestamp)?,
+    })
+}
+
+pub fn decode_lockup(lockup: &Lockup, schedule: &LockupSchedule, current_timestamp: u64) -> Result<UiLockup, DecodeError> {
+    let vested_amount = schedule.vested_amount(lockup.token_quantity, current_timestamp)?;
+    let redeemed_amount = lockup.redeemed_amount;
+    Ok(UiLockup {
+        lockup_schedule_state: lockup.lockup_schedule_state.to_string(),
+        receiving_account: lockup.receiving_account.to_string(),
+        lockup_token_account: lockup.lockup_token_account.to_string(),
+        token_quantity: lockup.token_quantity,
+        periods_redeemed: lockup.periods_redeemed,
+        frozen: lockup.frozen,
+        claim_delegate: (lockup.claim_delegate != Pubkey::default()).then(|| lockup.claim_delegate.to_string()),
+        delegate_destination: (lockup.delegate_destination != Pubkey::default()).then(|| lockup.delegate_destination.to_string()),
+        vested_amount,
+        unvested_amount: lockup.token_quantity.saturating_sub(vested_amount),
+        redeemed_amount,
+        redeemable_amount: vested_amount.saturating_sub(redeemed_amount),
+        next_unlock_timestamp: schedule.next_unlock_timestamp(current_timestamp)?,
+    })
+}
+
+pub fn decode_merkle_distributor(distributor: &MerkleDistributor) -> UiMerkleDistributor {
+    UiMerkleDistributor {
+        lockup_schedule_state: distributor.lockup_schedule_state.to_string(),
+        distributor_token_account: distributor.distributor_token_account.to_string(),
+        merkle_root: Hash::new_from_array(distributor.merkle_root).to_string(),
+        max_num_nodes: distributor.max_num_nodes,
+        total_amount: distributor.total_amount,
+        num_nodes_claimed: distributor.num_nodes_claimed,
+        amount_claimed: distributor.amount_claimed,
+        amount_unclaimed: distributor.total_amount.saturating_sub(distributor.amount_claimed),
+    }
+}
+
+// Decode raw account data. A lockup's derived fields need its schedule, which the caller
+// fetches from `Lockup.lockup_schedule_state` and passes as `schedule_data`. A schedule's vested
+// amounts sum its lockups, which the caller passes as `lockups`. Vesting is worked out at the
+// clock field matching the schedule's time unit.
+pub fn decode_account(
+    data: &[u8],
+    schedule_data: Option<&[u8]>,
+    lockups: &[Lockup],
+    clock: &Clock,
+) -> Result<DecodedAccount, DecodeError> {
+    match detect_account_type(data)? {
+        AccountType::LockupSchedule => {
+            let schedule = LockupSchedule::unpack(data)?;
+            let current_timestamp = schedule.time_unit.current_time(clock)?;
+            Ok(DecodedAccount::LockupSchedule(decode_lockup_schedule(&schedule, lockups, current_timestamp)?))
+        }
+        AccountType::Lockup => {
+            let lockup = Lockup::unpack(data)?;
+            let schedule_data = schedule_data.ok_or(DecodeError::MissingSchedule)?;
+            let schedule = LockupSchedule::unpack(schedule_data)?;
+            let current_timestamp = schedule.time_unit.current_time(clock)?;
+            Ok(DecodedAccount::Lockup(decode_lockup(&lockup, &schedule, current_timestamp)?))
+        }
+        AccountType::MerkleDistributor => {
+            // the claimed bitmap follows the header
+            let header = data.get(..MerkleDistributor::LEN).ok_or(ProgramError::AccountDataTooSmall)?;
+            Ok(DecodedAccount::MerkleDistributor(decode_merkle_distributor(&MerkleDistributor::unpack(header)?)))
+        }
+        // detect_account_type never returns it, an empty account has no tag to decode
+        AccountType::Uninitialized => Err(DecodeError::UnknownAccountType(data.first().copied())),
+    }
+}
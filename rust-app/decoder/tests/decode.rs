@@ -0,0 +1,123 @@
+use bpf_program_template::state::{AccountType, Lockup, LockupSchedule, MerkleDistributor, TimeUnit, STATE_VERSION};
+use solana_program::{clock::Clock, program_pack::Pack, pubkey::Pubkey};
+use token_distributor_decoder::{decode_account, detect_account_type, DecodeError, DecodedAccount};
+
+fn schedule() -> LockupSchedule {
+    LockupSchedule {
+        token_quantity_locked: 4_003,
+        ..LockupSchedule::new(Pubkey::new_unique(), Pubkey::new_unique(), 1_000, 4, 100, 10_000)
+    }
+}
+
+fn clock_at(unix_timestamp: i64) -> Clock {
+    Clock { unix_timestamp, ..Clock::default() }
+}
+
+fn pack<T: Pack>(state: T) -> Vec<u8> {
+    let mut data = vec![0; T::LEN];
+    T::pack(state, &mut data).unwrap();
+    data
+}
+
+#[test]
+fn test_decode_lockup_schedule() {
+    let data = pack(schedule());
+    assert_eq!(detect_account_type(&data).unwrap(), AccountType::LockupSchedule);
+
+    // each lockup rounds down on its own, so half of 4_003 locked in three lockups vests 2_000, not 2_001
+    let lockups: Vec<Lockup> = [1_001, 1_001, 2_001].iter()
+        .map(|token_quantity| Lockup::new(Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), *token_quantity))
+        .collect();
+    let decoded = decode_account(&data, None, &lockups, &clock_at(1_250)).unwrap();
+    let DecodedAccount::LockupSchedule(info) = &decoded else { panic!("expected a lockup schedule") };
+    assert_eq!(info.end_timestamp, 1_400);
+    assert_eq!(info.periods_unlocked, 2);
+    assert_eq!(info.vested_amount, 2_000);
+    assert_eq!(info.unvested_amount, 2_003);
+    assert_eq!(info.next_unlock_timestamp, Some(1_300));
+
+    let json: serde_json::Value = serde_json::from_str(&decoded.to_json()).unwrap();
+    assert_eq!(json["type"], "lockupSchedule");
+    assert_eq!(json["info"]["vestedAmount"], 2_000);
+    assert_eq!(json["info"]["pauseAuthority"], serde_json::Value::Null);
+    assert_eq!(json["info"]["paused"], false);
+    assert_eq!(json["info"]["timeUnit"], "unixTimestamp");
+    assert_eq!(json["info"]["periodType"], "fixed");
+}
+
+#[test]
+fn test_decode_slot_schedule() {
+    let data = pack(LockupSchedule { time_unit: TimeUnit::Slot, ..schedule() });
+
+    // vesting follows the clock's slot, not its unix timestamp
+    let clock = Clock { slot: 1_150, unix_timestamp: 1_400, ..Clock::default() };
+    let DecodedAccount::LockupSchedule(info) = decode_account(&data, None, &[], &clock).unwrap() else {
+        panic!("expected a lockup schedule")
+    };
+    assert_eq!(info.time_unit, "slot");
+    assert_eq!(info.periods_unlocked, 1);
+    assert_eq!(info.next_unlock_timestamp, Some(1_200));
+}
+
+#[test]
+fn test_decode_lockup() {
+    let schedule = schedule();
+    let lockup = Lockup {
+        periods_redeemed: 1,
+        redeemed_amount: 1_000,
+        ..Lockup::new(Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), 4_003)
+    };
+    let data = pack(lockup);
+    let schedule_data = pack(schedule);
+
+    let DecodedAccount::Lockup(info) = decode_account(&data, Some(&schedule_data), &[], &clock_at(1_399)).unwrap() else {
+        panic!("expected a lockup")
+    };
+    assert_eq!(info.vested_amount, 3_000);
+    assert_eq!(info.unvested_amount, 1_003);
+    assert_eq!(info.redeemed_amount, 1_000);
+    assert_eq!(info.redeemable_amount, 2_000);
+    assert_eq!(info.next_unlock_timestamp, Some(1_400));
+
+    // the final period releases the rounding remainder and there is no next unlock
+    let DecodedAccount::Lockup(info) = decode_account(&data, Some(&schedule_data), &[], &clock_at(1_400)).unwrap() else {
+        panic!("expected a lockup")
+    };
+    assert_eq!(info.vested_amount, 4_003);
+    assert_eq!(info.unvested_amount, 0);
+    assert_eq!(info.next_unlock_timestamp, None);
+
+    assert!(matches!(decode_account(&data, None, &[], &clock_at(1_400)), Err(DecodeError::MissingSchedule)));
+}
+
+#[test]
+fn test_decode_merkle_distributor() {
+    let distributor = MerkleDistributor {
+        account_type: AccountType::MerkleDistributor,
+        version: STATE_VERSION,
+        is_initialized: true,
+        lockup_schedule_state: Pubkey::new_unique(),
+        distributor_token_account: Pubkey::new_unique(),
+        merkle_root: [7; 32],
+        max_num_nodes: 20,
+        total_amount: 10_000,
+        num_nodes_claimed: 2,
+        amount_claimed: 2_500,
+    };
+    // header followed by the claimed bitmap
+    let mut data = vec![0; MerkleDistributor::space(20)];
+    MerkleDistributor::pack(distributor, &mut data[..MerkleDistributor::LEN]).unwrap();
+
+    let DecodedAccount::MerkleDistributor(info) = decode_account(&data, None, &[], &clock_at(0)).unwrap() else {
+        panic!("expected a merkle distributor")
+    };
+    assert_eq!(info.merkle_root, solana_program::hash::Hash::new_from_array([7; 32]).to_string());
+    assert_eq!(info.num_nodes_claimed, 2);
+    assert_eq!(info.amount_unclaimed, 7_500);
+}
+
+#[test]
+fn test_decode_unknown_account() {
+    assert!(matches!(decode_account(&[0; 64], None, &[], &clock_at(0)), Err(DecodeError::UnknownAccountType(Some(0)))));
+    assert!(matches!(decode_account(&[], None, &[], &clock_at(0)), Err(DecodeError::UnknownAccountType(None))));
+}
@@ -0,0 +1,59 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bpf_program_template::event::{TokenDistributorEvent, EVENT_LOG_PREFIX};
+use solana_program::pubkey::Pubkey;
+use token_distributor_decoder::{decode_event, parse_events, DecodeError};
+
+const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+
+fn program_data(event: &TokenDistributorEvent) -> String {
+    format!("Program log: {}{}", EVENT_LOG_PREFIX, STANDARD.encode(event.pack()))
+}
+
+#[test]
+fn test_parse_events() {
+    let program_id = Pubkey::new_unique();
+    let redeemed = TokenDistributorEvent::TokensRedeemed {
+        lockup_schedule_state: Pubkey::new_unique(),
+        lockup_state: Pubkey::new_unique(),
+        receiving_account: Pubkey::new_unique(),
+        amount: 2_003,
+        periods: 2,
+        periods_redeemed: 4,
+    };
+    let locked = TokenDistributorEvent::TokensLocked {
+        lockup_schedule_state: Pubkey::new_unique(),
+        lockup_state: Pubkey::new_unique(),
+        receiving_account: Pubkey::new_unique(),
+        lockup_token_account: Pubkey::new_unique(),
+        token_quantity: 4_003,
+    };
+    let logs = vec![
+        format!("Program {} invoke [1]", program_id),
+        "Program log: Instruction: RedeemTokens".to_string(),
+        format!("Program {} invoke [2]", TOKEN_PROGRAM_ID),
+        // lines logged by an invoked program are not ours
+        format!("Program log: {}AQID", EVENT_LOG_PREFIX),
+        format!("Program {} success", TOKEN_PROGRAM_ID),
+        program_data(&redeemed),
+        format!("Program {} success", program_id),
+        format!("Program {} invoke [1]", program_id),
+        program_data(&locked),
+        format!("Program {} success", program_id),
+    ];
+    assert_eq!(parse_events(&program_id, &logs).unwrap(), vec![redeemed, locked]);
+    assert_eq!(parse_events(&Pubkey::new_unique(), &logs).unwrap(), vec![]);
+}
+
+#[test]
+fn test_decode_event_invalid() {
+    let event = TokenDistributorEvent::AccountMigrated {
+        account: Pubkey::new_unique(),
+        account_type: bpf_program_template::state::AccountType::Lockup,
+    };
+    let mut data = event.pack();
+    assert_eq!(decode_event(&data).unwrap(), event);
+
+    data[0] = 2;
+    assert!(matches!(decode_event(&data), Err(DecodeError::UnsupportedEventVersion(2))));
+    assert!(matches!(decode_event(&data[..10]), Err(DecodeError::InvalidEvent(_))));
+}
@@ -0,0 +1,85 @@
+use bpf_program_template::state::{Lockup, LockupSchedule, TimeUnit};
+use solana_program::pubkey::Pubkey;
+use token_distributor_decoder::{
+    forecast::{AGGREGATE_BENEFICIARY, MAX_CALENDAR_MONTHS},
+    unlock_calendar, DecodeError,
+};
+
+const DAY: u64 = 24 * 60 * 60;
+// 2026-01-15T00:00:00Z
+const START: u64 = 1_768_435_200;
+
+fn schedule(number_periods: u64, period_duration: u64) -> LockupSchedule {
+    LockupSchedule {
+        token_quantity_locked: 3_601,
+        ..LockupSchedule::new(Pubkey::new_unique(), Pubkey::new_unique(), START, number_periods, period_duration, 10_000)
+    }
+}
+
+fn lockup(receiving_account: Pubkey, token_quantity: u64) -> Lockup {
+    Lockup::new(Pubkey::new_unique(), receiving_account, Pubkey::new_unique(), token_quantity)
+}
+
+#[test]
+fn test_unlock_calendar() {
+    // 3 periods of 30 days unlock on Feb 14, Mar 16 and Apr 15
+    let wallet = Pubkey::new_unique();
+    let other_wallet = Pubkey::new_unique();
+    let lockups = [lockup(wallet, 1_000), lockup(other_wallet, 600), lockup(wallet, 2_001)];
+    let rows = unlock_calendar(&schedule(3, 30 * DAY), &lockups).unwrap();
+
+    let months: Vec<(&str, u64)> = rows.iter()
+        .filter(|row| row.beneficiary == AGGREGATE_BENEFICIARY)
+        .map(|row| (row.month.as_str(), row.month_start))
+        .collect();
+    assert_eq!(months, vec![
+        ("2026-01", 1_767_225_600),
+        ("2026-02", 1_769_904_000),
+        ("2026-03", 1_772_323_200),
+        ("2026-04", 1_775_001_600),
+    ]);
+
+    // (unlocked, cumulative unlocked, still locked) per month
+    let amounts = |beneficiary: &str| -> Vec<(u64, u64, u64)> {
+        rows.iter()
+            .filter(|row| row.beneficiary == beneficiary)
+            .map(|row| (row.unlocked_amount, row.cumulative_unlocked_amount, row.locked_amount))
+            .collect()
+    };
+    // lockups of the same wallet are summed and the final period of each releases its remainder
+    assert_eq!(amounts(&wallet.to_string()), vec![(0, 0, 3_001), (1_000, 1_000, 2_001), (1_000, 2_000, 1_001), (1_001, 3_001, 0)]);
+    assert_eq!(amounts(&other_wallet.to_string()), vec![(0, 0, 600), (200, 200, 400), (200, 400, 200), (200, 600, 0)]);
+    assert_eq!(amounts(AGGREGATE_BENEFICIARY), vec![(0, 0, 3_601), (1_200, 1_200, 2_401), (1_200, 2_400, 1_201), (1_201, 3_601, 0)]);
+}
+
+#[test]
+fn test_unlock_calendar_rounds_per_lockup() {
+    // each 500 token lockup unlocks 166 a period - 1_000 in one lockup would unlock 333
+    let wallet = Pubkey::new_unique();
+    let lockups = [lockup(wallet, 500), lockup(wallet, 500)];
+    let rows = unlock_calendar(&schedule(3, 30 * DAY), &lockups).unwrap();
+
+    let amounts = |beneficiary: &str| -> Vec<(u64, u64, u64)> {
+        rows.iter()
+            .filter(|row| row.beneficiary == beneficiary)
+            .map(|row| (row.unlocked_amount, row.cumulative_unlocked_amount, row.locked_amount))
+            .collect()
+    };
+    let expected = vec![(0, 0, 1_000), (332, 332, 668), (332, 664, 336), (336, 1_000, 0)];
+    assert_eq!(amounts(&wallet.to_string()), expected);
+    assert_eq!(amounts(AGGREGATE_BENEFICIARY), expected);
+}
+
+#[test]
+fn test_unlock_calendar_too_long() {
+    let lockups = [lockup(Pubkey::new_unique(), 1_000)];
+    let result = unlock_calendar(&schedule(MAX_CALENDAR_MONTHS as u64 + 1, 31 * DAY), &lockups);
+    assert!(matches!(result, Err(DecodeError::CalendarTooLong(MAX_CALENDAR_MONTHS))));
+}
+
+#[test]
+fn test_unlock_calendar_slot_schedule() {
+    let lockups = [lockup(Pubkey::new_unique(), 1_000)];
+    let schedule = LockupSchedule { time_unit: TimeUnit::Slot, ..schedule(3, 30 * DAY) };
+    assert!(matches!(unlock_calendar(&schedule, &lockups), Err(DecodeError::UnsupportedTimeUnit("slot"))));
+}
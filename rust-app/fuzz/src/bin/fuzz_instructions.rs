@@ -0,0 +1,492 @@
+// Feeds arbitrary instruction data and account layouts through `Processor::process` in a
+// program-test bank, checking the program never panics and tokens and lamports are never created or lost.
+//
+// Run locally with `cargo hfuzz run token-distributor-instructions` from this directory.
+
+use arbitrary::Arbitrary;
+use bpf_program_template::{
+    find_pda_address,
+    find_sol_vault_address,
+    find_token_authority_address,
+    instruction::{self, TokenDistributorInstruction},
+    merkle::leaf_hash,
+    native_mint,
+    processor::Processor,
+    state::{CurvePoint, CurveType, Lockup, LockupSchedule, MerkleDistributor, PeriodType, TimeUnit, VestingCurve},
+};
+use honggfuzz::fuzz;
+use solana_program::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_instruction, system_program, sysvar,
+};
+use solana_program_test::{processor, tokio, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+const NUMBER_PERIODS: u64 = 4;
+const PERIOD_DURATION: u64 = 1_000;
+const LOCKUP_QUANTITIES: [u64; 2] = [4_000, 1_003];
+// claimable in full by receivers[1] as index 0 of a single leaf distributor
+const DISTRIBUTOR_QUANTITY: u64 = 2_000;
+const SOL_LOCKUP_LAMPORTS: u64 = 4_000_003;
+const INITIALIZER_LAMPORTS: u64 = 10 * LAMPORTS_PER_SOL;
+
+#[derive(Debug, Arbitrary)]
+struct AccountChoice {
+    index: u8,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+#[derive(Debug, Arbitrary)]
+enum FuzzData {
+    Raw(Vec<u8>),
+    CreateLockupSchedule {
+        start_timestamp: u64,
+        total_unlock_periods: u64,
+        period_duration: u64,
+        total_lockup_quantity: u64,
+        time_unit: Option<u8>,
+        period_type: Option<u8>,
+        curve: Option<(u8, Vec<(u16, u16)>)>,
+    },
+    LockTokens { token_quantity: u64 },
+    RedeemTokens { amount: Option<u64> },
+    LockLamports { lamports: u64 },
+    RedeemLamports { amount: Option<u64> },
+    MigrateAccount,
+    CreateMerkleDistributor { merkle_root: [u8; 32], max_num_nodes: u64, token_quantity: u64 },
+    ClaimTokens { index: u64, amount: u64, proof: Vec<[u8; 32]> },
+    // authorities, delegates and destinations are indexes into the account pool
+    SetPauseAuthority { pause_authority: u8 },
+    SetPaused { paused: bool },
+    SetComplianceAuthority { compliance_authority: u8 },
+    SetFrozen { frozen: bool },
+    SetClaimDelegate { claim_delegate: u8, delegate_destination: u8 },
+}
+
+#[derive(Debug, Arbitrary)]
+enum FuzzInstruction {
+    // any instruction data over any layout drawn from the fixture accounts
+    Program { data: FuzzData, accounts: Vec<AccountChoice> },
+    // a well-formed redeem of one of the fixture lockups, in full or of an amount
+    Redeem { lockup: u8, amount: Option<u64> },
+    // a well-formed claim of the distributor's leaf into the spare lockup state, of any amount
+    Claim { amount: u64 },
+    // a well-formed redeem of the SOL lockup to its receiver, in full or of an amount
+    RedeemSol { amount: Option<u64> },
+    // move the Clock sysvar forward
+    Warp { seconds: u32 },
+}
+
+// one schedule with two funded lockups and a funded merkle distributor, and a native SOL schedule
+// with one lockup
+struct Fixture {
+    program_id: Pubkey,
+    initializer: Keypair,
+    schedule: Keypair,
+    mint: Keypair,
+    receivers: Vec<Keypair>,
+    lockups: Vec<Keypair>,
+    vaults: Vec<Keypair>,
+    receiving_token_accounts: Vec<Keypair>,
+    source_token_account: Keypair,
+    spare_state: Keypair,
+    spare_vault: Keypair,
+    distributor: Keypair,
+    distributor_vault: Keypair,
+    sol_schedule: Keypair,
+    sol_lockup: Keypair,
+}
+
+impl Fixture {
+    fn keypairs(&self) -> Vec<&Keypair> {
+        let mut keypairs = vec![
+            &self.initializer,
+            &self.schedule,
+            &self.mint,
+            &self.source_token_account,
+            &self.spare_state,
+            &self.spare_vault,
+            &self.distributor,
+            &self.distributor_vault,
+            &self.sol_schedule,
+            &self.sol_lockup,
+        ];
+        keypairs.extend(self.receivers.iter());
+        keypairs.extend(self.lockups.iter());
+        keypairs.extend(self.vaults.iter());
+        keypairs.extend(self.receiving_token_accounts.iter());
+        keypairs
+    }
+
+    fn token_accounts(&self) -> Vec<Pubkey> {
+        self.vaults.iter()
+            .chain(self.receiving_token_accounts.iter())
+            .chain([&self.source_token_account, &self.spare_vault, &self.distributor_vault])
+            .map(|k| k.pubkey())
+            .collect()
+    }
+
+    // program-derived addresses that own token accounts or hold lamports
+    fn derived_addresses(&self) -> Vec<Pubkey> {
+        let mut addresses = vec![find_pda_address(&self.program_id).0];
+        for state in self.lockups.iter().chain([&self.spare_state, &self.distributor]) {
+            addresses.push(find_token_authority_address(&self.program_id, &state.pubkey()).0);
+        }
+        for state in self.lockups.iter().chain([&self.spare_state, &self.sol_lockup]) {
+            addresses.push(find_sol_vault_address(&self.program_id, &state.pubkey()).0);
+        }
+        addresses
+    }
+
+    // every account instructions can move lamports between, program and sysvar accounts aside
+    fn lamport_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts: Vec<Pubkey> = self.keypairs().iter().map(|k| k.pubkey()).collect();
+        accounts.extend(self.derived_addresses());
+        accounts
+    }
+
+    fn account_pool(&self) -> Vec<Pubkey> {
+        let mut pool = self.lamport_accounts();
+        pool.extend_from_slice(&[
+            native_mint::id(),
+            spl_token::id(),
+            sysvar::clock::id(),
+            sysvar::rent::id(),
+            system_program::id(),
+            self.program_id,
+        ]);
+        pool
+    }
+}
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    loop {
+        fuzz!(|instructions: Vec<FuzzInstruction>| {
+            runtime.block_on(run_fuzz_instructions(instructions));
+        });
+    }
+}
+
+async fn run_fuzz_instructions(instructions: Vec<FuzzInstruction>) {
+    let program_id = Pubkey::new_unique();
+    let program_test = ProgramTest::new("bpf_program_template", program_id, processor!(Processor::process));
+    let mut context = program_test.start_with_context().await;
+    let fixture = setup(&mut context, program_id).await;
+    let total_supply = LOCKUP_QUANTITIES.iter().sum::<u64>() + DISTRIBUTOR_QUANTITY;
+    let total_lamports = held_lamports(&mut context, &fixture).await;
+    check_invariants(&mut context, &fixture, total_supply, total_lamports).await;
+
+    let pool = fixture.account_pool();
+    let keypairs = fixture.keypairs();
+    for fuzz_instruction in instructions {
+        match fuzz_instruction {
+            FuzzInstruction::Program { data, accounts } => {
+                let mut signers = vec![];
+                let accounts = accounts
+                    .iter()
+                    .map(|choice| {
+                        let pubkey = pool[choice.index as usize % pool.len()];
+                        // only accounts we hold a keypair for can actually sign
+                        let signer = keypairs.iter().find(|k| k.pubkey() == pubkey).filter(|_| choice.is_signer);
+                        if let Some(signer) = signer.filter(|s| !signers.contains(*s)) {
+                            signers.push(*signer);
+                        }
+                        AccountMeta { pubkey, is_signer: signer.is_some(), is_writable: choice.is_writable }
+                    })
+                    .collect();
+                let ix = Instruction { program_id, accounts, data: pack_fuzz_data(data, &pool) };
+                let _ = process(&mut context, ix, &signers).await;
+            }
+            FuzzInstruction::Redeem { lockup, amount } => {
+                let i = lockup as usize % fixture.lockups.len();
+                let ix = instruction::redeem_tokens(
+                    &program_id,
+                    &fixture.receivers[i].pubkey(),
+                    &fixture.schedule.pubkey(),
+                    &fixture.lockups[i].pubkey(),
+                    &fixture.vaults[i].pubkey(),
+                    &find_token_authority_address(&program_id, &fixture.lockups[i].pubkey()).0,
+                    &fixture.receiving_token_accounts[i].pubkey(),
+                    &spl_token::id(),
+                    &fixture.mint.pubkey(),
+                    amount,
+                ).unwrap();
+                let _ = process(&mut context, ix, &[&fixture.receivers[i]]).await;
+            }
+            FuzzInstruction::Claim { amount } => {
+                let ix = instruction::claim_tokens(
+                    &program_id,
+                    &fixture.receivers[1].pubkey(),
+                    &fixture.distributor.pubkey(),
+                    &fixture.schedule.pubkey(),
+                    &fixture.spare_state.pubkey(),
+                    &fixture.spare_vault.pubkey(),
+                    &fixture.distributor_vault.pubkey(),
+                    &spl_token::id(),
+                    &fixture.mint.pubkey(),
+                    0,
+                    amount,
+                    vec![],
+                ).unwrap();
+                let _ = process(&mut context, ix, &[&fixture.receivers[1]]).await;
+            }
+            FuzzInstruction::RedeemSol { amount } => {
+                let ix = instruction::redeem_lamports(
+                    &program_id,
+                    &fixture.receivers[0].pubkey(),
+                    &fixture.sol_schedule.pubkey(),
+                    &fixture.sol_lockup.pubkey(),
+                    &fixture.receivers[0].pubkey(),
+                    None,
+                    amount,
+                ).unwrap();
+                let _ = process(&mut context, ix, &[&fixture.receivers[0]]).await;
+            }
+            FuzzInstruction::Warp { seconds } => {
+                let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+                clock.unix_timestamp = clock.unix_timestamp.saturating_add(seconds as i64);
+                context.set_sysvar(&clock);
+            }
+        }
+        check_invariants(&mut context, &fixture, total_supply, total_lamports).await;
+    }
+}
+
+fn pack_fuzz_data(data: FuzzData, pool: &[Pubkey]) -> Vec<u8> {
+    let account = |index: u8| pool[index as usize % pool.len()];
+    match data {
+        FuzzData::Raw(data) => data,
+        FuzzData::CreateLockupSchedule { start_timestamp, total_unlock_periods, period_duration, total_lockup_quantity, time_unit, period_type, curve } => {
+            TokenDistributorInstruction::CreateLockupSchedule {
+                start_timestamp,
+                total_unlock_periods,
+                period_duration,
+                total_lockup_quantity,
+                time_unit: time_unit.map(|unit| match unit % 3 {
+                    0 => TimeUnit::UnixTimestamp,
+                    1 => TimeUnit::Slot,
+                    _ => TimeUnit::Epoch,
+                }),
+                period_type: period_type.map(|period_type| match period_type % 3 {
+                    0 => PeriodType::Fixed,
+                    1 => PeriodType::CalendarMonth,
+                    _ => PeriodType::CalendarQuarter,
+                }),
+                curve: curve.map(|(curve_type, points)| VestingCurve {
+                    curve_type: match curve_type % 3 {
+                        0 => CurveType::Linear,
+                        1 => CurveType::Steps,
+                        _ => CurveType::PiecewiseLinear,
+                    },
+                    points: points.into_iter().map(|(period, basis_points)| CurvePoint { period, basis_points }).collect(),
+                }),
+            }.pack()
+        }
+        FuzzData::LockTokens { token_quantity } => TokenDistributorInstruction::LockTokens { token_quantity }.pack(),
+        FuzzData::RedeemTokens { amount } => TokenDistributorInstruction::RedeemTokens { amount }.pack(),
+        FuzzData::LockLamports { lamports } => TokenDistributorInstruction::LockLamports { lamports }.pack(),
+        FuzzData::RedeemLamports { amount } => TokenDistributorInstruction::RedeemLamports { amount }.pack(),
+        FuzzData::MigrateAccount => TokenDistributorInstruction::MigrateAccount {}.pack(),
+        FuzzData::CreateMerkleDistributor { merkle_root, max_num_nodes, token_quantity } => {
+            TokenDistributorInstruction::CreateMerkleDistributor { merkle_root, max_num_nodes, token_quantity }.pack()
+        }
+        FuzzData::ClaimTokens { index, amount, proof } => TokenDistributorInstruction::ClaimTokens { index, amount, proof }.pack(),
+        FuzzData::SetPauseAuthority { pause_authority } => {
+            TokenDistributorInstruction::SetPauseAuthority { pause_authority: account(pause_authority) }.pack()
+        }
+        FuzzData::SetPaused { paused } => TokenDistributorInstruction::SetPaused { paused }.pack(),
+        FuzzData::SetComplianceAuthority { compliance_authority } => {
+            TokenDistributorInstruction::SetComplianceAuthority { compliance_authority: account(compliance_authority) }.pack()
+        }
+        FuzzData::SetFrozen { frozen } => TokenDistributorInstruction::SetFrozen { frozen }.pack(),
+        FuzzData::SetClaimDelegate { claim_delegate, delegate_destination } => TokenDistributorInstruction::SetClaimDelegate {
+            claim_delegate: account(claim_delegate),
+            delegate_destination: account(delegate_destination),
+        }.pack(),
+    }
+}
+
+// lamports of every account instructions can move lamports between, the payer of fees aside
+async fn held_lamports(context: &mut ProgramTestContext, fixture: &Fixture) -> u64 {
+    let mut held = 0u64;
+    for account in fixture.lamport_accounts() {
+        held += context.banks_client.get_balance(account).await.unwrap();
+    }
+    held
+}
+
+// the mint supply never changes, every token stays in one of the fixture token accounts, and
+// lamports only move between the fixture accounts, SOL vaults included
+async fn check_invariants(context: &mut ProgramTestContext, fixture: &Fixture, total_supply: u64, total_lamports: u64) {
+    let mint = get_account(context, &fixture.mint.pubkey()).await;
+    assert_eq!(spl_token::state::Mint::unpack(&mint).unwrap().supply, total_supply);
+
+    let mut held = 0u64;
+    for token_account in fixture.token_accounts() {
+        let data = get_account(context, &token_account).await;
+        held += spl_token::state::Account::unpack(&data).unwrap().amount;
+    }
+    assert_eq!(held, total_supply);
+    assert_eq!(held_lamports(context, fixture).await, total_lamports);
+}
+
+async fn get_account(context: &mut ProgramTestContext, pubkey: &Pubkey) -> Vec<u8> {
+    context.banks_client.get_account(*pubkey).await.unwrap().unwrap().data
+}
+
+async fn process(context: &mut ProgramTestContext, ix: Instruction, signers: &[&Keypair]) -> bool {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(&[ix], Some(&context.payer.pubkey()), &all_signers, blockhash);
+    context.banks_client.process_transaction(transaction).await.is_ok()
+}
+
+async fn create_account(context: &mut ProgramTestContext, account: &Keypair, space: usize, owner: &Pubkey) {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ix = system_instruction::create_account(
+        &context.payer.pubkey(),
+        &account.pubkey(),
+        rent.minimum_balance(space),
+        space as u64,
+        owner,
+    );
+    assert!(process(context, ix, &[account]).await);
+}
+
+async fn create_token_account(context: &mut ProgramTestContext, account: &Keypair, mint: &Pubkey, owner: &Pubkey) {
+    create_account(context, account, spl_token::state::Account::LEN, &spl_token::id()).await;
+    let ix = spl_token::instruction::initialize_account(&spl_token::id(), &account.pubkey(), mint, owner).unwrap();
+    assert!(process(context, ix, &[]).await);
+}
+
+async fn setup(context: &mut ProgramTestContext, program_id: Pubkey) -> Fixture {
+    let payer = context.payer.pubkey();
+    let fixture = Fixture {
+        program_id,
+        initializer: Keypair::new(),
+        schedule: Keypair::new(),
+        mint: Keypair::new(),
+        receivers: LOCKUP_QUANTITIES.iter().map(|_| Keypair::new()).collect(),
+        lockups: LOCKUP_QUANTITIES.iter().map(|_| Keypair::new()).collect(),
+        vaults: LOCKUP_QUANTITIES.iter().map(|_| Keypair::new()).collect(),
+        receiving_token_accounts: LOCKUP_QUANTITIES.iter().map(|_| Keypair::new()).collect(),
+        source_token_account: Keypair::new(),
+        spare_state: Keypair::new(),
+        spare_vault: Keypair::new(),
+        distributor: Keypair::new(),
+        distributor_vault: Keypair::new(),
+        sol_schedule: Keypair::new(),
+        sol_lockup: Keypair::new(),
+    };
+    let total_quantity = LOCKUP_QUANTITIES.iter().sum::<u64>() + DISTRIBUTOR_QUANTITY;
+
+    create_account(context, &fixture.mint, spl_token::state::Mint::LEN, &spl_token::id()).await;
+    let ix = spl_token::instruction::initialize_mint(&spl_token::id(), &fixture.mint.pubkey(), &payer, None, 0).unwrap();
+    assert!(process(context, ix, &[]).await);
+
+    create_account(context, &fixture.schedule, LockupSchedule::LEN, &program_id).await;
+    create_account(context, &fixture.spare_state, Lockup::LEN, &program_id).await;
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let ix = instruction::create_lockup_schedule(
+        &program_id,
+        &fixture.initializer.pubkey(),
+        &fixture.schedule.pubkey(),
+        &fixture.mint.pubkey(),
+        clock.unix_timestamp as u64 + 100,
+        NUMBER_PERIODS,
+        PERIOD_DURATION,
+        total_quantity,
+        TimeUnit::UnixTimestamp,
+        PeriodType::Fixed,
+        VestingCurve::default(),
+    ).unwrap();
+    assert!(process(context, ix, &[&fixture.initializer]).await);
+
+    let mint = fixture.mint.pubkey();
+    let source = fixture.source_token_account.pubkey();
+    create_token_account(context, &fixture.source_token_account, &mint, &fixture.initializer.pubkey()).await;
+    let ix = spl_token::instruction::mint_to(&spl_token::id(), &mint, &source, &payer, &[], total_quantity).unwrap();
+    assert!(process(context, ix, &[]).await);
+
+    for (i, quantity) in LOCKUP_QUANTITIES.iter().enumerate() {
+        let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &fixture.lockups[i].pubkey());
+        create_token_account(context, &fixture.vaults[i], &mint, &token_authority).await;
+        create_token_account(context, &fixture.receiving_token_accounts[i], &mint, &fixture.receivers[i].pubkey()).await;
+
+        create_account(context, &fixture.lockups[i], Lockup::LEN, &program_id).await;
+        let ix = instruction::lock_tokens(
+            &program_id,
+            &fixture.initializer.pubkey(),
+            &fixture.schedule.pubkey(),
+            &fixture.lockups[i].pubkey(),
+            &fixture.receivers[i].pubkey(),
+            &fixture.vaults[i].pubkey(),
+            &spl_token::id(),
+            &source,
+            &mint,
+            *quantity,
+        ).unwrap();
+        assert!(process(context, ix, &[&fixture.initializer]).await);
+    }
+
+    let spare_authority = find_token_authority_address(&program_id, &fixture.spare_state.pubkey()).0;
+    create_token_account(context, &fixture.spare_vault, &mint, &spare_authority).await;
+    let distributor_authority = find_token_authority_address(&program_id, &fixture.distributor.pubkey()).0;
+    create_token_account(context, &fixture.distributor_vault, &mint, &distributor_authority).await;
+    create_account(context, &fixture.distributor, MerkleDistributor::space(1), &program_id).await;
+    let ix = instruction::create_merkle_distributor(
+        &program_id,
+        &fixture.initializer.pubkey(),
+        &fixture.schedule.pubkey(),
+        &fixture.distributor.pubkey(),
+        &fixture.distributor_vault.pubkey(),
+        &spl_token::id(),
+        &source,
+        &mint,
+        leaf_hash(0, &fixture.receivers[1].pubkey(), DISTRIBUTOR_QUANTITY),
+        1,
+        DISTRIBUTOR_QUANTITY,
+    ).unwrap();
+    assert!(process(context, ix, &[&fixture.initializer]).await);
+
+    // the initializer keeps enough lamports for fuzzed locks of lamports too
+    let ix = system_instruction::transfer(&payer, &fixture.initializer.pubkey(), INITIALIZER_LAMPORTS);
+    assert!(process(context, ix, &[]).await);
+    create_account(context, &fixture.sol_schedule, LockupSchedule::LEN, &program_id).await;
+    create_account(context, &fixture.sol_lockup, Lockup::LEN, &program_id).await;
+    let ix = instruction::create_lockup_schedule(
+        &program_id,
+        &fixture.initializer.pubkey(),
+        &fixture.sol_schedule.pubkey(),
+        &native_mint::id(),
+        clock.unix_timestamp as u64 + 100,
+        NUMBER_PERIODS,
+        PERIOD_DURATION,
+        2 * SOL_LOCKUP_LAMPORTS,
+        TimeUnit::UnixTimestamp,
+        PeriodType::Fixed,
+        VestingCurve::default(),
+    ).unwrap();
+    assert!(process(context, ix, &[&fixture.initializer]).await);
+    let ix = instruction::lock_lamports(
+        &program_id,
+        &fixture.initializer.pubkey(),
+        &fixture.sol_schedule.pubkey(),
+        &fixture.sol_lockup.pubkey(),
+        &fixture.receivers[0].pubkey(),
+        SOL_LOCKUP_LAMPORTS,
+    ).unwrap();
+    assert!(process(context, ix, &[&fixture.initializer]).await);
+
+    fixture
+}
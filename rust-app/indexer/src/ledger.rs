@@ -0,0 +1,485 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bpf_program_template::{
+    event::TokenDistributorEvent,
+    state::{AccountType, Lockup, LockupSchedule, LockupScheduleV0, LockupV0, VestingCurve},
+};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use solana_program::{clock::Clock, epoch_schedule::EpochSchedule, program_pack::Pack, pubkey::Pubkey};
+use token_distributor_decoder::parse_events;
+
+use crate::{IndexError, RecordedTransaction};
+
+// u64 amounts are stored bit-cast to SQLite's i64 and summed in Rust, never in SQL
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS transactions (
+        signature TEXT PRIMARY KEY,
+        slot INTEGER NOT NULL,
+        block_time INTEGER NOT NULL,
+        err TEXT
+    );
+    CREATE TABLE IF NOT EXISTS schedules (
+        address TEXT PRIMARY KEY,
+        initializer TEXT NOT NULL,
+        token_mint TEXT NOT NULL,
+        start_timestamp INTEGER NOT NULL,
+        number_periods INTEGER NOT NULL,
+        period_duration INTEGER NOT NULL,
+        total_token_quantity INTEGER NOT NULL,
+        created_at INTEGER NOT NULL,
+        signature TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS schedule_vesting (
+        address TEXT PRIMARY KEY,
+        time_unit BLOB NOT NULL,
+        period_type BLOB NOT NULL,
+        curve BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS lockups (
+        address TEXT PRIMARY KEY,
+        schedule TEXT NOT NULL,
+        wallet TEXT NOT NULL,
+        lockup_token_account TEXT NOT NULL,
+        token_quantity INTEGER NOT NULL,
+        locked_at INTEGER NOT NULL,
+        signature TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS redemptions (
+        signature TEXT NOT NULL,
+        event_index INTEGER NOT NULL,
+        lockup TEXT NOT NULL,
+        schedule TEXT NOT NULL,
+        wallet TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        periods INTEGER NOT NULL,
+        periods_redeemed INTEGER NOT NULL,
+        redeemed_at INTEGER NOT NULL,
+        PRIMARY KEY (signature, event_index)
+    );
+    CREATE TABLE IF NOT EXISTS epoch_schedule (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        slots_per_epoch INTEGER NOT NULL,
+        leader_schedule_slot_offset INTEGER NOT NULL,
+        warmup INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS lockups_wallet ON lockups (wallet, schedule);
+    CREATE INDEX IF NOT EXISTS redemptions_wallet ON redemptions (wallet, schedule);
+";
+
+const INSERT_LOCKUP: &str = "
+    INSERT INTO lockups (address, schedule, wallet, lockup_token_account, token_quantity, locked_at, signature)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+";
+
+// signature recorded for schedules, lockups and redeems backfilled from account state
+const BACKFILL_SIGNATURE: &str = "account-state";
+
+// totals of one wallet's lockups under one schedule at a point in time
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletBalance {
+    pub wallet: String,
+    pub schedule: String,
+    pub locked_amount: u64,
+    pub vested_amount: u64,
+    pub redeemed_amount: u64,
+}
+
+pub struct Ledger {
+    conn: Connection,
+}
+
+impl Ledger {
+    pub fn open(path: &Path) -> Result<Self, IndexError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, IndexError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, IndexError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Ledger { conn })
+    }
+
+    // most recent indexed transaction, where an incremental sync picks up from
+    pub fn latest_signature(&self) -> Result<Option<String>, IndexError> {
+        Ok(self.conn
+            .query_row("SELECT signature FROM transactions ORDER BY slot DESC, rowid DESC LIMIT 1", [], |row| row.get(0))
+            .optional()?)
+    }
+
+    // Index the program's events in a transaction. Returns false if it was already indexed.
+    // Failed transactions are recorded so they are not fetched again, but change nothing.
+    pub fn index_transaction(&mut self, program_id: &Pubkey, transaction: &RecordedTransaction) -> Result<bool, IndexError> {
+        let block_time = transaction.block_time
+            .ok_or_else(|| IndexError::MissingBlockTime(transaction.signature.clone()))?;
+        let events = if transaction.err.is_none() {
+            parse_events(program_id, &transaction.log_messages)?
+        } else {
+            vec![]
+        };
+
+        let db = self.conn.transaction()?;
+        let inserted = db.execute(
+            "INSERT OR IGNORE INTO transactions (signature, slot, block_time, err) VALUES (?1, ?2, ?3, ?4)",
+            params![transaction.signature, to_sql(transaction.slot), block_time, transaction.err],
+        )?;
+        if inserted == 0 {
+            return Ok(false);
+        }
+
+        for (event_index, event) in events.into_iter().enumerate() {
+            match event {
+                TokenDistributorEvent::ScheduleCreated {
+                    lockup_schedule_state, initializer, token_mint, start_timestamp, number_periods, period_duration, total_token_quantity,
+                } => {
+                    db.execute(
+                        "INSERT INTO schedules (address, initializer, token_mint, start_timestamp, number_periods, period_duration, total_token_quantity, created_at, signature)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                        params![
+                            lockup_schedule_state.to_string(),
+                            initializer.to_string(),
+                            token_mint.to_string(),
+                            to_sql(start_timestamp),
+                            to_sql(number_periods),
+                            to_sql(period_duration),
+                            to_sql(total_token_quantity),
+                            block_time,
+                            transaction.signature,
+                        ],
+                    )?;
+                }
+                // time unit, period type and curve are stored Borsh encoded
+                TokenDistributorEvent::ScheduleVestingSet { lockup_schedule_state, time_unit, period_type, curve } => {
+                    db.execute(
+                        "INSERT INTO schedule_vesting (address, time_unit, period_type, curve) VALUES (?1, ?2, ?3, ?4)",
+                        params![
+                            lockup_schedule_state.to_string(),
+                            borsh::to_vec(&time_unit).unwrap(),
+                            borsh::to_vec(&period_type).unwrap(),
+                            borsh::to_vec(&curve).unwrap(),
+                        ],
+                    )?;
+                }
+                TokenDistributorEvent::TokensLocked {
+                    lockup_schedule_state, lockup_state, receiving_account, lockup_token_account, token_quantity,
+                } => {
+                    db.execute(
+                        INSERT_LOCKUP,
+                        params![
+                            lockup_state.to_string(),
+                            lockup_schedule_state.to_string(),
+                            receiving_account.to_string(),
+                            lockup_token_account.to_string(),
+                            to_sql(token_quantity),
+                            block_time,
+                            transaction.signature,
+                        ],
+                    )?;
+                }
+                TokenDistributorEvent::TokensRedeemed {
+                    lockup_schedule_state, lockup_state, receiving_account, amount, periods, periods_redeemed,
+                } => {
+                    db.execute(
+                        "INSERT INTO redemptions (signature, event_index, lockup, schedule, wallet, amount, periods, periods_redeemed, redeemed_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                        params![
+                            transaction.signature,
+                            event_index as i64,
+                            lockup_state.to_string(),
+                            lockup_schedule_state.to_string(),
+                            receiving_account.to_string(),
+                            to_sql(amount),
+                            to_sql(periods),
+                            to_sql(periods_redeemed),
+                            block_time,
+                        ],
+                    )?;
+                }
+                // a merkle claim creates a lockup like a lock does
+                TokenDistributorEvent::TokensClaimed {
+                    lockup_schedule_state, lockup_state, receiving_account, lockup_token_account, token_quantity, ..
+                } => {
+                    db.execute(
+                        INSERT_LOCKUP,
+                        params![
+                            lockup_state.to_string(),
+                            lockup_schedule_state.to_string(),
+                            receiving_account.to_string(),
+                            lockup_token_account.to_string(),
+                            to_sql(token_quantity),
+                            block_time,
+                            transaction.signature,
+                        ],
+                    )?;
+                }
+                // layout upgrades, funding a distributor and authority or delegate changes leave every
+                // wallet's balance as is - vesting keeps accruing while a schedule is paused or a lockup frozen
+                TokenDistributorEvent::AccountMigrated { .. }
+                | TokenDistributorEvent::DistributorCreated { .. }
+                | TokenDistributorEvent::PauseAuthoritySet { .. }
+                | TokenDistributorEvent::PauseSet { .. }
+                | TokenDistributorEvent::ComplianceAuthoritySet { .. }
+                | TokenDistributorEvent::FreezeSet { .. }
+                | TokenDistributorEvent::ClaimDelegateSet { .. } => {}
+            }
+        }
+        db.commit()?;
+        Ok(true)
+    }
+
+    // index transactions in order, returning how many were new
+    pub fn index_transactions(&mut self, program_id: &Pubkey, transactions: &[RecordedTransaction]) -> Result<usize, IndexError> {
+        let mut indexed = 0;
+        for transaction in transactions {
+            if self.index_transaction(program_id, transaction)? {
+                indexed += 1;
+            }
+        }
+        Ok(indexed)
+    }
+
+    // the cluster's epoch schedule, as stored by the last sync
+    pub fn epoch_schedule(&self) -> Result<Option<EpochSchedule>, IndexError> {
+        Ok(self.conn
+            .query_row(
+                "SELECT slots_per_epoch, leader_schedule_slot_offset, warmup FROM epoch_schedule WHERE id = 0",
+                [],
+                |row| Ok(EpochSchedule::custom(from_sql(row.get(0)?), from_sql(row.get(1)?), row.get(2)?)),
+            )
+            .optional()?)
+    }
+
+    pub fn set_epoch_schedule(&self, epoch_schedule: &EpochSchedule) -> Result<(), IndexError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO epoch_schedule (id, slots_per_epoch, leader_schedule_slot_offset, warmup) VALUES (0, ?1, ?2, ?3)",
+            params![
+                to_sql(epoch_schedule.slots_per_epoch),
+                to_sql(epoch_schedule.leader_schedule_slot_offset),
+                epoch_schedule.warmup,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Clock as of `timestamp` for schedules in slots or epochs: the slot of the latest transaction
+    // indexed by then, and its epoch under the cluster's epoch schedule
+    pub fn clock_at(&self, timestamp: i64, epoch_schedule: &EpochSchedule) -> Result<Clock, IndexError> {
+        let slot: Option<i64> = self.conn.query_row(
+            "SELECT MAX(slot) FROM transactions WHERE block_time <= ?1",
+            params![timestamp],
+            |row| row.get(0),
+        )?;
+        let slot = slot.map(from_sql).unwrap_or(0);
+        Ok(Clock {
+            slot,
+            epoch: epoch_schedule.get_epoch(slot),
+            unix_timestamp: timestamp,
+            ..Clock::default()
+        })
+    }
+
+    // Backfill what the events miss from the program's current accounts: schedules and lockups
+    // created before the program logged events, and amounts redeemed without a TokensRedeemed
+    // event. When they happened is unknown, so they count from unix timestamp 0. Run right after
+    // a sync, as redeems are backfilled as the lockup's redeemed amount less those indexed.
+    // Version 0 accounts are read as their migrated layout. Returns how many rows were backfilled.
+    pub fn backfill_accounts(&mut self, accounts: &[(Pubkey, Vec<u8>)]) -> Result<usize, IndexError> {
+        let invalid = |address: &Pubkey| IndexError::InvalidAccount(address.to_string());
+        // version 0 accounts have no tag, their first byte is is_initialized, so go by length first
+        let mut schedules = BTreeMap::new();
+        for (address, data) in accounts {
+            if data.len() == LockupV0::LEN {
+                continue;
+            } else if data.len() == LockupScheduleV0::LEN {
+                let schedule = LockupScheduleV0::unpack(data).map_err(|_| invalid(address))?;
+                if schedule.is_initialized {
+                    schedules.insert(*address, schedule.migrate());
+                }
+            } else if data.first() == Some(&(AccountType::LockupSchedule as u8)) {
+                schedules.insert(*address, LockupSchedule::unpack(data).map_err(|_| invalid(address))?);
+            }
+        }
+        let mut lockups = vec![];
+        for (address, data) in accounts {
+            if data.len() == LockupV0::LEN {
+                let lockup = LockupV0::unpack(data).map_err(|_| invalid(address))?;
+                if lockup.is_initialized {
+                    let schedule = schedules.get(&lockup.lockup_schedule_state)
+                        .ok_or_else(|| IndexError::MissingSchedule(lockup.lockup_schedule_state.to_string()))?;
+                    lockups.push((*address, lockup.migrate(schedule)?));
+                }
+            } else if data.len() != LockupScheduleV0::LEN && data.first() == Some(&(AccountType::Lockup as u8)) {
+                lockups.push((*address, Lockup::unpack(data).map_err(|_| invalid(address))?));
+            }
+        }
+
+        let db = self.conn.transaction()?;
+        let mut backfilled = 0;
+        for (address, schedule) in &schedules {
+            backfilled += db.execute(
+                "INSERT OR IGNORE INTO schedules (address, initializer, token_mint, start_timestamp, number_periods, period_duration, total_token_quantity, created_at, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0, ?8)",
+                params![
+                    address.to_string(),
+                    schedule.initializer.to_string(),
+                    schedule.token_mint.to_string(),
+                    to_sql(schedule.start_timestamp),
+                    to_sql(schedule.number_periods),
+                    to_sql(schedule.period_duration),
+                    to_sql(schedule.total_token_quantity),
+                    BACKFILL_SIGNATURE,
+                ],
+            )?;
+            // also fills in the vesting terms of schedules created before ScheduleVestingSet
+            let curve = VestingCurve { curve_type: schedule.curve_type, points: schedule.curve_points().to_vec() };
+            db.execute(
+                "INSERT OR IGNORE INTO schedule_vesting (address, time_unit, period_type, curve) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    address.to_string(),
+                    borsh::to_vec(&schedule.time_unit).unwrap(),
+                    borsh::to_vec(&schedule.period_type).unwrap(),
+                    borsh::to_vec(&curve).unwrap(),
+                ],
+            )?;
+        }
+        for (address, lockup) in &lockups {
+            backfilled += db.execute(
+                "INSERT OR IGNORE INTO lockups (address, schedule, wallet, lockup_token_account, token_quantity, locked_at, signature)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6)",
+                params![
+                    address.to_string(),
+                    lockup.lockup_schedule_state.to_string(),
+                    lockup.receiving_account.to_string(),
+                    lockup.lockup_token_account.to_string(),
+                    to_sql(lockup.token_quantity),
+                    BACKFILL_SIGNATURE,
+                ],
+            )?;
+            // one redemption per lockup holds whatever the indexed redeems do not account for, updated
+            // by later backfills
+            let signature = format!("{}:{}", BACKFILL_SIGNATURE, address);
+            let indexed: Vec<i64> = db
+                .prepare("SELECT amount FROM redemptions WHERE lockup = ?1 AND signature != ?2")?
+                .query_map(params![address.to_string(), signature], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            let indexed = indexed.into_iter().map(from_sql).fold(0u64, u64::saturating_add);
+            let missing = lockup.redeemed_amount.saturating_sub(indexed);
+            let backfilled_before: Option<i64> = db
+                .query_row("SELECT amount FROM redemptions WHERE signature = ?1", params![signature], |row| row.get(0))
+                .optional()?;
+            if missing != backfilled_before.map(from_sql).unwrap_or(0) {
+                backfilled += db.execute(
+                    "INSERT OR REPLACE INTO redemptions (signature, event_index, lockup, schedule, wallet, amount, periods, periods_redeemed, redeemed_at)
+                     VALUES (?1, 0, ?2, ?3, ?4, ?5, ?6, ?6, 0)",
+                    params![
+                        signature,
+                        address.to_string(),
+                        lockup.lockup_schedule_state.to_string(),
+                        lockup.receiving_account.to_string(),
+                        to_sql(missing),
+                        to_sql(lockup.periods_redeemed),
+                    ],
+                )?;
+            }
+        }
+        db.commit()?;
+        Ok(backfilled)
+    }
+
+    // Locked, vested and redeemed totals per wallet per schedule as of `clock`, counting only
+    // lockups and redeems that landed by its unix timestamp. Each schedule vests by the clock field
+    // of its time unit. Optionally filtered to one wallet or schedule.
+    pub fn balances_at(
+        &self,
+        clock: &Clock,
+        wallet: Option<&Pubkey>,
+        schedule: Option<&Pubkey>,
+    ) -> Result<Vec<WalletBalance>, IndexError> {
+        let timestamp = clock.unix_timestamp;
+        let wallet = wallet.map(|wallet| wallet.to_string());
+        let schedule = schedule.map(|schedule| schedule.to_string());
+        let schedules = self.schedules()?;
+        let mut balances: BTreeMap<(String, String), WalletBalance> = BTreeMap::new();
+
+        let mut statement = self.conn.prepare(
+            "SELECT wallet, schedule, token_quantity FROM lockups
+             WHERE locked_at <= ?1 AND (?2 IS NULL OR wallet = ?2) AND (?3 IS NULL OR schedule = ?3)",
+        )?;
+        let mut rows = statement.query(params![timestamp, wallet, schedule])?;
+        while let Some(row) = rows.next()? {
+            let (wallet, schedule, token_quantity): (String, String, i64) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            let token_quantity = from_sql(token_quantity);
+            let lockup_schedule = schedules.get(&schedule).ok_or_else(|| IndexError::MissingSchedule(schedule.clone()))?;
+            // a clock before the epoch or slot 0 has vested nothing
+            let current_time = lockup_schedule.time_unit.current_time(clock).unwrap_or(0);
+            let vested_amount = lockup_schedule.vested_amount(token_quantity, current_time)?;
+            let balance = balances.entry((wallet.clone(), schedule.clone())).or_insert_with(|| WalletBalance::new(wallet, schedule));
+            balance.locked_amount = balance.locked_amount.saturating_add(token_quantity);
+            balance.vested_amount = balance.vested_amount.saturating_add(vested_amount);
+        }
+
+        let mut statement = self.conn.prepare(
+            "SELECT wallet, schedule, amount FROM redemptions
+             WHERE redeemed_at <= ?1 AND (?2 IS NULL OR wallet = ?2) AND (?3 IS NULL OR schedule = ?3)",
+        )?;
+        let mut rows = statement.query(params![timestamp, wallet, schedule])?;
+        while let Some(row) = rows.next()? {
+            let (wallet, schedule, amount): (String, String, i64) = (row.get(0)?, row.get(1)?, row.get(2)?);
+            let balance = balances.entry((wallet.clone(), schedule.clone())).or_insert_with(|| WalletBalance::new(wallet, schedule));
+            balance.redeemed_amount = balance.redeemed_amount.saturating_add(from_sql(amount));
+        }
+
+        Ok(balances.into_values().collect())
+    }
+
+    // every indexed schedule with the fields the vesting math needs
+    fn schedules(&self) -> Result<BTreeMap<String, LockupSchedule>, IndexError> {
+        let mut statement = self.conn.prepare(
+            "SELECT schedules.address, start_timestamp, number_periods, period_duration, total_token_quantity, time_unit, period_type, curve
+             FROM schedules LEFT JOIN schedule_vesting ON schedule_vesting.address = schedules.address",
+        )?;
+        let mut rows = statement.query([])?;
+        let mut schedules = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            let address: String = row.get(0)?;
+            let mut schedule = LockupSchedule::new(
+                Pubkey::default(),
+                Pubkey::default(),
+                from_sql(row.get(1)?),
+                from_sql(row.get(2)?),
+                from_sql(row.get(3)?),
+                from_sql(row.get(4)?),
+            );
+            // schedules created before ScheduleVestingSet vest linearly over fixed periods of unix seconds
+            let time_unit: Option<Vec<u8>> = row.get(5)?;
+            let period_type: Option<Vec<u8>> = row.get(6)?;
+            let curve: Option<Vec<u8>> = row.get(7)?;
+            if let (Some(time_unit), Some(period_type), Some(curve)) = (time_unit, period_type, curve) {
+                schedule.time_unit = borsh::from_slice(&time_unit).map_err(|_| IndexError::InvalidSchedule(address.clone()))?;
+                schedule.period_type = borsh::from_slice(&period_type).map_err(|_| IndexError::InvalidSchedule(address.clone()))?;
+                let curve: VestingCurve = borsh::from_slice(&curve).map_err(|_| IndexError::InvalidSchedule(address.clone()))?;
+                schedule.set_curve(&curve)?;
+            }
+            schedules.insert(address, schedule);
+        }
+        Ok(schedules)
+    }
+}
+
+impl WalletBalance {
+    fn new(wallet: String, schedule: String) -> Self {
+        WalletBalance { wallet, schedule, locked_amount: 0, vested_amount: 0, redeemed_amount: 0 }
+    }
+}
+
+fn to_sql(value: u64) -> i64 {
+    value as i64
+}
+
+fn from_sql(value: i64) -> u64 {
+    value as u64
+}
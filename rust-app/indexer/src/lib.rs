@@ -0,0 +1,74 @@
+// Builds a ledger of every schedule, lock and redeem in an embedded SQLite database from the
+// program's events in transaction logs, backfilled from account state for what predates the
+// events, and answers vested/redeemed totals at a point in time.
+
+pub mod ledger;
+pub mod rpc;
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::ClientError;
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+use token_distributor_decoder::DecodeError;
+
+pub use ledger::{Ledger, WalletBalance};
+
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error("database error: {0}")]
+    Database(#[from] rusqlite::Error),
+    #[error("failed to decode events: {0}")]
+    Decode(#[from] DecodeError),
+    #[error("RPC error: {0}")]
+    Rpc(Box<ClientError>),
+    #[error("failed to read transactions: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid transaction fixture: {0}")]
+    Fixture(#[from] serde_json::Error),
+    #[error("vesting math failed: {0}")]
+    Vesting(#[from] ProgramError),
+    #[error("invalid signature {0}")]
+    InvalidSignature(String),
+    #[error("transaction {0} has no block time")]
+    MissingBlockTime(String),
+    #[error("lockup schedule {0} has not been indexed")]
+    MissingSchedule(String),
+    #[error("invalid vesting terms indexed for lockup schedule {0}")]
+    InvalidSchedule(String),
+    #[error("program account {0} holds invalid state")]
+    InvalidAccount(String),
+    #[error("no epoch schedule stored, sync the ledger or pass --url")]
+    MissingEpochSchedule,
+}
+
+// boxed as the client error is much larger than the other variants
+impl From<ClientError> for IndexError {
+    fn from(err: ClientError) -> Self {
+        IndexError::Rpc(Box::new(err))
+    }
+}
+
+// the parts of a confirmed transaction the indexer needs, as fetched over RPC or recorded to JSON
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedTransaction {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    // the transaction error, None when it succeeded
+    #[serde(default)]
+    pub err: Option<String>,
+    pub log_messages: Vec<String>,
+}
+
+// read a JSON array of recorded transactions, oldest first
+pub fn read_fixture(path: &Path) -> Result<Vec<RecordedTransaction>, IndexError> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+pub fn write_fixture(path: &Path, transactions: &[RecordedTransaction]) -> Result<(), IndexError> {
+    fs::write(path, serde_json::to_vec_pretty(transactions)?)?;
+    Ok(())
+}
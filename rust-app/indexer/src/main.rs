@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use token_distributor_indexer::{read_fixture, rpc::{fetch_accounts, fetch_transactions}, write_fixture, IndexError, Ledger};
+
+#[derive(Parser)]
+#[command(name = "token-distributor-indexer", version, about = "Index token distributor lockups and redeems into SQLite")]
+struct Cli {
+    /// SQLite database holding the ledger, created if missing
+    #[arg(long, default_value = "token-distributor.sqlite")]
+    db: PathBuf,
+
+    /// Address of the deployed token distributor program
+    #[arg(long, env = "TOKEN_DISTRIBUTOR_PROGRAM_ID")]
+    program_id: Pubkey,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Fetch and index every program transaction newer than the last one indexed
+    Sync {
+        /// JSON RPC URL of the cluster
+        #[arg(long, short = 'u', default_value = "http://localhost:8899")]
+        url: String,
+        /// Also write the fetched transactions to a JSON fixture
+        #[arg(long)]
+        record: Option<PathBuf>,
+    },
+    /// Index transactions from a recorded JSON fixture
+    Import {
+        fixture: PathBuf,
+    },
+    /// Backfill schedules, lockups and redeems that predate the program's events from account state
+    Backfill {
+        /// JSON RPC URL of the cluster
+        #[arg(long, short = 'u', default_value = "http://localhost:8899")]
+        url: String,
+    },
+    /// Print locked, vested and redeemed totals per wallet per schedule as JSON
+    Balances {
+        /// Unix timestamp to total at
+        #[arg(long)]
+        at: i64,
+        /// Slot to vest slot schedules at, defaults to the latest slot indexed by --at
+        #[arg(long)]
+        slot: Option<u64>,
+        /// Epoch to vest epoch schedules at, defaults to the epoch of the slot
+        #[arg(long)]
+        epoch: Option<u64>,
+        #[arg(long)]
+        wallet: Option<Pubkey>,
+        #[arg(long)]
+        schedule: Option<Pubkey>,
+        /// JSON RPC URL to read the epoch schedule from, defaults to the one stored by the last sync
+        #[arg(long, short = 'u')]
+        url: Option<String>,
+    },
+}
+
+fn run(cli: Cli) -> Result<(), IndexError> {
+    let mut ledger = Ledger::open(&cli.db)?;
+    match cli.command {
+        Command::Sync { url, record } => {
+            let rpc_client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+            ledger.set_epoch_schedule(&rpc_client.get_epoch_schedule()?)?;
+            let until = ledger.latest_signature()?;
+            let transactions = fetch_transactions(&rpc_client, &cli.program_id, until.as_deref())?;
+            if let Some(path) = record {
+                write_fixture(&path, &transactions)?;
+            }
+            let indexed = ledger.index_transactions(&cli.program_id, &transactions)?;
+            println!("Indexed {} new transactions into {}", indexed, cli.db.display());
+        }
+        Command::Import { fixture } => {
+            let transactions = read_fixture(&fixture)?;
+            let indexed = ledger.index_transactions(&cli.program_id, &transactions)?;
+            println!("Indexed {} of {} transactions into {}", indexed, transactions.len(), cli.db.display());
+        }
+        Command::Backfill { url } => {
+            let rpc_client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+            let accounts = fetch_accounts(&rpc_client, &cli.program_id)?;
+            let backfilled = ledger.backfill_accounts(&accounts)?;
+            println!("Backfilled {} rows from {} accounts into {}", backfilled, accounts.len(), cli.db.display());
+        }
+        Command::Balances { at, slot, epoch, wallet, schedule, url } => {
+            let epoch_schedule = match url {
+                Some(url) => {
+                    let rpc_client = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+                    let epoch_schedule = rpc_client.get_epoch_schedule()?;
+                    ledger.set_epoch_schedule(&epoch_schedule)?;
+                    epoch_schedule
+                }
+                None => ledger.epoch_schedule()?.ok_or(IndexError::MissingEpochSchedule)?,
+            };
+            let mut clock = ledger.clock_at(at, &epoch_schedule)?;
+            if let Some(slot) = slot {
+                clock.slot = slot;
+                clock.epoch = epoch_schedule.get_epoch(slot);
+            }
+            if let Some(epoch) = epoch {
+                clock.epoch = epoch;
+            }
+            let balances = ledger.balances_at(&clock, wallet.as_ref(), schedule.as_ref())?;
+            println!("{}", serde_json::to_string_pretty(&balances)?);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
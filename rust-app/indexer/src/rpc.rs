@@ -0,0 +1,68 @@
+use std::str::FromStr;
+
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::{IndexError, RecordedTransaction};
+
+fn parse_signature(signature: &str) -> Result<Signature, IndexError> {
+    Signature::from_str(signature).map_err(|_| IndexError::InvalidSignature(signature.to_string()))
+}
+
+// Fetch every transaction mentioning the program, oldest first. With `until` set, only
+// transactions newer than that signature are fetched.
+pub fn fetch_transactions(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    until: Option<&str>,
+) -> Result<Vec<RecordedTransaction>, IndexError> {
+    let until = until.map(parse_signature).transpose()?;
+    let commitment = CommitmentConfig::confirmed();
+
+    // signatures come back newest first, a page at a time
+    let mut signatures = vec![];
+    let mut before = None;
+    loop {
+        let page = rpc_client.get_signatures_for_address_with_config(
+            program_id,
+            GetConfirmedSignaturesForAddress2Config { before, until, limit: None, commitment: Some(commitment) },
+        )?;
+        let Some(last) = page.last() else { break };
+        before = Some(parse_signature(&last.signature)?);
+        signatures.extend(page);
+    }
+    signatures.reverse();
+
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Json),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+    let mut transactions = vec![];
+    for status in signatures {
+        let signature = parse_signature(&status.signature)?;
+        let transaction = rpc_client.get_transaction_with_config(&signature, config)?;
+        let meta = transaction.transaction.meta;
+        transactions.push(RecordedTransaction {
+            signature: status.signature,
+            slot: transaction.slot,
+            block_time: transaction.block_time,
+            err: meta.as_ref().and_then(|meta| meta.err.as_ref()).map(|err| err.to_string()),
+            log_messages: meta.and_then(|meta| meta.log_messages.into()).unwrap_or_default(),
+        });
+    }
+    Ok(transactions)
+}
+
+// every account the program owns with its data, for backfilling what the events miss
+pub fn fetch_accounts(rpc_client: &RpcClient, program_id: &Pubkey) -> Result<Vec<(Pubkey, Vec<u8>)>, IndexError> {
+    Ok(rpc_client
+        .get_program_accounts(program_id)?
+        .into_iter()
+        .map(|(address, account)| (address, account.data))
+        .collect())
+}
@@ -0,0 +1,226 @@
+use std::{path::Path, str::FromStr};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bpf_program_template::{
+    event::{TokenDistributorEvent, EVENT_LOG_PREFIX},
+    state::{CurvePoint, CurveType, Lockup, LockupScheduleV0, LockupV0, PeriodType, TimeUnit, VestingCurve},
+};
+use solana_program::{epoch_schedule::EpochSchedule, program_pack::Pack, pubkey::Pubkey};
+use token_distributor_indexer::{read_fixture, Ledger, RecordedTransaction, WalletBalance};
+
+// recorded from a program-test run: a schedule starting at START with 4 periods of 1000s,
+// lockups of 4_000 for WALLET_A and 2_003 for WALLET_B, WALLET_A redeeming at START + 1000,
+// a failed redeem, then both redeeming at START + 2500
+const PROGRAM_ID: &str = "Dist1bUTor1111111111111111111111111111111111";
+const SCHEDULE: &str = "CC2zXou7eGo3CschXA4qaFq9U4tYkkY5EyXbYrZtsKS2";
+const WALLET_A: &str = "Dbm3tqLu3QDeLvczhYd1eKd9HUYGbCex1e5LDhphZQum";
+const WALLET_B: &str = "7Vj4wRQNyhgu8hfhrJ8a82rj8V8qh3MFAnvnM49uZyZh";
+const START: i64 = 1_792_391_067;
+
+fn indexed_ledger() -> Ledger {
+    let program_id = Pubkey::from_str(PROGRAM_ID).unwrap();
+    let transactions = read_fixture(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/transactions.json")).unwrap();
+    let mut ledger = Ledger::open_in_memory().unwrap();
+    assert_eq!(ledger.index_transactions(&program_id, &transactions).unwrap(), transactions.len());
+    // indexing again changes nothing
+    assert_eq!(ledger.index_transactions(&program_id, &transactions).unwrap(), 0);
+    assert_eq!(ledger.latest_signature().unwrap(), Some(transactions.last().unwrap().signature.clone()));
+    ledger
+}
+
+fn balance(wallet: &str, locked_amount: u64, vested_amount: u64, redeemed_amount: u64) -> WalletBalance {
+    WalletBalance {
+        wallet: wallet.to_string(),
+        schedule: SCHEDULE.to_string(),
+        locked_amount,
+        vested_amount,
+        redeemed_amount,
+    }
+}
+
+#[test]
+fn test_balances_at() {
+    let ledger = indexed_ledger();
+
+    assert_eq!(ledger.balances_at(&ledger.clock_at(START - 1_000, &EpochSchedule::without_warmup()).unwrap(), None, None).unwrap(), vec![]);
+    assert_eq!(
+        ledger.balances_at(&ledger.clock_at(START + 1_500, &EpochSchedule::without_warmup()).unwrap(), None, None).unwrap(),
+        vec![balance(WALLET_B, 2_003, 500, 0), balance(WALLET_A, 4_000, 1_000, 1_000)],
+    );
+    assert_eq!(
+        ledger.balances_at(&ledger.clock_at(START + 2_500, &EpochSchedule::without_warmup()).unwrap(), None, None).unwrap(),
+        vec![balance(WALLET_B, 2_003, 1_000, 1_000), balance(WALLET_A, 4_000, 2_000, 2_000)],
+    );
+    // the final period vests the rounding remainder
+    assert_eq!(
+        ledger.balances_at(&ledger.clock_at(START + 4_000, &EpochSchedule::without_warmup()).unwrap(), None, None).unwrap(),
+        vec![balance(WALLET_B, 2_003, 2_003, 1_000), balance(WALLET_A, 4_000, 4_000, 2_000)],
+    );
+}
+
+#[test]
+fn test_balances_filtered() {
+    let ledger = indexed_ledger();
+    let wallet = Pubkey::from_str(WALLET_A).unwrap();
+    let schedule = Pubkey::from_str(SCHEDULE).unwrap();
+
+    assert_eq!(
+        ledger.balances_at(&ledger.clock_at(START + 2_500, &EpochSchedule::without_warmup()).unwrap(), Some(&wallet), Some(&schedule)).unwrap(),
+        vec![balance(WALLET_A, 4_000, 2_000, 2_000)],
+    );
+    assert_eq!(ledger.balances_at(&ledger.clock_at(START + 2_500, &EpochSchedule::without_warmup()).unwrap(), None, Some(&Pubkey::new_unique())).unwrap(), vec![]);
+}
+
+// a successful transaction of the program logging events
+fn transaction(program_id: &Pubkey, slot: u64, block_time: i64, events: &[TokenDistributorEvent]) -> RecordedTransaction {
+    let mut log_messages = vec![format!("Program {} invoke [1]", program_id)];
+    log_messages.extend(events.iter().map(|event| format!("Program log: {}{}", EVENT_LOG_PREFIX, STANDARD.encode(event.pack()))));
+    log_messages.push(format!("Program {} success", program_id));
+    RecordedTransaction { signature: format!("signature-{}", slot), slot, block_time: Some(block_time), err: None, log_messages }
+}
+
+#[test]
+fn test_balances_slot_steps_schedule() {
+    let program_id = Pubkey::new_unique();
+    let schedule = Pubkey::new_unique();
+    let lockup = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    // 4 periods of 10 slots from slot 100, vesting 10%, 20%, 50% then 100%
+    let curve = VestingCurve {
+        curve_type: CurveType::Steps,
+        points: vec![
+            CurvePoint { period: 1, basis_points: 1_000 },
+            CurvePoint { period: 2, basis_points: 2_000 },
+            CurvePoint { period: 3, basis_points: 5_000 },
+            CurvePoint { period: 4, basis_points: 10_000 },
+        ],
+    };
+    let transactions = vec![
+        transaction(&program_id, 90, 1_000, &[
+            TokenDistributorEvent::ScheduleCreated {
+                lockup_schedule_state: schedule,
+                initializer: Pubkey::new_unique(),
+                token_mint: Pubkey::new_unique(),
+                start_timestamp: 100,
+                number_periods: 4,
+                period_duration: 10,
+                total_token_quantity: 1_000,
+            },
+            TokenDistributorEvent::ScheduleVestingSet {
+                lockup_schedule_state: schedule,
+                time_unit: TimeUnit::Slot,
+                period_type: PeriodType::Fixed,
+                curve,
+            },
+        ]),
+        transaction(&program_id, 95, 1_002, &[TokenDistributorEvent::TokensLocked {
+            lockup_schedule_state: schedule,
+            lockup_state: lockup,
+            receiving_account: wallet,
+            lockup_token_account: Pubkey::new_unique(),
+            token_quantity: 1_000,
+        }]),
+        transaction(&program_id, 125, 1_050, &[TokenDistributorEvent::TokensRedeemed {
+            lockup_schedule_state: schedule,
+            lockup_state: lockup,
+            receiving_account: wallet,
+            amount: 200,
+            periods: 2,
+            periods_redeemed: 2,
+        }]),
+    ];
+    let mut ledger = Ledger::open_in_memory().unwrap();
+    ledger.index_transactions(&program_id, &transactions).unwrap();
+    let balance = |locked_amount, vested_amount, redeemed_amount| WalletBalance {
+        wallet: wallet.to_string(),
+        schedule: schedule.to_string(),
+        locked_amount,
+        vested_amount,
+        redeemed_amount,
+    };
+
+    // by the redeem at slot 125 two periods unlocked 20%, not the 50% of a linear schedule in seconds
+    let clock = ledger.clock_at(1_050, &EpochSchedule::without_warmup()).unwrap();
+    assert_eq!(clock.slot, 125);
+    assert_eq!(ledger.balances_at(&clock, None, None).unwrap(), vec![balance(1_000, 200, 200)]);
+
+    // before the start slot nothing vested, at the last period everything
+    let mut clock = ledger.clock_at(1_002, &EpochSchedule::without_warmup()).unwrap();
+    assert_eq!(ledger.balances_at(&clock, None, None).unwrap(), vec![balance(1_000, 0, 0)]);
+    clock.slot = 140;
+    assert_eq!(ledger.balances_at(&clock, None, None).unwrap(), vec![balance(1_000, 1_000, 0)]);
+}
+
+#[test]
+fn test_clock_at_epoch_schedule() {
+    let program_id = Pubkey::new_unique();
+    let mut ledger = Ledger::open_in_memory().unwrap();
+    ledger.index_transactions(&program_id, &[transaction(&program_id, 100, 1_000, &[])]).unwrap();
+    assert_eq!(ledger.epoch_schedule().unwrap(), None);
+
+    // warming up to 128 slot epochs through epochs of 32 and 64 slots puts slot 100 in epoch 2, not 0
+    let epoch_schedule = EpochSchedule::custom(128, 128, true);
+    ledger.set_epoch_schedule(&epoch_schedule).unwrap();
+    assert_eq!(ledger.epoch_schedule().unwrap(), Some(epoch_schedule));
+    assert_eq!(ledger.clock_at(1_000, &epoch_schedule).unwrap().epoch, 2);
+    assert_eq!(ledger.clock_at(1_000, &EpochSchedule::custom(128, 128, false)).unwrap().epoch, 0);
+}
+
+#[test]
+fn test_backfill_accounts() {
+    let program_id = Pubkey::new_unique();
+    let schedule = Pubkey::new_unique();
+    let legacy_lockup = Pubkey::new_unique();
+    let lockup = Pubkey::new_unique();
+    let legacy_wallet = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    // a version 0 schedule of 4 periods of 1000s from 1_000, with a version 0 lockup that redeemed
+    // one period and a current lockup that redeemed 500, only 250 of it with an event
+    let schedule_data = borsh::to_vec(&LockupScheduleV0 {
+        is_initialized: true,
+        initializer: Pubkey::new_unique(),
+        token_mint: Pubkey::new_unique(),
+        start_timestamp: 1_000,
+        number_periods: 4,
+        period_duration: 1_000,
+        total_token_quantity: 5_000,
+        token_quantity_locked: 5_000,
+    }).unwrap();
+    let legacy_lockup_data = borsh::to_vec(&LockupV0 {
+        is_initialized: true,
+        lockup_schedule_state: schedule,
+        receiving_account: legacy_wallet,
+        lockup_token_account: Pubkey::new_unique(),
+        token_quantity: 4_000,
+        periods_redeemed: 1,
+    }).unwrap();
+    let mut lockup_data = vec![0; Lockup::LEN];
+    Lockup { redeemed_amount: 500, ..Lockup::new(schedule, wallet, Pubkey::new_unique(), 1_000) }.pack_into_slice(&mut lockup_data);
+    let accounts = vec![(legacy_lockup, legacy_lockup_data), (schedule, schedule_data), (lockup, lockup_data)];
+
+    let mut ledger = Ledger::open_in_memory().unwrap();
+    ledger.index_transactions(&program_id, &[transaction(&program_id, 10, 2_100, &[TokenDistributorEvent::TokensRedeemed {
+        lockup_schedule_state: schedule,
+        lockup_state: lockup,
+        receiving_account: wallet,
+        amount: 250,
+        periods: 1,
+        periods_redeemed: 1,
+    }])]).unwrap();
+    // the schedule, both lockups and a redemption for each
+    assert_eq!(ledger.backfill_accounts(&accounts).unwrap(), 5);
+    // backfilling again changes nothing
+    assert_eq!(ledger.backfill_accounts(&accounts).unwrap(), 0);
+
+    let balance = |wallet: &Pubkey, locked_amount, vested_amount, redeemed_amount| WalletBalance {
+        wallet: wallet.to_string(),
+        schedule: schedule.to_string(),
+        locked_amount,
+        vested_amount,
+        redeemed_amount,
+    };
+    let mut expected = vec![balance(&legacy_wallet, 4_000, 2_000, 1_000), balance(&wallet, 1_000, 500, 500)];
+    expected.sort_by(|a, b| a.wallet.cmp(&b.wallet));
+    let clock = ledger.clock_at(3_000, &EpochSchedule::without_warmup()).unwrap();
+    assert_eq!(ledger.balances_at(&clock, None, None).unwrap(), expected);
+}
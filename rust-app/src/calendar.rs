@@ -0,0 +1,72 @@
+// UTC calendar date math for calendar-month vesting periods, in plain integer arithmetic so it runs
+// on-chain without std time libraries. Days are counted from 1970-01-01 in the proleptic Gregorian
+// calendar, converted to and from civil dates with Howard Hinnant's algorithms.
+
+use std::{cmp, convert::TryFrom};
+
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// days since 1970-01-01 to (year, month, day)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+// (year, month, day) to days since 1970-01-01
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// unix timestamp `months` calendar months after `timestamp`, at the same time of day and on the
+// same day of the month, or the last day of the month if it is shorter. None on overflow.
+pub fn add_months(timestamp: u64, months: u64) -> Option<u64> {
+    let seconds_of_day = timestamp % SECONDS_PER_DAY;
+    let (year, month, day) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    let month_index = (year * 12 + month as i64 - 1).checked_add(u32::try_from(months).ok()? as i64)?;
+    let year = month_index.div_euclid(12);
+    let month = month_index.rem_euclid(12) as u32 + 1;
+    let day = cmp::min(day, days_in_month(year, month));
+    u64::try_from(days_from_civil(year, month, day))
+        .ok()?
+        .checked_mul(SECONDS_PER_DAY)?
+        .checked_add(seconds_of_day)
+}
+
+// whole calendar months from `start` to `timestamp` - a month counts once its anniversary of
+// start, as add_months gives it, is reached. 0 if timestamp isn't after start.
+pub fn months_elapsed(start: u64, timestamp: u64) -> u64 {
+    if timestamp <= start {
+        return 0;
+    }
+    let (start_year, start_month, _) = civil_from_days((start / SECONDS_PER_DAY) as i64);
+    let (year, month, _) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    let months = ((year * 12 + month as i64) - (start_year * 12 + start_month as i64)) as u64;
+    // the anniversary in timestamp's month may still be ahead of it
+    match add_months(start, months) {
+        Some(anniversary) if anniversary <= timestamp => months,
+        _ => months.saturating_sub(1),
+    }
+}
@@ -18,7 +18,7 @@ pub enum TokenDistributorError {
         // Invalid mint
         #[error("Invalid mint")]
         InvalidMint,
-        // Expected amount mismatch - wrong number of tokens in temporary token account
+        // Expected amount mismatch - no tokens reached the lockup token account
         #[error("Expected amount mismatch")]
         ExpectedAmountMismatch,
         // Unauthorized receiving account
@@ -30,6 +30,51 @@ pub enum TokenDistributorError {
         // Incorrect account owner
         #[error("Incorrect account owner")]
         IncorrectOwner,
+        // Math overflow - checked arithmetic failed
+        #[error("Math overflow")]
+        MathOverflow,
+        // Invalid period config - zero or out of range periods/duration
+        #[error("Invalid period config")]
+        InvalidPeriodConfig,
+        // Invalid account type - state account tag doesn't match the expected account
+        #[error("Invalid account type")]
+        InvalidAccountType,
+        // Invalid state version - state account layout version isn't supported
+        #[error("Invalid state version")]
+        InvalidStateVersion,
+        // Invalid lockup token account - not empty, not owned by the PDA or has a delegate or close authority
+        #[error("Invalid lockup token account")]
+        InvalidLockupTokenAccount,
+        // Invalid merkle proof - the claim isn't a leaf of the distributor's merkle root
+        #[error("Invalid merkle proof")]
+        InvalidMerkleProof,
+        // Already claimed - the claim index is set in the distributor's bitmap
+        #[error("Already claimed")]
+        AlreadyClaimed,
+        // Claim exceeds distributor - more than the distributor was funded with would be claimed
+        #[error("Claim exceeds distributor")]
+        ClaimExceedsDistributor,
+        // Invalid distributor config - zero or too many claims for the bitmap
+        #[error("Invalid distributor config")]
+        InvalidDistributorConfig,
+        // Schedule paused - the schedule's pause authority has paused locks, claims and redeems
+        #[error("Schedule paused")]
+        SchedulePaused,
+        // Lockup frozen - the schedule's compliance authority has frozen redeems of the lockup
+        #[error("Lockup frozen")]
+        LockupFrozen,
+        // Invalid destination - a claim delegate may only redeem to the receiving account's token accounts or its allowed destination
+        #[error("Invalid destination")]
+        InvalidDestination,
+        // Invalid redeem amount - zero or more than the lockup's redeemable amount
+        #[error("Invalid redeem amount")]
+        InvalidRedeemAmount,
+        // Invalid curve - curve points out of order, decreasing, not ending at 100% on the last period, or given for a linear curve
+        #[error("Invalid curve")]
+        InvalidCurve,
+        // Lockup redeemed - everything locked has been redeemed already
+        #[error("Lockup redeemed")]
+        LockupRedeemed,
 }
 
 impl From<TokenDistributorError> for ProgramError {
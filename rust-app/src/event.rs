@@ -0,0 +1,123 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{msg, pubkey::Pubkey};
+
+use crate::state::{AccountType, PeriodType, TimeUnit, VestingCurve};
+
+// version of the event layout, written before every event
+pub const EVENT_VERSION: u8 = 1;
+
+// marks an event in the program logs, which show it as `Program log: event: <base64>`
+pub const EVENT_LOG_PREFIX: &str = "event: ";
+
+// Structured events emitted on every state change, for indexers.
+// Each event is logged as base64 of the Borsh encoded event version followed by the event.
+// Variants must only ever be appended.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum TokenDistributorEvent {
+
+    ScheduleCreated {
+        lockup_schedule_state: Pubkey,
+        initializer: Pubkey,
+        token_mint: Pubkey,
+        start_timestamp: u64,
+        number_periods: u64,
+        period_duration: u64,
+        total_token_quantity: u64,
+    },
+
+    TokensLocked {
+        lockup_schedule_state: Pubkey,
+        lockup_state: Pubkey,
+        receiving_account: Pubkey,
+        lockup_token_account: Pubkey,
+        token_quantity: u64,
+    },
+
+    TokensRedeemed {
+        lockup_schedule_state: Pubkey,
+        lockup_state: Pubkey,
+        receiving_account: Pubkey,
+        amount: u64,
+        // no. periods redeemed by this instruction and in total
+        periods: u64,
+        periods_redeemed: u64,
+    },
+
+    AccountMigrated {
+        account: Pubkey,
+        account_type: AccountType,
+    },
+
+    DistributorCreated {
+        merkle_distributor: Pubkey,
+        lockup_schedule_state: Pubkey,
+        distributor_token_account: Pubkey,
+        merkle_root: [u8; 32],
+        max_num_nodes: u64,
+        total_amount: u64,
+    },
+
+    // a claim creates a lockup like TokensLocked does
+    TokensClaimed {
+        merkle_distributor: Pubkey,
+        lockup_schedule_state: Pubkey,
+        lockup_state: Pubkey,
+        receiving_account: Pubkey,
+        lockup_token_account: Pubkey,
+        index: u64,
+        token_quantity: u64,
+    },
+
+    // pause_authority is Pubkey::default() when removed
+    PauseAuthoritySet {
+        lockup_schedule_state: Pubkey,
+        pause_authority: Pubkey,
+    },
+
+    PauseSet {
+        lockup_schedule_state: Pubkey,
+        authority: Pubkey,
+        paused: bool,
+    },
+
+    // compliance_authority is Pubkey::default() when removed
+    ComplianceAuthoritySet {
+        lockup_schedule_state: Pubkey,
+        compliance_authority: Pubkey,
+    },
+
+    FreezeSet {
+        lockup_schedule_state: Pubkey,
+        lockup_state: Pubkey,
+        authority: Pubkey,
+        frozen: bool,
+    },
+
+    // claim_delegate and delegate_destination are Pubkey::default() when removed
+    ClaimDelegateSet {
+        lockup_state: Pubkey,
+        receiving_account: Pubkey,
+        claim_delegate: Pubkey,
+        delegate_destination: Pubkey,
+    },
+
+    // emitted right after ScheduleCreated with the rest of the vesting terms, which that event
+    // predates; schedules created before it vest linearly over fixed periods of unix seconds
+    ScheduleVestingSet {
+        lockup_schedule_state: Pubkey,
+        time_unit: TimeUnit,
+        period_type: PeriodType,
+        curve: VestingCurve,
+    },
+}
+
+impl TokenDistributorEvent {
+    pub fn pack(&self) -> Vec<u8> {
+        borsh::to_vec(&(EVENT_VERSION, self)).unwrap()
+    }
+
+    pub fn emit(&self) {
+        msg!("{}{}", EVENT_LOG_PREFIX, STANDARD.encode(self.pack()));
+    }
+}
@@ -1,9 +1,32 @@
-use solana_program::program_error::ProgramError;
-use crate::error::TokenDistributorError::InvalidInstruction;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+    sysvar,
+};
+use crate::{error::TokenDistributorError::InvalidInstruction, find_sol_vault_address, find_token_authority_address, state::{PeriodType, TimeUnit, VestingCurve}};
 use std::convert::TryInto;
 
+// lengths of the original hand-packed data of tags 0/1/2: the tag followed by u64s
+const LEGACY_CREATE_LOCKUP_SCHEDULE_LEN: usize = 33;
+const LEGACY_LOCK_TOKENS_LEN: usize = 9;
+const LEGACY_REDEEM_TOKENS_LEN: usize = 1;
+
+// Instruction data is Borsh encoded: a u8 variant tag followed by the variant's fields.
+// The tag is the instruction version - variants must only ever be appended, never reordered,
+// so the data of tags 0/1/2 stays byte-for-byte identical to the original hand-packed data. Fields
+// are only ever appended to a variant as an Option, so the original data still decodes as None.
+// Only the data is kept: the account lists of LockTokens and RedeemTokens have changed since, so
+// transactions built for the original program fail against this one.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
 pub enum TokenDistributorInstruction {
 
+    // Start and period duration are in time_unit (unix seconds if None) and read from the matching
+    // clock field for the whole life of the schedule. With a calendar period_type the period duration
+    // is in months or quarters instead, each period ending on a UTC anniversary of the start. The
+    // curve (linear if None) sets how much vests as each period unlocks.
     // Accounts expected:
     // 0. [signer] initializer
     // 1. [writable] lockup schedule state (empty)
@@ -13,51 +36,214 @@ pub enum TokenDistributorInstruction {
     CreateLockupSchedule {
         start_timestamp: u64,
         total_unlock_periods: u64,
-        period_duration: u64, // in seconds
+        period_duration: u64,
         total_lockup_quantity: u64,
+        time_unit: Option<TimeUnit>,
+        period_type: Option<PeriodType>,
+        curve: Option<VestingCurve>,
     },
 
+    // Moves token_quantity from the source token account into the lockup token account with
+    // transfer_checked. The amount that arrives, after any Token-2022 transfer fee, is what's locked.
     // Accounts expected:
     // 0. [signer] initializer
     // 1. [writable] lockup schedule state
     // 2. [writable] lockup state account (empty)
     // 3. [] token receiver main Solana account
-    // 4. [writable] temporary lockup token account
-    // 5. [] token program (transfer ownership of temp token account to PDA)
+    // 4. [writable] lockup token account (empty, owned by the lockup's program-derived address)
+    // 5. [] token program (spl-token or Token-2022)
     // 6. [] clock sysvar
     // 7. [] rent sysvar
+    // 8. [writable] source token account owned by the initializer
+    // 9. [] token mint
     LockTokens {
         token_quantity: u64,
     },
 
+    // Send the unlocked tokens not yet redeemed with transfer_checked, or only amount of them, leaving
+    // the rest to redeem later. The lockup token account must be the lockup's and the mint the
+    // schedule's. A claim delegate can sign instead of the receiver, but only to the receiver's own
+    // token accounts or the lockup's delegate destination.
     // Accounts expected:
-    // 0. [signer] token receiver's main Solana account
+    // 0. [signer] token receiver's main Solana account, or the lockup's claim delegate
     // 1. [] lockup schedule state
     // 2. [writable] lockup state
-    // 3. [writable] lockup token account
+    // 3. [writable] lockup token account of the lockup state
     // 4. [writable] receiving token account
-    // 5. [signer] program-derived-address (owns lockup token account)
-    // 6. [] token program
+    // 5. [] program-derived address owning the lockup token account, signs via invoke_signed - the
+    //       lockup's own, or the one shared by version 0 lockups
+    // 6. [] token program (spl-token or Token-2022)
     // 7. [] clock sysvar
+    // 8. [] token mint of the lockup schedule
     RedeemTokens {
+        amount: Option<u64>,
+    },
+
+    // Rewrite a state account in an older layout in the current layout, growing it if needed.
+    // Fields missing from the old layout are derived from the old fields or set to their defaults -
+    // a lockup's redeemed amount from its periods redeemed, vested along its lockup schedule.
+    // Anyone can call this - the payer covers any extra rent. Already migrated accounts are left as is.
+    // Accounts expected:
+    // 0. [signer, writable] payer
+    // 1. [writable] lockup schedule, lockup or merkle distributor state
+    // 2. [] system program
+    // 3. [] rent sysvar
+    // 4. [] lockup schedule state of the lockup, in any layout - only when migrating a lockup
+    MigrateAccount {
+
+    },
+
+    // Fund a single distributor token account for an airdrop of (index, wallet, amount) claims
+    // committed to by merkle_root. Every claim vests under the lockup schedule. Like LockTokens,
+    // token_quantity moves from the source with transfer_checked and what arrives is claimable.
+    // Accounts expected:
+    // 0. [signer] initializer of the lockup schedule
+    // 1. [] lockup schedule state
+    // 2. [writable] merkle distributor state (empty, MerkleDistributor::space(max_num_nodes) bytes)
+    // 3. [writable] distributor token account (empty, owned by the distributor's program-derived address)
+    // 4. [] token program (spl-token or Token-2022)
+    // 5. [writable] source token account owned by the initializer
+    // 6. [] token mint
+    // 7. [] rent sysvar
+    CreateMerkleDistributor {
+        merkle_root: [u8; 32],
+        max_num_nodes: u64,
+        token_quantity: u64,
+    },
+
+    // Claim the leaf (index, claimant, amount) of a merkle distributor with its proof. Moves amount
+    // from the distributor token account into a new lockup of the claimant, redeemed as usual.
+    // Accounts expected:
+    // 0. [signer] claimant - the wallet of the leaf, becomes the lockup's receiving account
+    // 1. [writable] merkle distributor state
+    // 2. [writable] lockup schedule state of the distributor
+    // 3. [writable] lockup state (empty)
+    // 4. [writable] lockup token account (empty, owned by the lockup's program-derived address)
+    // 5. [writable] distributor token account
+    // 6. [] the distributor's program-derived address (owns its token account, signs via invoke_signed)
+    // 7. [] token program (spl-token or Token-2022)
+    // 8. [] token mint
+    // 9. [] rent sysvar
+    ClaimTokens {
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    },
+
+    // Set or remove (Pubkey::default()) the account that can pause the schedule besides the initializer.
+    // Accounts expected:
+    // 0. [signer] initializer of the lockup schedule
+    // 1. [writable] lockup schedule state
+    SetPauseAuthority {
+        pause_authority: Pubkey,
+    },
+
+    // Pause or resume locks, claims and redeems under the schedule. Periods keep unlocking while
+    // paused, so a redeem after resuming pays out everything unlocked in the meantime.
+    // Accounts expected:
+    // 0. [signer] initializer or pause authority of the lockup schedule
+    // 1. [writable] lockup schedule state
+    SetPaused {
+        paused: bool,
+    },
+
+    // Set or remove (Pubkey::default()) the account that can freeze and thaw lockups of the schedule.
+    // Accounts expected:
+    // 0. [signer] initializer of the lockup schedule
+    // 1. [writable] lockup schedule state
+    SetComplianceAuthority {
+        compliance_authority: Pubkey,
+    },
 
+    // Freeze or thaw redeems of a single lockup, e.g. during a sanctions review. Periods keep
+    // unlocking while frozen, so a redeem after thawing pays out everything unlocked in the meantime.
+    // Accounts expected:
+    // 0. [signer] compliance authority of the lockup schedule
+    // 1. [] lockup schedule state
+    // 2. [writable] lockup state
+    SetFrozen {
+        frozen: bool,
+    },
+
+    // Set or remove (Pubkey::default()) the account that can redeem on the receiver's behalf, and
+    // one token account besides the receiver's own it may redeem to.
+    // Accounts expected:
+    // 0. [signer] receiving account of the lockup
+    // 1. [writable] lockup state
+    SetClaimDelegate {
+        claim_delegate: Pubkey,
+        delegate_destination: Pubkey,
+    },
+
+    // Like LockTokens for a schedule of the native mint, but locks lamports without wrapping them.
+    // The lamports move from the initializer into a new SOL vault at the program-derived address of
+    // the lockup state, which the program owns and keeps rent exempt. The vault is written to the
+    // lockup as its lockup token account. Lamports already at the vault's address count towards
+    // what it needs, the initializer only tops up the rest.
+    // Accounts expected:
+    // 0. [signer, writable] initializer of the lockup schedule, paying the lamports and vault rent
+    // 1. [writable] lockup schedule state
+    // 2. [writable] lockup state account (empty)
+    // 3. [] receiver main Solana account
+    // 4. [writable] SOL vault of the lockup state (uncreated)
+    // 5. [] system program
+    // 6. [] clock sysvar
+    // 7. [] rent sysvar
+    LockLamports {
+        lamports: u64,
+    },
+
+    // Like RedeemTokens for a lockup made by LockLamports. The lamports are sent to a system account,
+    // or, when the token program is given, to a native (wrapped SOL) token account which is then
+    // synced. Once every lamport locked is redeemed the vault is closed: its rent and any lamports
+    // sent to it go to the destination too.
+    // Accounts expected:
+    // 0. [signer] receiver main Solana account, or the lockup's claim delegate
+    // 1. [] lockup schedule state
+    // 2. [writable] lockup state
+    // 3. [writable] SOL vault of the lockup state
+    // 4. [writable] receiving account, or native token account of the receiver
+    // 5. [] clock sysvar
+    // 6. [] (optional) token program (spl-token or Token-2022) owning the native token account
+    RedeemLamports {
+        amount: Option<u64>,
     }
 }
 
 impl TokenDistributorInstruction {
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        // strict Borsh decode first, then fall back to the legacy layout only for data of exactly a
+        // legacy length - anything else, e.g. a truncated Some(amount), must not decode as a default
+        Self::try_from_slice(input).or_else(|_| match (input.first(), input.len()) {
+            (Some(0), LEGACY_CREATE_LOCKUP_SCHEDULE_LEN)
+            | (Some(1), LEGACY_LOCK_TOKENS_LEN)
+            | (Some(2), LEGACY_REDEEM_TOKENS_LEN) => Self::unpack_legacy(input),
+            _ => Err(ProgramError::InvalidInstructionData),
+        })
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        borsh::to_vec(self).unwrap()
+    }
+
+    // decode the original tag 0/1/2 data with fixed byte offsets - the accounts must be in the
+    // current lists
+    fn unpack_legacy(input: &[u8]) -> Result<Self, ProgramError> {
         let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
         Ok(match tag {
             0 => Self::CreateLockupSchedule {
                 start_timestamp: Self::unpack_start_timestamp(rest)?,
                 total_unlock_periods: Self::unpack_total_unlock_periods(rest)?,
                 period_duration: Self::unpack_period_duration(rest)?,
-                total_lockup_quantity: Self::unpack_total_lockup_quantity(rest)?
+                total_lockup_quantity: Self::unpack_total_lockup_quantity(rest)?,
+                time_unit: None,
+                period_type: None,
+                curve: None,
             }, 
             1 => Self::LockTokens {
                 token_quantity: Self::unpack_token_quantity(rest)?
             },
-            2 => Self::RedeemTokens {},
+            2 => Self::RedeemTokens { amount: None },
             _ => return Err(InvalidInstruction.into())
         })
     }
@@ -85,4 +271,347 @@ impl TokenDistributorInstruction {
         let token_quantity = input.get(..8).and_then(|slice| slice.try_into().ok()).map(u64::from_le_bytes).ok_or(InvalidInstruction)?;
         Ok(token_quantity)
     }
-}
\ No newline at end of file
+}
+
+// INSTRUCTION BUILDERS
+
+// Creates a `CreateLockupSchedule` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn create_lockup_schedule(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    token_mint: &Pubkey,
+    start_timestamp: u64,
+    total_unlock_periods: u64,
+    period_duration: u64,
+    total_lockup_quantity: u64,
+    time_unit: TimeUnit,
+    period_type: PeriodType,
+    curve: VestingCurve,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::CreateLockupSchedule {
+        start_timestamp,
+        total_unlock_periods,
+        period_duration,
+        total_lockup_quantity,
+        time_unit: Some(time_unit),
+        period_type: Some(period_type),
+        curve: Some(curve),
+    }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*lockup_schedule_state, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `LockTokens` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn lock_tokens(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    lockup_state: &Pubkey,
+    receiver: &Pubkey,
+    lockup_token_account: &Pubkey,
+    token_program_id: &Pubkey,
+    source_token_account: &Pubkey,
+    token_mint: &Pubkey,
+    token_quantity: u64,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::check_spl_token_program_account(token_program_id)?;
+    let data = TokenDistributorInstruction::LockTokens { token_quantity }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*lockup_schedule_state, false),
+        AccountMeta::new(*lockup_state, false),
+        AccountMeta::new_readonly(*receiver, false),
+        AccountMeta::new(*lockup_token_account, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new(*source_token_account, false),
+        AccountMeta::new_readonly(*token_mint, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `RedeemTokens` instruction. The receiver is the signer, the lockup's receiving account
+// or its claim delegate. The lockup token authority owns the lockup token account: the lockup's
+// find_token_authority_address, or find_pda_address for a version 0 lockup. An amount of None
+// redeems everything unlocked.
+#[allow(clippy::too_many_arguments)]
+pub fn redeem_tokens(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    lockup_state: &Pubkey,
+    lockup_token_account: &Pubkey,
+    lockup_token_authority: &Pubkey,
+    receiving_token_account: &Pubkey,
+    token_program_id: &Pubkey,
+    token_mint: &Pubkey,
+    amount: Option<u64>,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::check_spl_token_program_account(token_program_id)?;
+    let data = TokenDistributorInstruction::RedeemTokens { amount }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*receiver, true),
+        AccountMeta::new_readonly(*lockup_schedule_state, false),
+        AccountMeta::new(*lockup_state, false),
+        AccountMeta::new(*lockup_token_account, false),
+        AccountMeta::new(*receiving_token_account, false),
+        AccountMeta::new_readonly(*lockup_token_authority, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(*token_mint, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `MigrateAccount` instruction
+pub fn migrate_account(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    state_account: &Pubkey,
+    lockup_schedule_state: Option<&Pubkey>,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::MigrateAccount {}.pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*state_account, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    if let Some(lockup_schedule_state) = lockup_schedule_state {
+        accounts.push(AccountMeta::new_readonly(*lockup_schedule_state, false));
+    }
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `CreateMerkleDistributor` instruction
+#[allow(clippy::too_many_arguments)]
+pub fn create_merkle_distributor(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    merkle_distributor: &Pubkey,
+    distributor_token_account: &Pubkey,
+    token_program_id: &Pubkey,
+    source_token_account: &Pubkey,
+    token_mint: &Pubkey,
+    merkle_root: [u8; 32],
+    max_num_nodes: u64,
+    token_quantity: u64,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::check_spl_token_program_account(token_program_id)?;
+    let data = TokenDistributorInstruction::CreateMerkleDistributor { merkle_root, max_num_nodes, token_quantity }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new_readonly(*lockup_schedule_state, false),
+        AccountMeta::new(*merkle_distributor, false),
+        AccountMeta::new(*distributor_token_account, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new(*source_token_account, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `ClaimTokens` instruction, deriving the PDA that owns the distributor token account
+#[allow(clippy::too_many_arguments)]
+pub fn claim_tokens(
+    program_id: &Pubkey,
+    claimant: &Pubkey,
+    merkle_distributor: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    lockup_state: &Pubkey,
+    lockup_token_account: &Pubkey,
+    distributor_token_account: &Pubkey,
+    token_program_id: &Pubkey,
+    token_mint: &Pubkey,
+    index: u64,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> Result<Instruction, ProgramError> {
+    spl_token_2022::check_spl_token_program_account(token_program_id)?;
+    let data = TokenDistributorInstruction::ClaimTokens { index, amount, proof }.pack();
+    let (pda, _bump_seed) = find_token_authority_address(program_id, merkle_distributor);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*claimant, true),
+        AccountMeta::new(*merkle_distributor, false),
+        AccountMeta::new(*lockup_schedule_state, false),
+        AccountMeta::new(*lockup_state, false),
+        AccountMeta::new(*lockup_token_account, false),
+        AccountMeta::new(*distributor_token_account, false),
+        AccountMeta::new_readonly(pda, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `SetPauseAuthority` instruction
+pub fn set_pause_authority(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    pause_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::SetPauseAuthority { pause_authority: *pause_authority }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*lockup_schedule_state, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `SetPaused` instruction
+pub fn set_paused(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    paused: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::SetPaused { paused }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*lockup_schedule_state, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `SetComplianceAuthority` instruction
+pub fn set_compliance_authority(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    compliance_authority: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::SetComplianceAuthority { compliance_authority: *compliance_authority }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*initializer, true),
+        AccountMeta::new(*lockup_schedule_state, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `SetFrozen` instruction
+pub fn set_frozen(
+    program_id: &Pubkey,
+    compliance_authority: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    lockup_state: &Pubkey,
+    frozen: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::SetFrozen { frozen }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*compliance_authority, true),
+        AccountMeta::new_readonly(*lockup_schedule_state, false),
+        AccountMeta::new(*lockup_state, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `SetClaimDelegate` instruction
+pub fn set_claim_delegate(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    lockup_state: &Pubkey,
+    claim_delegate: &Pubkey,
+    delegate_destination: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::SetClaimDelegate {
+        claim_delegate: *claim_delegate,
+        delegate_destination: *delegate_destination,
+    }.pack();
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*receiver, true),
+        AccountMeta::new(*lockup_state, false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `LockLamports` instruction, deriving the SOL vault of the lockup state
+pub fn lock_lamports(
+    program_id: &Pubkey,
+    initializer: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    lockup_state: &Pubkey,
+    receiver: &Pubkey,
+    lamports: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::LockLamports { lamports }.pack();
+    let (sol_vault, _bump_seed) = find_sol_vault_address(program_id, lockup_state);
+
+    let accounts = vec![
+        AccountMeta::new(*initializer, true),
+        AccountMeta::new(*lockup_schedule_state, false),
+        AccountMeta::new(*lockup_state, false),
+        AccountMeta::new_readonly(*receiver, false),
+        AccountMeta::new(sol_vault, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
+
+// Creates a `RedeemLamports` instruction, deriving the SOL vault of the lockup state. With a token
+// program the destination is a native token account of it, otherwise it receives plain lamports.
+pub fn redeem_lamports(
+    program_id: &Pubkey,
+    receiver: &Pubkey,
+    lockup_schedule_state: &Pubkey,
+    lockup_state: &Pubkey,
+    destination: &Pubkey,
+    token_program_id: Option<&Pubkey>,
+    amount: Option<u64>,
+) -> Result<Instruction, ProgramError> {
+    let data = TokenDistributorInstruction::RedeemLamports { amount }.pack();
+    let (sol_vault, _bump_seed) = find_sol_vault_address(program_id, lockup_state);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*receiver, true),
+        AccountMeta::new_readonly(*lockup_schedule_state, false),
+        AccountMeta::new(*lockup_state, false),
+        AccountMeta::new(sol_vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    if let Some(token_program_id) = token_program_id {
+        spl_token_2022::check_spl_token_program_account(token_program_id)?;
+        accounts.push(AccountMeta::new_readonly(*token_program_id, false));
+    }
+
+    Ok(Instruction { program_id: *program_id, accounts, data })
+}
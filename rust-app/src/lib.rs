@@ -1,5 +1,44 @@
+#[cfg(not(feature = "no-entrypoint"))]
 pub mod entrypoint;
+pub mod calendar;
 pub mod instruction;
 pub mod error;
+pub mod event;
+pub mod merkle;
 pub mod processor;
-pub mod state;
\ No newline at end of file
+pub mod state;
+
+use solana_program::pubkey::Pubkey;
+
+// seed of the program-derived addresses that own the lockup and distributor token accounts
+pub const PDA_SEED: &[u8] = b"tokenDistributor";
+
+// derive the program-derived address (and bump seed) that owned the lockup token accounts of
+// version 0 lockups, shared by all of them
+pub fn find_pda_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PDA_SEED], program_id)
+}
+
+// derive the program-derived address (and bump seed) that owns the token account of a single
+// lockup or merkle distributor state, so no token account can back two of them
+pub fn find_token_authority_address(program_id: &Pubkey, state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PDA_SEED, state.as_ref()], program_id)
+}
+
+// seed, with the lockup state, of the program-owned account holding a native SOL lockup's lamports
+pub const SOL_VAULT_SEED: &[u8] = b"solVault";
+
+// the spl-token native mint, which Token-2022 replaces with its own
+pub mod native_mint {
+    solana_program::declare_id!("So11111111111111111111111111111111111111112");
+}
+
+// whether a schedule's mint is native SOL, of spl-token or Token-2022
+pub fn is_native_mint(mint: &Pubkey) -> bool {
+    *mint == native_mint::id() || *mint == spl_token_2022::native_mint::id()
+}
+
+// derive the SOL vault (and bump seed) of a lockup of native SOL
+pub fn find_sol_vault_address(program_id: &Pubkey, lockup_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SOL_VAULT_SEED, lockup_state.as_ref()], program_id)
+}
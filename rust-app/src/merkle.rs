@@ -0,0 +1,65 @@
+// Hashing of the merkle tree a MerkleDistributor's root commits to. Each leaf is one claim of
+// (index, wallet, amount). Leaves and inner nodes are keccak256 hashes with different prefixes,
+// so an inner node can never be passed off as a leaf, and each pair of children is hashed in
+// sorted order, so a proof is just the sibling hashes from the leaf up to the root.
+
+use solana_program::{keccak, pubkey::Pubkey};
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf_hash(index: u64, wallet: &Pubkey, amount: u64) -> [u8; 32] {
+    keccak::hashv(&[LEAF_PREFIX, &index.to_le_bytes(), wallet.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if left <= right { (left, right) } else { (right, left) };
+    keccak::hashv(&[NODE_PREFIX, first, second]).to_bytes()
+}
+
+// check the proof leads from the leaf to the root
+pub fn verify_proof(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed_root = proof.iter().fold(leaf, |node, sibling| node_hash(&node, sibling));
+    computed_root == *root
+}
+
+// Tree over a distributor's leaves, in claim index order, as built off-chain to get the root
+// and each leaf's proof. An odd node out at any level is carried up to the next unchanged.
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<[u8; 32]>) -> Self {
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let next = layers[layers.len() - 1]
+                .chunks(2)
+                .map(|pair| if let [left, right] = pair { node_hash(left, right) } else { pair[0] })
+                .collect();
+            layers.push(next);
+        }
+        MerkleTree { layers }
+    }
+
+    // root of the tree, all zeroes for a tree without leaves
+    pub fn root(&self) -> [u8; 32] {
+        self.layers[self.layers.len() - 1].first().copied().unwrap_or_default()
+    }
+
+    // sibling hashes from the leaf at `index` up to the root, None if there is no such leaf
+    pub fn proof(&self, index: usize) -> Option<Vec<[u8; 32]>> {
+        if index >= self.layers[0].len() {
+            return None;
+        }
+        let mut proof = vec![];
+        let mut index = index;
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
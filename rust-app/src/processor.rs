@@ -1,20 +1,35 @@
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
+    instruction::AccountMeta,
     program_error::ProgramError,
     msg,
     pubkey::Pubkey,
     program_pack::{Pack},
     sysvar::{rent::Rent, Sysvar},
     program::{invoke, invoke_signed},
-    clock::{Clock}
+    clock::{Clock},
+    system_instruction,
 };
 
-use std::cmp;
-
-use spl_token::state::Account as TokenAccount;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account as TokenAccount, Mint},
+};
 
-use crate::{instruction::TokenDistributorInstruction, state::LockupSchedule, state::Lockup, error::TokenDistributorError};
+use crate::{
+    instruction::TokenDistributorInstruction,
+    state::{AccountType, Lockup, LockupSchedule, LockupScheduleV0, LockupV0, MerkleDistributor, PeriodType, TimeUnit, VestingCurve, MAX_DISTRIBUTOR_NODES, STATE_VERSION},
+    error::TokenDistributorError,
+    event::TokenDistributorEvent,
+    merkle,
+    find_pda_address,
+    find_token_authority_address,
+    find_sol_vault_address,
+    is_native_mint,
+    PDA_SEED,
+    SOL_VAULT_SEED,
+};
 
 pub struct Processor;
 impl Processor {
@@ -22,27 +37,81 @@ impl Processor {
         
         let instruction = TokenDistributorInstruction::unpack(instruction_data)?;
         match instruction {
-            TokenDistributorInstruction::CreateLockupSchedule {start_timestamp, total_unlock_periods, period_duration, total_lockup_quantity } => {
+            TokenDistributorInstruction::CreateLockupSchedule {start_timestamp, total_unlock_periods, period_duration, total_lockup_quantity, time_unit, period_type, curve } => {
                 msg!("Instruction: CreateLockupSchedule");
-                Self::process_create_lockup_schedule(accounts, start_timestamp, total_unlock_periods, period_duration, total_lockup_quantity, program_id)
+                Self::process_create_lockup_schedule(
+                    accounts,
+                    start_timestamp,
+                    total_unlock_periods,
+                    period_duration,
+                    total_lockup_quantity,
+                    time_unit.unwrap_or_default(),
+                    period_type.unwrap_or_default(),
+                    &curve.unwrap_or_default(),
+                    program_id,
+                )
             },
             TokenDistributorInstruction::LockTokens {token_quantity} => {
                 msg!("Instruction: LockTokens");
                 Self::process_lock_tokens(accounts, token_quantity, program_id)
             },
-            TokenDistributorInstruction::RedeemTokens {} => {
+            TokenDistributorInstruction::RedeemTokens {amount} => {
                 msg!("Instruction: RedeemTokens");
-                Self::process_redeem_tokens(accounts, program_id)
+                Self::process_redeem_tokens(accounts, amount, program_id)
+            },
+            TokenDistributorInstruction::MigrateAccount {} => {
+                msg!("Instruction: MigrateAccount");
+                Self::process_migrate_account(accounts, program_id)
+            },
+            TokenDistributorInstruction::CreateMerkleDistributor {merkle_root, max_num_nodes, token_quantity} => {
+                msg!("Instruction: CreateMerkleDistributor");
+                Self::process_create_merkle_distributor(accounts, merkle_root, max_num_nodes, token_quantity, program_id)
+            },
+            TokenDistributorInstruction::ClaimTokens {index, amount, proof} => {
+                msg!("Instruction: ClaimTokens");
+                Self::process_claim_tokens(accounts, index, amount, &proof, program_id)
+            },
+            TokenDistributorInstruction::SetPauseAuthority {pause_authority} => {
+                msg!("Instruction: SetPauseAuthority");
+                Self::process_set_pause_authority(accounts, pause_authority, program_id)
+            },
+            TokenDistributorInstruction::SetPaused {paused} => {
+                msg!("Instruction: SetPaused");
+                Self::process_set_paused(accounts, paused, program_id)
+            },
+            TokenDistributorInstruction::SetComplianceAuthority {compliance_authority} => {
+                msg!("Instruction: SetComplianceAuthority");
+                Self::process_set_compliance_authority(accounts, compliance_authority, program_id)
+            },
+            TokenDistributorInstruction::SetFrozen {frozen} => {
+                msg!("Instruction: SetFrozen");
+                Self::process_set_frozen(accounts, frozen, program_id)
+            },
+            TokenDistributorInstruction::SetClaimDelegate {claim_delegate, delegate_destination} => {
+                msg!("Instruction: SetClaimDelegate");
+                Self::process_set_claim_delegate(accounts, claim_delegate, delegate_destination, program_id)
+            },
+            TokenDistributorInstruction::LockLamports {lamports} => {
+                msg!("Instruction: LockLamports");
+                Self::process_lock_lamports(accounts, lamports, program_id)
+            },
+            TokenDistributorInstruction::RedeemLamports {amount} => {
+                msg!("Instruction: RedeemLamports");
+                Self::process_redeem_lamports(accounts, amount, program_id)
             }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn process_create_lockup_schedule(
         accounts: &[AccountInfo],
         start_timestamp: u64,
         total_unlock_periods: u64,
         period_duration: u64,
         total_lockup_quantity: u64,
+        time_unit: TimeUnit,
+        period_type: PeriodType,
+        curve: &VestingCurve,
         program_id: &Pubkey,
     ) -> ProgramResult {
 
@@ -59,8 +128,8 @@ impl Processor {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // check the start timestamp is after current timestamp
-        let current_timestamp = clock.unix_timestamp as u64;
+        // check the start is after the current time, in the schedule's time unit
+        let current_timestamp = time_unit.current_time(clock)?;
         if current_timestamp > start_timestamp {
             return Err(TokenDistributorError::InvalidStartTimestamp.into());
         }
@@ -75,18 +144,44 @@ impl Processor {
             return Err(TokenDistributorError::NotRentExempt.into());
         }
 
-        // write lockup information to state account
-        let mut lockup_schedule_state = LockupSchedule::unpack_unchecked(&lockup_schedule_state_account.data.borrow())?;
-        lockup_schedule_state.is_initialized = true;
-        lockup_schedule_state.initializer = *initializer.key;
-        lockup_schedule_state.token_mint = *token_mint.key;
-        lockup_schedule_state.start_timestamp = start_timestamp;
-        lockup_schedule_state.number_periods = total_unlock_periods;
-        lockup_schedule_state.period_duration = period_duration;
-        lockup_schedule_state.total_token_quantity = total_lockup_quantity;
-        lockup_schedule_state.token_quantity_locked = 0;
+        // write lockup information to state account, which must not hold a schedule already
+        if LockupSchedule::unpack_unchecked(&lockup_schedule_state_account.data.borrow())?.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        let mut lockup_schedule_state = LockupSchedule::new(
+            *initializer.key,
+            *token_mint.key,
+            start_timestamp,
+            total_unlock_periods,
+            period_duration,
+            total_lockup_quantity,
+        );
+        lockup_schedule_state.time_unit = time_unit;
+        lockup_schedule_state.period_type = period_type;
+        lockup_schedule_state.set_curve(curve)?;
+
+        // check periods and duration are non-zero, the schedule end doesn't overflow and the curve
+        // only increases to 100% at the last period
+        lockup_schedule_state.validate_period_config()?;
+        lockup_schedule_state.validate_curve()?;
         LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
 
+        TokenDistributorEvent::ScheduleCreated {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            initializer: *initializer.key,
+            token_mint: *token_mint.key,
+            start_timestamp,
+            number_periods: total_unlock_periods,
+            period_duration,
+            total_token_quantity: total_lockup_quantity,
+        }.emit();
+        TokenDistributorEvent::ScheduleVestingSet {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            time_unit,
+            period_type,
+            curve: curve.clone(),
+        }.emit();
+
         Ok(())
     }
 
@@ -103,18 +198,23 @@ impl Processor {
         let lockup_schedule_state_account = next_account_info(account_info_iter)?;
         let empty_state_account = next_account_info(account_info_iter)?;
         let receiver_account = next_account_info(account_info_iter)?;
-        let temp_token_account = next_account_info(account_info_iter)?;
+        let lockup_token_account = next_account_info(account_info_iter)?;
         let token_program = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
         let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        let source_token_account = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
 
         // check the initializer signed the tx
         if !initializer.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // check token program has correct program id
-        spl_token::check_program_account(token_program.key)?;
+        // check token program is spl-token or Token-2022, and owns the mint and lockup token account
+        spl_token_2022::check_spl_token_program_account(token_program.key)?;
+        if token_mint.owner != token_program.key || lockup_token_account.owner != token_program.key {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
 
         // check program owns the state accounts
         if lockup_schedule_state_account.owner != program_id {
@@ -129,6 +229,11 @@ impl Processor {
             return Err(TokenDistributorError::NotRentExempt.into());
         }
 
+        // check the empty state account doesn't hold a lockup already
+        if Lockup::unpack_unchecked(&empty_state_account.data.borrow())?.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
         // unpack the lockup schedule state
         let mut lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
 
@@ -136,76 +241,95 @@ impl Processor {
         if *initializer.key != lockup_schedule_state.initializer {
             return Err(TokenDistributorError::IncorrectOwner.into());
         }
+
+        // check the schedule isn't paused
+        if lockup_schedule_state.paused {
+            return Err(TokenDistributorError::SchedulePaused.into());
+        }
         
         // check current time is before lockup start time
-        let current_timestamp = clock.unix_timestamp as u64;
+        let current_timestamp = lockup_schedule_state.time_unit.current_time(clock)?;
         if current_timestamp > lockup_schedule_state.start_timestamp {
             return Err(TokenDistributorError::InvalidStartTimestamp.into());
         }
 
-        // unpack temp token account data
-        let temp_token_account_info = TokenAccount::unpack(&temp_token_account.data.borrow())?;
-
-        // check temp token account has same mint as written in lockup schedule state
-        if temp_token_account_info.mint != lockup_schedule_state.token_mint {
+        // check mint is the mint written in lockup schedule state
+        if *token_mint.key != lockup_schedule_state.token_mint {
             return Err(TokenDistributorError::InvalidMint.into());
         }
 
-        // check number of tokens in temp account is the same as token_quantity
-        if temp_token_account_info.amount != token_quantity {
-            return Err(TokenDistributorError::ExpectedAmountMismatch.into());
-        }
-        
-        // transfer ownership of temp token account to program-derived address
-        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"tokenDistributor"], program_id);
-        let owner_change_ix = spl_token::instruction::set_authority(
+        // check the lockup token account is an empty account of the mint only the lockup's PDA controls
+        let (token_authority, _bump_seed) = find_token_authority_address(program_id, empty_state_account.key);
+        Self::check_empty_pda_token_account(lockup_token_account, token_mint.key, &token_authority)?;
+
+        // transfer the tokens from the source token account to the lockup token account
+        let decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())?.base.decimals;
+        let transfer_to_lockup_ix = spl_token_2022::instruction::transfer_checked(
             token_program.key,
-            temp_token_account.key,
-            Some(&pda),
-            spl_token::instruction::AuthorityType::AccountOwner,
+            source_token_account.key,
+            token_mint.key,
+            lockup_token_account.key,
             initializer.key,
-            &[&initializer.key],
+            &[],
+            token_quantity,
+            decimals,
         )?;
-        msg!("Calling the token program to transfer token account ownership...");
+        msg!("Calling the token program to transfer tokens to the lockup token account...");
         invoke(
-            &owner_change_ix,
+            &transfer_to_lockup_ix,
             &[
-                temp_token_account.clone(),
+                source_token_account.clone(),
+                token_mint.clone(),
+                lockup_token_account.clone(),
                 initializer.clone(),
                 token_program.clone(),
             ],
         )?;
 
-        // unpack empty state account
-        let mut lockup_state = Lockup::unpack_unchecked(&empty_state_account.data.borrow())?;
+        // the tokens that arrived, after any transfer fee, are what's locked
+        let token_quantity = Self::unpack_token_account(lockup_token_account)?.amount;
+        if token_quantity == 0 {
+            return Err(TokenDistributorError::ExpectedAmountMismatch.into());
+        }
 
         // write lockup information to the empty state account
-        lockup_state.is_initialized = true;
-        lockup_state.lockup_schedule_state = *lockup_schedule_state_account.key;
-        lockup_state.receiving_account = *receiver_account.key;
-        lockup_state.lockup_token_account = *temp_token_account.key;
-        lockup_state.token_quantity = token_quantity;
-        lockup_state.periods_redeemed = 0;
+        let lockup_state = Lockup::new(
+            *lockup_schedule_state_account.key,
+            *receiver_account.key,
+            *lockup_token_account.key,
+            token_quantity,
+        );
 
         // update the token_quantity_locked variable in lockup schedule state
-        lockup_schedule_state.token_quantity_locked += token_quantity;
+        lockup_schedule_state.token_quantity_locked = lockup_schedule_state.token_quantity_locked
+            .checked_add(token_quantity)
+            .ok_or(TokenDistributorError::MathOverflow)?;
 
         // pack the state accounts
         Lockup::pack(lockup_state, &mut empty_state_account.data.borrow_mut())?;
         LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
 
+        TokenDistributorEvent::TokensLocked {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            lockup_state: *empty_state_account.key,
+            receiving_account: *receiver_account.key,
+            lockup_token_account: *lockup_token_account.key,
+            token_quantity,
+        }.emit();
+
         Ok(())
     }
 
     // REDEEM TOKENS 
     fn process_redeem_tokens(
         accounts: &[AccountInfo],
+        amount: Option<u64>,
         program_id: &Pubkey
     ) -> ProgramResult {
 
         // get accounts
         let account_info_iter = &mut accounts.iter();
-        let receiving_account = next_account_info(account_info_iter)?;
+        let redeem_authority = next_account_info(account_info_iter)?;
         let lockup_schedule_state_account = next_account_info(account_info_iter)?;
         let lockup_state_account = next_account_info(account_info_iter)?;
         let lockup_token_account = next_account_info(account_info_iter)?;
@@ -214,15 +338,15 @@ impl Processor {
         let token_program = next_account_info(account_info_iter)?;
         let clock_sysvar = next_account_info(account_info_iter)?;
         let clock = &Clock::from_account_info(clock_sysvar)?;
-        let current_timestamp = clock.unix_timestamp as u64;
+        let token_mint = next_account_info(account_info_iter)?;
 
-        // check the initializer signed the tx
-        if !receiving_account.is_signer {
+        // check the receiver or its claim delegate signed the tx
+        if !redeem_authority.is_signer {
             return Err(ProgramError::MissingRequiredSignature);
         }
 
-        // check token program has correct program id
-        spl_token::check_program_account(token_program.key)?;
+        // check token program is spl-token or Token-2022
+        spl_token_2022::check_spl_token_program_account(token_program.key)?;
 
         // check program owns the state accounts
         if lockup_schedule_state_account.owner != program_id {
@@ -233,12 +357,12 @@ impl Processor {
         }
 
         // unpack lockup state account
-        let mut lockup_state = Lockup::unpack_unchecked(&lockup_state_account.data.borrow())?;
+        let mut lockup_state = Lockup::unpack(&lockup_state_account.data.borrow())?;
 
-        // check signer is same as receiving account in lockup state
-        if *receiving_account.key != lockup_state.receiving_account {
-            return Err(TokenDistributorError::UnauthorizedAccount.into());
-        }
+        // check signer is the receiving account in lockup state, or its claim delegate redeeming to
+        // the receiving account's own token accounts or the delegate destination
+        let destination_owner = Self::unpack_token_account(receiving_token_account)?.owner;
+        lockup_state.check_redeem_authority(redeem_authority.key, receiving_token_account.key, &destination_owner)?;
 
         // check lockup schedule state account is same as written in lockup state
         if *lockup_schedule_state_account.key != lockup_state.lockup_schedule_state {
@@ -246,35 +370,65 @@ impl Processor {
         }
 
         // unpack lockup schedule state
-        let lockup_schedule_state = LockupSchedule::unpack_unchecked(&lockup_schedule_state_account.data.borrow())?;
+        let lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
 
-        // CALCULATE NO. TOKENS TO REDEEM
-        // max no. periods to redeem = total no. periods - periods redeemed
-        let periods_to_redeem: u64;
-        let max_periods_to_redeem = lockup_schedule_state.number_periods - lockup_state.periods_redeemed;
-        if current_timestamp > lockup_schedule_state.start_timestamp {
-            // no. periods unlocked not already redeemed = ((current_timestamp - lockup_schedule.start_timestamp) / lockup_schedule.period_duration) - lockup_state.periods_redeemed
-            let periods_unlocked = ((current_timestamp - lockup_schedule_state.start_timestamp) / lockup_schedule_state.period_duration) - lockup_state.periods_redeemed;
-            // no. periods to redeem = min(max no. periods to redeem, no. periods unlocked)
-            periods_to_redeem = cmp::min(max_periods_to_redeem, periods_unlocked);
-        } else {
-            periods_to_redeem = 0;
+        // check the schedule isn't paused nor the lockup frozen - periods keep unlocking, so nothing is lost by waiting
+        if lockup_schedule_state.paused {
+            return Err(TokenDistributorError::SchedulePaused.into());
+        }
+        if lockup_state.frozen {
+            return Err(TokenDistributorError::LockupFrozen.into());
+        }
+
+        // check lockup token account is same as written in lockup state
+        if *lockup_token_account.key != lockup_state.lockup_token_account {
+            return Err(TokenDistributorError::InvalidLockupTokenAccount.into());
+        }
+
+        // check token program owns the mint and lockup token account
+        if token_mint.owner != token_program.key || lockup_token_account.owner != token_program.key {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check mint and lockup token account have the mint written in lockup schedule state
+        if *token_mint.key != lockup_schedule_state.token_mint {
+            return Err(TokenDistributorError::InvalidMint.into());
         }
+        let lockup_token_account_info = Self::unpack_token_account(lockup_token_account)?;
+        if lockup_token_account_info.mint != lockup_schedule_state.token_mint {
+            return Err(TokenDistributorError::InvalidMint.into());
+        }
+
+        // the lockup's own PDA owns its lockup token account, or the PDA shared by every version 0
+        // lockup did, which signs the transfers out of it
+        let (token_authority, bump_seed) = find_token_authority_address(program_id, lockup_state_account.key);
+        let (legacy_pda, legacy_bump_seed) = find_pda_address(program_id);
+        let token_authority_bump_seed = [bump_seed];
+        let legacy_bump_seed = [legacy_bump_seed];
+        let signer_seeds: &[&[u8]] = if lockup_token_account_info.owner == token_authority {
+            &[PDA_SEED, lockup_state_account.key.as_ref(), &token_authority_bump_seed]
+        } else if lockup_token_account_info.owner == legacy_pda {
+            &[PDA_SEED, &legacy_bump_seed]
+        } else {
+            return Err(TokenDistributorError::InvalidLockupTokenAccount.into());
+        };
+        let pda = lockup_token_account_info.owner;
 
-        // no. tokens per period = lockup.token_quantity / lockup_schedule.number_periods
-        let tokens_per_period = lockup_state.token_quantity / lockup_schedule_state.number_periods;
-        // no. tokens to redeem = no. periods to redeem * no. tokens per period
-        let tokens_to_redeem = periods_to_redeem * tokens_per_period;
+        // CALCULATE NO. TOKENS TO REDEEM
+        let (tokens_to_redeem, periods_to_redeem) = Self::record_redeem(&mut lockup_state, &lockup_schedule_state, amount, clock)?;
+        let periods_redeemed = lockup_state.periods_redeemed;
 
         // INSTRUCTION: send tokens from the lockup token account to receiving token account
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"tokenDistributor"], program_id);
-        let transfer_to_receiver_ix = spl_token::instruction::transfer(
+        let decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())?.base.decimals;
+        let transfer_to_receiver_ix = spl_token_2022::instruction::transfer_checked(
             token_program.key, 
             lockup_token_account.key, // src = lockup token account
+            token_mint.key,
             receiving_token_account.key, // dst = receiving token account
             &pda, 
-            &[&pda], 
+            &[], 
             tokens_to_redeem, // quantity 
+            decimals,
         )?;
         msg!("Calling the token program to transfer tokens from lockup to receiving account");
         invoke_signed(
@@ -282,48 +436,1034 @@ impl Processor {
             &[
                 token_program.clone(),
                 lockup_token_account.clone(),
+                token_mint.clone(),
                 receiving_token_account.clone(),
                 pda_account.clone(),
             ],
-            &[&[&b"tokenDistributor"[..], &[bump_seed]]],
+            &[signer_seeds],
         )?;
 
-        // increment the number of periods redeemed in state
-        lockup_state.periods_redeemed += periods_to_redeem;
-
-        // check if all periods have been redeemed
-        if lockup_state.periods_redeemed == lockup_schedule_state.number_periods {
+        // once this redeem completes the lockup, send anything else in the lockup token account along.
+        // A completed lockup can't be redeemed again, so this only happens once
+        let mut amount_redeemed = tokens_to_redeem;
+        if lockup_state.redeemed_amount == lockup_state.token_quantity {
             // check lockup token account is empty
-            let lockup_token_account_info = TokenAccount::unpack(&lockup_token_account.data.borrow())?;
-            let lockup_tokens_remaining = lockup_token_account_info.amount;
+            let lockup_tokens_remaining = Self::unpack_token_account(lockup_token_account)?.amount;
             // if any remaining, send to the receiving token account
             if lockup_tokens_remaining != 0 {
-                let transfer_remaining_to_receiver_ix = spl_token::instruction::transfer(
+                let transfer_remaining_to_receiver_ix = spl_token_2022::instruction::transfer_checked(
                     token_program.key, 
                     lockup_token_account.key, // src = lockup token account
+                    token_mint.key,
                     receiving_token_account.key, // dst = receiving token account
                     &pda, 
-                    &[&pda], 
+                    &[], 
                     lockup_tokens_remaining, // quantity 
+                    decimals,
                 )?;
-                msg!("Calling the token program to transfer tokens from buyer temp to new temp token account...");
+                msg!("Calling the token program to transfer the remaining tokens to the receiving account...");
                 invoke_signed(
                     &transfer_remaining_to_receiver_ix,
                     &[
                         token_program.clone(),
                         lockup_token_account.clone(),
+                        token_mint.clone(),
                         receiving_token_account.clone(),
                         pda_account.clone(),
                     ],
-                    &[&[&b"tokenDistributor"[..], &[bump_seed]]],
+                    &[signer_seeds],
                 )?;
-        
+                amount_redeemed = amount_redeemed
+                    .checked_add(lockup_tokens_remaining)
+                    .ok_or(TokenDistributorError::MathOverflow)?;
             }
         }   
 
+        let receiving_account = lockup_state.receiving_account;
+
         // pack the lockup state accounts (lockup schedule state is unchanged)
         Lockup::pack(lockup_state, &mut lockup_state_account.data.borrow_mut())?;
 
+        TokenDistributorEvent::TokensRedeemed {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            lockup_state: *lockup_state_account.key,
+            receiving_account,
+            amount: amount_redeemed,
+            periods: periods_to_redeem,
+            periods_redeemed,
+        }.emit();
+
+        Ok(())
+    }
+
+    // MIGRATE ACCOUNT
+    fn process_migrate_account(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let payer = next_account_info(account_info_iter)?;
+        let state_account = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+
+        // check the payer signed the tx
+        if !payer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state account
+        if state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // only the untagged version 0 schedules and lockups need migrating. Current accounts, including
+        // merkle distributors of any length, start with their tag, which a version 0 layout (starting
+        // with is_initialized) never has beyond AccountType::LockupSchedule - told apart by length
+        let data = state_account.data.borrow().to_vec();
+        if data.first() == Some(&(AccountType::MerkleDistributor as u8)) {
+            let mut distributor_data = state_account.data.borrow_mut();
+            let (header, _bitmap) = MerkleDistributor::split_data(&mut distributor_data)?;
+            MerkleDistributor::unpack_unchecked(header)?;
+            msg!("Account already migrated");
+            return Ok(());
+        }
+
+        // convert to the current layout, then grow and rewrite the account
+        let account_type = match data.len() {
+            LockupScheduleV0::LEN => {
+                let old_lockup_schedule_state = LockupScheduleV0::unpack(&data)?;
+                let lockup_schedule_state = if old_lockup_schedule_state.is_initialized {
+                    old_lockup_schedule_state.migrate()
+                } else {
+                    LockupSchedule::unpack_unchecked(&[0; LockupSchedule::LEN])?
+                };
+                Self::resize_state_account(payer, state_account, system_program, rent, LockupSchedule::LEN)?;
+                LockupSchedule::pack(lockup_schedule_state, &mut state_account.data.borrow_mut())?;
+                AccountType::LockupSchedule
+            },
+            LockupV0::LEN => {
+                let old_lockup_state = LockupV0::unpack(&data)?;
+                let lockup_state = if old_lockup_state.is_initialized {
+                    let lockup_schedule_state = Self::migrated_lockup_schedule(account_info_iter, program_id, &old_lockup_state.lockup_schedule_state)?;
+                    old_lockup_state.migrate(&lockup_schedule_state)?
+                } else {
+                    // never locked into, so there is no schedule and nothing to carry over
+                    Lockup::unpack_unchecked(&[0; Lockup::LEN])?
+                };
+                Self::resize_state_account(payer, state_account, system_program, rent, Lockup::LEN)?;
+                Lockup::pack(lockup_state, &mut state_account.data.borrow_mut())?;
+                AccountType::Lockup
+            },
+            // already in the current layout - check it unpacks and leave it untouched
+            LockupSchedule::LEN => {
+                LockupSchedule::unpack_unchecked(&data)?;
+                msg!("Account already migrated");
+                return Ok(());
+            },
+            Lockup::LEN => {
+                Lockup::unpack_unchecked(&data)?;
+                msg!("Account already migrated");
+                return Ok(());
+            },
+            _ => return Err(TokenDistributorError::InvalidAccountType.into())
+        };
+        msg!("Migrated state account from {} to {} bytes", data.len(), state_account.data_len());
+
+        TokenDistributorEvent::AccountMigrated {
+            account: *state_account.key,
+            account_type,
+        }.emit();
+
+        Ok(())
+    }
+
+    // CREATE MERKLE DISTRIBUTOR
+    fn process_create_merkle_distributor(
+        accounts: &[AccountInfo],
+        merkle_root: [u8; 32],
+        max_num_nodes: u64,
+        token_quantity: u64,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+        let distributor_state_account = next_account_info(account_info_iter)?;
+        let distributor_token_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let source_token_account = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+
+        // check the initializer signed the tx
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check token program is spl-token or Token-2022, and owns the mint and distributor token account
+        spl_token_2022::check_spl_token_program_account(token_program.key)?;
+        if token_mint.owner != token_program.key || distributor_token_account.owner != token_program.key {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check program owns the state accounts
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if distributor_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check the distributor state account fits the claimed bitmap and is rent exempt
+        if max_num_nodes == 0 || max_num_nodes > MAX_DISTRIBUTOR_NODES {
+            return Err(TokenDistributorError::InvalidDistributorConfig.into());
+        }
+        if distributor_state_account.data_len() < MerkleDistributor::space(max_num_nodes) {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        if !rent.is_exempt(distributor_state_account.lamports(), distributor_state_account.data_len()) {
+            return Err(TokenDistributorError::NotRentExempt.into());
+        }
+
+        // check signer is initializer in lockup schedule and the mint is the schedule's
+        let lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+        if *initializer.key != lockup_schedule_state.initializer {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if *token_mint.key != lockup_schedule_state.token_mint {
+            return Err(TokenDistributorError::InvalidMint.into());
+        }
+
+        // check the schedule isn't paused
+        if lockup_schedule_state.paused {
+            return Err(TokenDistributorError::SchedulePaused.into());
+        }
+
+        // check the distributor token account is an empty account of the mint only the distributor's PDA controls
+        let (token_authority, _bump_seed) = find_token_authority_address(program_id, distributor_state_account.key);
+        Self::check_empty_pda_token_account(distributor_token_account, token_mint.key, &token_authority)?;
+
+        // transfer the tokens from the source token account to the distributor token account
+        let decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())?.base.decimals;
+        let transfer_to_distributor_ix = spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            source_token_account.key,
+            token_mint.key,
+            distributor_token_account.key,
+            initializer.key,
+            &[],
+            token_quantity,
+            decimals,
+        )?;
+        msg!("Calling the token program to transfer tokens to the distributor token account...");
+        invoke(
+            &transfer_to_distributor_ix,
+            &[
+                source_token_account.clone(),
+                token_mint.clone(),
+                distributor_token_account.clone(),
+                initializer.clone(),
+                token_program.clone(),
+            ],
+        )?;
+
+        // the tokens that arrived, after any transfer fee, are what can be claimed
+        let total_amount = Self::unpack_token_account(distributor_token_account)?.amount;
+        if total_amount == 0 {
+            return Err(TokenDistributorError::ExpectedAmountMismatch.into());
+        }
+
+        // write the distributor header, the bitmap starts out all unclaimed
+        let mut data = distributor_state_account.data.borrow_mut();
+        let (header, _bitmap) = MerkleDistributor::split_data(&mut data)?;
+        let mut distributor_state = MerkleDistributor::unpack_unchecked(header)?;
+        if distributor_state.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        distributor_state.account_type = AccountType::MerkleDistributor;
+        distributor_state.version = STATE_VERSION;
+        distributor_state.is_initialized = true;
+        distributor_state.lockup_schedule_state = *lockup_schedule_state_account.key;
+        distributor_state.distributor_token_account = *distributor_token_account.key;
+        distributor_state.merkle_root = merkle_root;
+        distributor_state.max_num_nodes = max_num_nodes;
+        distributor_state.total_amount = total_amount;
+        distributor_state.num_nodes_claimed = 0;
+        distributor_state.amount_claimed = 0;
+        MerkleDistributor::pack(distributor_state, header)?;
+
+        TokenDistributorEvent::DistributorCreated {
+            merkle_distributor: *distributor_state_account.key,
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            distributor_token_account: *distributor_token_account.key,
+            merkle_root,
+            max_num_nodes,
+            total_amount,
+        }.emit();
+
+        Ok(())
+    }
+
+    // CLAIM TOKENS
+    fn process_claim_tokens(
+        accounts: &[AccountInfo],
+        index: u64,
+        amount: u64,
+        proof: &[[u8; 32]],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let claimant = next_account_info(account_info_iter)?;
+        let distributor_state_account = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+        let empty_state_account = next_account_info(account_info_iter)?;
+        let lockup_token_account = next_account_info(account_info_iter)?;
+        let distributor_token_account = next_account_info(account_info_iter)?;
+        let pda_account = next_account_info(account_info_iter)?;
+        let token_program = next_account_info(account_info_iter)?;
+        let token_mint = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+
+        // check the claimant signed the tx
+        if !claimant.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check token program is spl-token or Token-2022, and owns the mint and token accounts
+        spl_token_2022::check_spl_token_program_account(token_program.key)?;
+        if token_mint.owner != token_program.key
+            || lockup_token_account.owner != token_program.key
+            || distributor_token_account.owner != token_program.key
+        {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check program owns the state accounts
+        if distributor_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if empty_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check empty state account has enough lamports to ensure it doesn't get closed by the runtime
+        if !rent.is_exempt(empty_state_account.lamports(), empty_state_account.data_len()) {
+            return Err(TokenDistributorError::NotRentExempt.into());
+        }
+
+        let mut distributor_data = distributor_state_account.data.borrow_mut();
+        let (header, bitmap) = MerkleDistributor::split_data(&mut distributor_data)?;
+        let mut distributor_state = MerkleDistributor::unpack(header)?;
+
+        // check lockup schedule state account and distributor token account are the distributor's
+        if *lockup_schedule_state_account.key != distributor_state.lockup_schedule_state {
+            return Err(TokenDistributorError::IncorrectSchedule.into());
+        }
+        if *distributor_token_account.key != distributor_state.distributor_token_account {
+            return Err(TokenDistributorError::InvalidLockupTokenAccount.into());
+        }
+
+        // check mint is the mint written in lockup schedule state
+        let mut lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+        if *token_mint.key != lockup_schedule_state.token_mint {
+            return Err(TokenDistributorError::InvalidMint.into());
+        }
+
+        // check the schedule isn't paused
+        if lockup_schedule_state.paused {
+            return Err(TokenDistributorError::SchedulePaused.into());
+        }
+
+        // check the claim is a leaf of the merkle root and hasn't been claimed
+        if index >= distributor_state.max_num_nodes {
+            return Err(TokenDistributorError::InvalidMerkleProof.into());
+        }
+        if MerkleDistributor::is_claimed(bitmap, index) {
+            return Err(TokenDistributorError::AlreadyClaimed.into());
+        }
+        if !merkle::verify_proof(proof, &distributor_state.merkle_root, merkle::leaf_hash(index, claimant.key, amount)) {
+            return Err(TokenDistributorError::InvalidMerkleProof.into());
+        }
+
+        // check the distributor was funded with enough for the claim
+        let amount_claimed = distributor_state.amount_claimed
+            .checked_add(amount)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        if amount_claimed > distributor_state.total_amount {
+            return Err(TokenDistributorError::ClaimExceedsDistributor.into());
+        }
+
+        // check the lockup token account is an empty account of the mint only the lockup's PDA controls
+        let (token_authority, _bump_seed) = find_token_authority_address(program_id, empty_state_account.key);
+        Self::check_empty_pda_token_account(lockup_token_account, token_mint.key, &token_authority)?;
+
+        // transfer the claim from the distributor token account, signed by the distributor's PDA, to the
+        // lockup token account
+        let (pda, bump_seed) = find_token_authority_address(program_id, distributor_state_account.key);
+        let decimals = StateWithExtensions::<Mint>::unpack(&token_mint.data.borrow())?.base.decimals;
+        let transfer_to_lockup_ix = spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            distributor_token_account.key,
+            token_mint.key,
+            lockup_token_account.key,
+            &pda,
+            &[],
+            amount,
+            decimals,
+        )?;
+        msg!("Calling the token program to transfer the claim to the lockup token account...");
+        invoke_signed(
+            &transfer_to_lockup_ix,
+            &[
+                token_program.clone(),
+                distributor_token_account.clone(),
+                token_mint.clone(),
+                lockup_token_account.clone(),
+                pda_account.clone(),
+            ],
+            &[&[PDA_SEED, distributor_state_account.key.as_ref(), &[bump_seed]]],
+        )?;
+
+        // the tokens that arrived, after any transfer fee, are what's locked
+        let token_quantity = Self::unpack_token_account(lockup_token_account)?.amount;
+        if token_quantity == 0 {
+            return Err(TokenDistributorError::ExpectedAmountMismatch.into());
+        }
+
+        // write the claimant's lockup to the empty state account
+        if Lockup::unpack_unchecked(&empty_state_account.data.borrow())?.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+        let lockup_state = Lockup::new(
+            *lockup_schedule_state_account.key,
+            *claimant.key,
+            *lockup_token_account.key,
+            token_quantity,
+        );
+
+        // mark the claim and update the claimed and locked totals
+        MerkleDistributor::set_claimed(bitmap, index);
+        distributor_state.num_nodes_claimed = distributor_state.num_nodes_claimed
+            .checked_add(1)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        distributor_state.amount_claimed = amount_claimed;
+        lockup_schedule_state.token_quantity_locked = lockup_schedule_state.token_quantity_locked
+            .checked_add(token_quantity)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+
+        // pack the state accounts
+        MerkleDistributor::pack(distributor_state, header)?;
+        Lockup::pack(lockup_state, &mut empty_state_account.data.borrow_mut())?;
+        LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::TokensClaimed {
+            merkle_distributor: *distributor_state_account.key,
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            lockup_state: *empty_state_account.key,
+            receiving_account: *claimant.key,
+            lockup_token_account: *lockup_token_account.key,
+            index,
+            token_quantity,
+        }.emit();
+
         Ok(())
     }
+
+    // SET PAUSE AUTHORITY
+    fn process_set_pause_authority(
+        accounts: &[AccountInfo],
+        pause_authority: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+
+        // check the initializer signed the tx
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state account
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check signer is initializer in lockup schedule - a pause authority can't replace itself
+        let mut lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+        if *initializer.key != lockup_schedule_state.initializer {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        lockup_schedule_state.pause_authority = pause_authority;
+        LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::PauseAuthoritySet {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            pause_authority,
+        }.emit();
+
+        Ok(())
+    }
+
+    // PAUSE / RESUME
+    fn process_set_paused(
+        accounts: &[AccountInfo],
+        paused: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+
+        // check the authority signed the tx
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state account
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check signer is the initializer or pause authority of the lockup schedule
+        let mut lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+        if !lockup_schedule_state.is_pause_authority(authority.key) {
+            return Err(TokenDistributorError::UnauthorizedAccount.into());
+        }
+
+        lockup_schedule_state.paused = paused;
+        LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::PauseSet {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            authority: *authority.key,
+            paused,
+        }.emit();
+
+        Ok(())
+    }
+
+    // SET COMPLIANCE AUTHORITY
+    fn process_set_compliance_authority(
+        accounts: &[AccountInfo],
+        compliance_authority: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+
+        // check the initializer signed the tx
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state account
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check signer is initializer in lockup schedule
+        let mut lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+        if *initializer.key != lockup_schedule_state.initializer {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        lockup_schedule_state.compliance_authority = compliance_authority;
+        LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::ComplianceAuthoritySet {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            compliance_authority,
+        }.emit();
+
+        Ok(())
+    }
+
+    // FREEZE / THAW LOCKUP
+    fn process_set_frozen(
+        accounts: &[AccountInfo],
+        frozen: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let authority = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+        let lockup_state_account = next_account_info(account_info_iter)?;
+
+        // check the authority signed the tx
+        if !authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state accounts
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if lockup_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check lockup schedule state account is same as written in lockup state
+        let mut lockup_state = Lockup::unpack(&lockup_state_account.data.borrow())?;
+        if *lockup_schedule_state_account.key != lockup_state.lockup_schedule_state {
+            return Err(TokenDistributorError::IncorrectSchedule.into());
+        }
+
+        // check signer is the compliance authority of the lockup schedule - nobody is until one is set
+        let lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+        if lockup_schedule_state.compliance_authority == Pubkey::default()
+            || *authority.key != lockup_schedule_state.compliance_authority
+        {
+            return Err(TokenDistributorError::UnauthorizedAccount.into());
+        }
+
+        lockup_state.frozen = frozen;
+        Lockup::pack(lockup_state, &mut lockup_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::FreezeSet {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            lockup_state: *lockup_state_account.key,
+            authority: *authority.key,
+            frozen,
+        }.emit();
+
+        Ok(())
+    }
+
+    // SET CLAIM DELEGATE
+    fn process_set_claim_delegate(
+        accounts: &[AccountInfo],
+        claim_delegate: Pubkey,
+        delegate_destination: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let receiving_account = next_account_info(account_info_iter)?;
+        let lockup_state_account = next_account_info(account_info_iter)?;
+
+        // check the receiving account signed the tx
+        if !receiving_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state account
+        if lockup_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check signer is same as receiving account in lockup state - a delegate can't replace itself
+        let mut lockup_state = Lockup::unpack(&lockup_state_account.data.borrow())?;
+        if *receiving_account.key != lockup_state.receiving_account {
+            return Err(TokenDistributorError::UnauthorizedAccount.into());
+        }
+
+        lockup_state.claim_delegate = claim_delegate;
+        lockup_state.delegate_destination = delegate_destination;
+        Lockup::pack(lockup_state, &mut lockup_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::ClaimDelegateSet {
+            lockup_state: *lockup_state_account.key,
+            receiving_account: *receiving_account.key,
+            claim_delegate,
+            delegate_destination,
+        }.emit();
+
+        Ok(())
+    }
+
+    // LOCK LAMPORTS
+    fn process_lock_lamports(
+        accounts: &[AccountInfo],
+        lamports: u64,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let initializer = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+        let empty_state_account = next_account_info(account_info_iter)?;
+        let receiver_account = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+        let system_program = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+        let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+
+        // check the initializer signed the tx
+        if !initializer.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state accounts
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if empty_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check empty state account has enough lamports to ensure it doesn't get closed by the runtime
+        if !rent.is_exempt(empty_state_account.lamports(), empty_state_account.data_len()) {
+            return Err(TokenDistributorError::NotRentExempt.into());
+        }
+
+        // check the empty state account doesn't hold a lockup already
+        if Lockup::unpack_unchecked(&empty_state_account.data.borrow())?.is_initialized {
+            return Err(ProgramError::AccountAlreadyInitialized);
+        }
+
+        // unpack the lockup schedule state
+        let mut lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+
+        // check signer is initializer in lockup schedule
+        if *initializer.key != lockup_schedule_state.initializer {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // check the schedule isn't paused
+        if lockup_schedule_state.paused {
+            return Err(TokenDistributorError::SchedulePaused.into());
+        }
+
+        // check current time is before lockup start time
+        let current_timestamp = lockup_schedule_state.time_unit.current_time(clock)?;
+        if current_timestamp > lockup_schedule_state.start_timestamp {
+            return Err(TokenDistributorError::InvalidStartTimestamp.into());
+        }
+
+        // check the schedule vests native SOL
+        if !is_native_mint(&lockup_schedule_state.token_mint) {
+            return Err(TokenDistributorError::InvalidMint.into());
+        }
+
+        // check the vault is the SOL vault of the lockup state
+        let (sol_vault_address, vault_bump_seed) = find_sol_vault_address(program_id, empty_state_account.key);
+        if *sol_vault.key != sol_vault_address {
+            return Err(TokenDistributorError::InvalidLockupTokenAccount.into());
+        }
+
+        if lamports == 0 {
+            return Err(TokenDistributorError::ExpectedAmountMismatch.into());
+        }
+
+        // fund the vault with the lamports on top of its rent. Anyone can send lamports to the vault's
+        // address beforehand, which would make create_account fail, so only top up what's missing
+        let vault_lamports = rent.minimum_balance(0)
+            .checked_add(lamports)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        let missing_lamports = vault_lamports.saturating_sub(sol_vault.lamports());
+        if missing_lamports > 0 {
+            msg!("Calling the system program to fund the SOL vault...");
+            invoke(
+                &system_instruction::transfer(initializer.key, sol_vault.key, missing_lamports),
+                &[initializer.clone(), sol_vault.clone(), system_program.clone()],
+            )?;
+        }
+
+        // then make the program own the vault, signing for it with its seeds
+        let vault_seeds: &[&[u8]] = &[SOL_VAULT_SEED, empty_state_account.key.as_ref(), &[vault_bump_seed]];
+        msg!("Calling the system program to assign the SOL vault...");
+        invoke_signed(
+            &system_instruction::allocate(sol_vault.key, 0),
+            &[sol_vault.clone(), system_program.clone()],
+            &[vault_seeds],
+        )?;
+        invoke_signed(
+            &system_instruction::assign(sol_vault.key, program_id),
+            &[sol_vault.clone(), system_program.clone()],
+            &[vault_seeds],
+        )?;
+
+        // write lockup information to the empty state account, with the vault as its lockup token account
+        let lockup_state = Lockup::new(*lockup_schedule_state_account.key, *receiver_account.key, *sol_vault.key, lamports);
+
+        // update the token_quantity_locked variable in lockup schedule state
+        lockup_schedule_state.token_quantity_locked = lockup_schedule_state.token_quantity_locked
+            .checked_add(lamports)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+
+        // pack the state accounts
+        Lockup::pack(lockup_state, &mut empty_state_account.data.borrow_mut())?;
+        LockupSchedule::pack(lockup_schedule_state, &mut lockup_schedule_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::TokensLocked {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            lockup_state: *empty_state_account.key,
+            receiving_account: *receiver_account.key,
+            lockup_token_account: *sol_vault.key,
+            token_quantity: lamports,
+        }.emit();
+
+        Ok(())
+    }
+
+    // REDEEM LAMPORTS
+    fn process_redeem_lamports(
+        accounts: &[AccountInfo],
+        amount: Option<u64>,
+        program_id: &Pubkey
+    ) -> ProgramResult {
+
+        // get accounts
+        let account_info_iter = &mut accounts.iter();
+        let redeem_authority = next_account_info(account_info_iter)?;
+        let lockup_schedule_state_account = next_account_info(account_info_iter)?;
+        let lockup_state_account = next_account_info(account_info_iter)?;
+        let sol_vault = next_account_info(account_info_iter)?;
+        let destination = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+        // only given to redeem wrapped SOL to a native token account
+        let token_program = next_account_info(account_info_iter).ok();
+
+        // check the receiver or its claim delegate signed the tx
+        if !redeem_authority.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // check program owns the state accounts
+        if lockup_schedule_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if lockup_state_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+
+        // the owner of the destination is the token account owner for wrapped SOL, else the destination itself.
+        // A token account can only get lamports with the token program given to sync it.
+        let destination_owner = match token_program {
+            Some(token_program) => {
+                spl_token_2022::check_spl_token_program_account(token_program.key)?;
+                if destination.owner != token_program.key {
+                    return Err(TokenDistributorError::IncorrectOwner.into());
+                }
+                let destination_token_account = Self::unpack_token_account(destination)?;
+                if !destination_token_account.is_native() {
+                    return Err(TokenDistributorError::InvalidMint.into());
+                }
+                destination_token_account.owner
+            },
+            None => {
+                if spl_token_2022::check_spl_token_program_account(destination.owner).is_ok() {
+                    return Err(TokenDistributorError::InvalidDestination.into());
+                }
+                *destination.key
+            },
+        };
+
+        // unpack lockup state account
+        let mut lockup_state = Lockup::unpack(&lockup_state_account.data.borrow())?;
+
+        // check signer is the receiving account in lockup state, or its claim delegate redeeming to
+        // the receiving account itself, its native token accounts or the delegate destination
+        lockup_state.check_redeem_authority(redeem_authority.key, destination.key, &destination_owner)?;
+
+        // check lockup schedule state account is same as written in lockup state
+        if *lockup_schedule_state_account.key != lockup_state.lockup_schedule_state {
+            return Err(TokenDistributorError::IncorrectSchedule.into());
+        }
+
+        // unpack lockup schedule state
+        let lockup_schedule_state = LockupSchedule::unpack(&lockup_schedule_state_account.data.borrow())?;
+
+        // check the schedule isn't paused nor the lockup frozen
+        if lockup_schedule_state.paused {
+            return Err(TokenDistributorError::SchedulePaused.into());
+        }
+        if lockup_state.frozen {
+            return Err(TokenDistributorError::LockupFrozen.into());
+        }
+
+        // check the schedule vests native SOL and the vault is the lockup's - only SOL vaults are
+        // program-owned accounts without data
+        if !is_native_mint(&lockup_schedule_state.token_mint) {
+            return Err(TokenDistributorError::InvalidMint.into());
+        }
+        if *sol_vault.key != lockup_state.lockup_token_account
+            || sol_vault.owner != program_id
+            || sol_vault.data_len() != 0
+        {
+            return Err(TokenDistributorError::InvalidLockupTokenAccount.into());
+        }
+
+        // CALCULATE NO. LAMPORTS TO REDEEM
+        let (lamports_to_redeem, periods_to_redeem) = Self::record_redeem(&mut lockup_state, &lockup_schedule_state, amount, clock)?;
+        let periods_redeemed = lockup_state.periods_redeemed;
+
+        // once every lamport locked has been redeemed the vault is closed, so its rent and anything
+        // else sent to it go along. Every period can be unlocked with lamports still left to redeem
+        let mut amount_redeemed = lamports_to_redeem;
+        if lockup_state.redeemed_amount == lockup_state.token_quantity {
+            amount_redeemed = sol_vault.lamports();
+        }
+
+        // the program owns the vault, so it moves the lamports itself
+        let vault_lamports = sol_vault.lamports()
+            .checked_sub(amount_redeemed)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        let destination_lamports = destination.lamports()
+            .checked_add(amount_redeemed)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        **sol_vault.try_borrow_mut_lamports()? = vault_lamports;
+        **destination.try_borrow_mut_lamports()? = destination_lamports;
+
+        // wrapped SOL only counts towards the token balance once synced. sync_native itself only takes
+        // the native token account, but the runtime checks that the lamports of the accounts passed to
+        // an invoke add up to the same total before and after it: the destination was just credited
+        // what the vault was debited, so without the vault the invoke fails as unbalanced. The token
+        // program ignores the extra account.
+        if let Some(token_program) = token_program {
+            let mut sync_native_ix = spl_token_2022::instruction::sync_native(token_program.key, destination.key)?;
+            sync_native_ix.accounts.push(AccountMeta::new(*sol_vault.key, false));
+            msg!("Calling the token program to sync the native token account...");
+            invoke(
+                &sync_native_ix,
+                &[destination.clone(), sol_vault.clone(), token_program.clone()],
+            )?;
+        }
+
+        let receiving_account = lockup_state.receiving_account;
+
+        // pack the lockup state accounts (lockup schedule state is unchanged)
+        Lockup::pack(lockup_state, &mut lockup_state_account.data.borrow_mut())?;
+
+        TokenDistributorEvent::TokensRedeemed {
+            lockup_schedule_state: *lockup_schedule_state_account.key,
+            lockup_state: *lockup_state_account.key,
+            receiving_account,
+            amount: amount_redeemed,
+            periods: periods_to_redeem,
+            periods_redeemed,
+        }.emit();
+
+        Ok(())
+    }
+
+    // check a token account is an empty account of the mint that only the given PDA - the token
+    // authority of the lockup or distributor it is used for - can move tokens out of, so its whole
+    // balance belongs to that lockup or distributor
+    fn check_empty_pda_token_account(token_account: &AccountInfo, token_mint: &Pubkey, token_authority: &Pubkey) -> ProgramResult {
+        let token_account_info = Self::unpack_token_account(token_account)?;
+        if token_account_info.mint != *token_mint {
+            return Err(TokenDistributorError::InvalidMint.into());
+        }
+        if token_account_info.owner != *token_authority
+            || token_account_info.amount != 0
+            || token_account_info.delegate.is_some()
+            || token_account_info.close_authority.is_some()
+        {
+            return Err(TokenDistributorError::InvalidLockupTokenAccount.into());
+        }
+        Ok(())
+    }
+
+    // top up the account to the rent-exempt minimum for new_len from the payer, then realloc
+    fn resize_state_account<'a>(
+        payer: &AccountInfo<'a>,
+        state_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        rent: &Rent,
+        new_len: usize,
+    ) -> ProgramResult {
+        let extra_rent = rent.minimum_balance(new_len).saturating_sub(state_account.lamports());
+        if extra_rent > 0 {
+            invoke(
+                &system_instruction::transfer(payer.key, state_account.key, extra_rent),
+                &[payer.clone(), state_account.clone(), system_program.clone()],
+            )?;
+        }
+        state_account.realloc(new_len, true)
+    }
+
+    // the lockup schedule a lockup being migrated vests under, in whichever layout it is still in
+    fn migrated_lockup_schedule(
+        account_info_iter: &mut std::slice::Iter<AccountInfo>,
+        program_id: &Pubkey,
+        lockup_schedule_key: &Pubkey,
+    ) -> Result<LockupSchedule, ProgramError> {
+        let lockup_schedule_account = next_account_info(account_info_iter)?;
+
+        // check program owns the lockup schedule state account and it's the lockup's schedule
+        if lockup_schedule_account.owner != program_id {
+            return Err(TokenDistributorError::IncorrectOwner.into());
+        }
+        if lockup_schedule_account.key != lockup_schedule_key {
+            return Err(TokenDistributorError::IncorrectSchedule.into());
+        }
+
+        let data = lockup_schedule_account.data.borrow();
+        let lockup_schedule_state = match data.len() {
+            LockupScheduleV0::LEN => LockupScheduleV0::unpack(&data)?.migrate(),
+            _ => LockupSchedule::unpack(&data)?,
+        };
+        Ok(lockup_schedule_state)
+    }
+
+    // record a redeem of amount, or of everything unlocked if None, in the lockup state. Returns the
+    // no. tokens to redeem and the no. whole periods they newly cover
+    fn record_redeem(
+        lockup_state: &mut Lockup,
+        lockup_schedule_state: &LockupSchedule,
+        amount: Option<u64>,
+        clock: &Clock,
+    ) -> Result<(u64, u64), ProgramError> {
+        // check the lockup hasn't been redeemed in full already
+        if lockup_state.redeemed_amount == lockup_state.token_quantity {
+            return Err(TokenDistributorError::LockupRedeemed.into());
+        }
+
+        // vested amount = no. periods unlocked * tokens per period, or the whole lockup once every period unlocked,
+        // with the current time read from the clock field of the schedule's time unit
+        let current_timestamp = lockup_schedule_state.time_unit.current_time(clock)?;
+        let vested_amount = lockup_schedule_state.vested_amount(lockup_state.token_quantity, current_timestamp)?;
+        // redeemable amount = vested amount - tokens already redeemed
+        let redeemed_amount = lockup_state.redeemed_amount;
+        let redeemable_amount = vested_amount
+            .checked_sub(redeemed_amount)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        // no. tokens to redeem = the amount asked for, at most the redeemable amount, or all of it
+        let tokens_to_redeem = match amount {
+            Some(amount) if amount == 0 || amount > redeemable_amount => {
+                return Err(TokenDistributorError::InvalidRedeemAmount.into());
+            },
+            Some(amount) => amount,
+            None => redeemable_amount,
+        };
+
+        // update the amount redeemed and the no. whole periods it covers in state
+        lockup_state.redeemed_amount = redeemed_amount
+            .checked_add(tokens_to_redeem)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        let periods_redeemed = lockup_schedule_state.periods_covered(lockup_state.token_quantity, lockup_state.redeemed_amount)?;
+        let periods_to_redeem = periods_redeemed.saturating_sub(lockup_state.periods_redeemed);
+        lockup_state.periods_redeemed = periods_redeemed;
+
+        Ok((tokens_to_redeem, periods_to_redeem))
+    }
+
+    // unpack an spl-token or Token-2022 token account, ignoring any extensions
+    fn unpack_token_account(token_account: &AccountInfo) -> Result<TokenAccount, ProgramError> {
+        Ok(StateWithExtensions::<TokenAccount>::unpack(&token_account.data.borrow())?.base)
+    }
 }
\ No newline at end of file
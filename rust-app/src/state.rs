@@ -1,15 +1,150 @@
 use solana_program::{
+    clock::Clock,
     program_pack::{IsInitialized, Pack, Sealed},
     program_error::ProgramError,
     pubkey::Pubkey,
 };
 
-use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use borsh::{BorshDeserialize, BorshSerialize};
 
-use crate::error::TokenDistributorError;
+use std::{cmp, convert::TryFrom};
+
+use crate::{calendar, error::TokenDistributorError};
+
+// version of the state layouts written by this program - version 0 is the untagged layout from
+// before the account type and version were added
+pub const STATE_VERSION: u8 = 1;
+
+// tag at the start of every state account telling the account types apart
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum AccountType {
+    Uninitialized = 0,
+    LockupSchedule = 1,
+    Lockup = 2,
+    MerkleDistributor = 3,
+}
+
+impl AccountType {
+    // check an unpacked header: either an empty account or the expected type at its current version
+    fn check(self, version: u8, is_initialized: bool, expected: AccountType) -> Result<(), ProgramError> {
+        if self == AccountType::Uninitialized && version == 0 && !is_initialized {
+            return Ok(());
+        }
+        if self != expected {
+            return Err(TokenDistributorError::InvalidAccountType.into());
+        }
+        if version != STATE_VERSION {
+            return Err(TokenDistributorError::InvalidStateVersion.into());
+        }
+        Ok(())
+    }
+}
+
+// limits on the period config accepted by CreateLockupSchedule
+pub const MAX_UNLOCK_PERIODS: u64 = 10_000;
+pub const MAX_PERIOD_DURATION: u64 = 100 * 365 * 24 * 60 * 60; // 100 years in seconds
+pub const MAX_PERIOD_DURATION_SLOTS: u64 = MAX_PERIOD_DURATION * 5 / 2; // 100 years of 400ms slots
+pub const MAX_PERIOD_DURATION_EPOCHS: u64 = MAX_PERIOD_DURATION_SLOTS / 432_000; // 100 years of mainnet epochs
+pub const MAX_PERIOD_DURATION_MONTHS: u64 = 100 * 12; // 100 years in months
+
+// limit on the points of a vesting curve, which live in the schedule account
+pub const MAX_CURVE_POINTS: usize = 16;
+// vesting curves are in basis points of a lockup's token quantity
+pub const BASIS_POINTS: u64 = 10_000;
+
+// Clock field a schedule's start and period duration are measured in. Unix timestamps can be
+// nudged by validators, slots and epochs only move forward at the cluster's pace.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum TimeUnit {
+    #[default]
+    UnixTimestamp = 0,
+    Slot = 1,
+    Epoch = 2,
+}
+
+impl TimeUnit {
+    // current time in this unit, rejecting negative unix timestamps
+    pub fn current_time(self, clock: &Clock) -> Result<u64, ProgramError> {
+        match self {
+            TimeUnit::UnixTimestamp => u64::try_from(clock.unix_timestamp).map_err(|_| TokenDistributorError::MathOverflow.into()),
+            TimeUnit::Slot => Ok(clock.slot),
+            TimeUnit::Epoch => Ok(clock.epoch),
+        }
+    }
+
+    // longest period duration accepted in this unit
+    pub fn max_period_duration(self) -> u64 {
+        match self {
+            TimeUnit::UnixTimestamp => MAX_PERIOD_DURATION,
+            TimeUnit::Slot => MAX_PERIOD_DURATION_SLOTS,
+            TimeUnit::Epoch => MAX_PERIOD_DURATION_EPOCHS,
+        }
+    }
+}
+
+// limit on the claims of a merkle distributor, keeping its bitmap well under the 10MB account limit
+pub const MAX_DISTRIBUTOR_NODES: u64 = 10_000_000;
+
+// How a schedule's periods are measured. Fixed periods are period_duration of the time unit long.
+// Calendar periods are period_duration months or quarters long and end on the UTC anniversaries of
+// the start, so "monthly on the 1st" stays on the 1st - they need a unix timestamp schedule.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum PeriodType {
+    #[default]
+    Fixed = 0,
+    CalendarMonth = 1,
+    CalendarQuarter = 2,
+}
+
+impl PeriodType {
+    // calendar months in one unit of period_duration, None for fixed periods
+    pub fn months(self) -> Option<u64> {
+        match self {
+            PeriodType::Fixed => None,
+            PeriodType::CalendarMonth => Some(1),
+            PeriodType::CalendarQuarter => Some(3),
+        }
+    }
+}
+
+// How much of a lockup has vested after each period. Linear splits it evenly across the periods.
+// Steps and PiecewiseLinear follow curve points of (period, cumulative basis points): steps vest a
+// point's basis points once its period unlocks, piecewise linear interpolates between the points,
+// starting from 0 at period 0. Either way the last point is 100% at the last period.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[borsh(use_discriminant = true)]
+#[repr(u8)]
+pub enum CurveType {
+    #[default]
+    Linear = 0,
+    Steps = 1,
+    PiecewiseLinear = 2,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CurvePoint {
+    pub period: u16,
+    pub basis_points: u16,
+}
+
+// vesting curve of a CreateLockupSchedule instruction, points are empty for Linear
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VestingCurve {
+    pub curve_type: CurveType,
+    pub points: Vec<CurvePoint>,
+}
 
 // LOCKUP SCHEDULE STATE
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
 pub struct LockupSchedule {
+    pub account_type: AccountType,
+    pub version: u8,
     pub is_initialized: bool,
     pub initializer: Pubkey,
     pub token_mint: Pubkey,
@@ -17,7 +152,242 @@ pub struct LockupSchedule {
     pub number_periods: u64,
     pub period_duration: u64,
     pub total_token_quantity: u64,
-    pub token_quantity_locked: u64
+    pub token_quantity_locked: u64,
+    // can pause and resume the schedule besides the initializer, Pubkey::default() if none
+    pub pause_authority: Pubkey,
+    // while paused nothing can be locked, claimed or redeemed under the schedule
+    pub paused: bool,
+    // can freeze and thaw single lockups under the schedule, Pubkey::default() if none
+    pub compliance_authority: Pubkey,
+    // unit of start_timestamp and period_duration, unix seconds on schedules created before it was set
+    pub time_unit: TimeUnit,
+    // fixed periods on schedules created before it was set
+    pub period_type: PeriodType,
+    // linear on schedules created before it was set
+    pub curve_type: CurveType,
+    // the first curve_points_len of curve_points are the curve's points, the rest are zeroed
+    pub curve_points_len: u8,
+    pub curve_points: [CurvePoint; MAX_CURVE_POINTS],
+    // zeroed space for fields added without another migration
+    pub reserved: [u8; 28]
+}
+
+impl LockupSchedule {
+    // a new schedule with nothing locked yet, no authorities besides the initializer, measured in
+    // fixed periods of unix seconds and vesting linearly
+    pub fn new(
+        initializer: Pubkey,
+        token_mint: Pubkey,
+        start_timestamp: u64,
+        number_periods: u64,
+        period_duration: u64,
+        total_token_quantity: u64,
+    ) -> Self {
+        LockupSchedule {
+            account_type: AccountType::LockupSchedule,
+            version: STATE_VERSION,
+            is_initialized: true,
+            initializer,
+            token_mint,
+            start_timestamp,
+            number_periods,
+            period_duration,
+            total_token_quantity,
+            token_quantity_locked: 0,
+            pause_authority: Pubkey::default(),
+            paused: false,
+            compliance_authority: Pubkey::default(),
+            time_unit: TimeUnit::UnixTimestamp,
+            period_type: PeriodType::Fixed,
+            curve_type: CurveType::Linear,
+            curve_points_len: 0,
+            curve_points: [CurvePoint::default(); MAX_CURVE_POINTS],
+            reserved: [0; 28],
+        }
+    }
+
+    // the initializer, or the pause authority if one is set
+    pub fn is_pause_authority(&self, authority: &Pubkey) -> bool {
+        *authority == self.initializer || (self.pause_authority != Pubkey::default() && *authority == self.pause_authority)
+    }
+
+    // check periods and duration are non-zero, within limits for the time unit and period type, calendar
+    // periods are in unix timestamps and the schedule ends before i64::MAX
+    pub fn validate_period_config(&self) -> Result<(), ProgramError> {
+        if self.number_periods == 0 || self.number_periods > MAX_UNLOCK_PERIODS {
+            return Err(TokenDistributorError::InvalidPeriodConfig.into());
+        }
+        let max_period_duration = match self.period_type.months() {
+            Some(months) if self.time_unit == TimeUnit::UnixTimestamp => MAX_PERIOD_DURATION_MONTHS / months,
+            Some(_) => return Err(TokenDistributorError::InvalidPeriodConfig.into()),
+            None => self.time_unit.max_period_duration(),
+        };
+        if self.period_duration == 0 || self.period_duration > max_period_duration {
+            return Err(TokenDistributorError::InvalidPeriodConfig.into());
+        }
+        if self.period_end(self.number_periods)? > i64::MAX as u64 {
+            return Err(TokenDistributorError::InvalidPeriodConfig.into());
+        }
+        Ok(())
+    }
+
+    // set the vesting curve, which validate_curve checks against the period config
+    pub fn set_curve(&mut self, curve: &VestingCurve) -> Result<(), ProgramError> {
+        if curve.points.len() > MAX_CURVE_POINTS {
+            return Err(TokenDistributorError::InvalidCurve.into());
+        }
+        self.curve_type = curve.curve_type;
+        self.curve_points_len = curve.points.len() as u8;
+        self.curve_points = [CurvePoint::default(); MAX_CURVE_POINTS];
+        self.curve_points[..curve.points.len()].copy_from_slice(&curve.points);
+        Ok(())
+    }
+
+    pub fn curve_points(&self) -> &[CurvePoint] {
+        &self.curve_points[..cmp::min(self.curve_points_len as usize, MAX_CURVE_POINTS)]
+    }
+
+    // check a linear curve has no points, and any other curve's points are in increasing periods
+    // from period 1, never decrease and end at 100% at the last period
+    pub fn validate_curve(&self) -> Result<(), ProgramError> {
+        let points = self.curve_points();
+        if self.curve_type == CurveType::Linear {
+            if !points.is_empty() {
+                return Err(TokenDistributorError::InvalidCurve.into());
+            }
+            return Ok(());
+        }
+        let mut previous = CurvePoint::default();
+        for point in points {
+            if point.period <= previous.period || point.basis_points < previous.basis_points {
+                return Err(TokenDistributorError::InvalidCurve.into());
+            }
+            previous = *point;
+        }
+        if previous.period as u64 != self.number_periods || previous.basis_points as u64 != BASIS_POINTS {
+            return Err(TokenDistributorError::InvalidCurve.into());
+        }
+        Ok(())
+    }
+
+    // time period no. `period` ends and unlocks at - period * period_duration after the start, or for
+    // calendar periods the UTC anniversary of the start that many months later
+    pub fn period_end(&self, period: u64) -> Result<u64, ProgramError> {
+        let duration = period
+            .checked_mul(self.period_duration)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        let period_end = match self.period_type.months() {
+            Some(months) => duration
+                .checked_mul(months)
+                .and_then(|months| calendar::add_months(self.start_timestamp, months)),
+            None => duration.checked_add(self.start_timestamp),
+        };
+        Ok(period_end.ok_or(TokenDistributorError::MathOverflow)?)
+    }
+
+    // no. periods unlocked at current_timestamp, capped at the total no. periods
+    pub fn periods_unlocked(&self, current_timestamp: u64) -> Result<u64, ProgramError> {
+        if current_timestamp <= self.start_timestamp {
+            return Ok(0);
+        }
+        let periods_elapsed = match self.period_type.months() {
+            Some(months) => self.period_duration
+                .checked_mul(months)
+                .and_then(|period_months| calendar::months_elapsed(self.start_timestamp, current_timestamp).checked_div(period_months)),
+            None => current_timestamp
+                .checked_sub(self.start_timestamp)
+                .and_then(|elapsed| elapsed.checked_div(self.period_duration)),
+        }.ok_or(TokenDistributorError::InvalidPeriodConfig)?;
+        Ok(cmp::min(periods_elapsed, self.number_periods))
+    }
+
+    // no. tokens per period = lockup token quantity / no. periods
+    pub fn tokens_per_period(&self, token_quantity: u64) -> Result<u64, ProgramError> {
+        let tokens_per_period = token_quantity
+            .checked_div(self.number_periods)
+            .ok_or(TokenDistributorError::InvalidPeriodConfig)?;
+        Ok(tokens_per_period)
+    }
+
+    // no. tokens of a lockup vested at current_timestamp
+    pub fn vested_amount(&self, token_quantity: u64, current_timestamp: u64) -> Result<u64, ProgramError> {
+        self.vested_at_period(token_quantity, self.periods_unlocked(current_timestamp)?)
+    }
+
+    // no. tokens of a lockup vested once `period` periods have unlocked, rounded down along the
+    // curve - the final period also releases the rounding remainder
+    pub fn vested_at_period(&self, token_quantity: u64, period: u64) -> Result<u64, ProgramError> {
+        if period >= self.number_periods {
+            return Ok(token_quantity);
+        }
+        // vested share of the lockup = numerator / (denominator * BASIS_POINTS)
+        let (numerator, denominator) = match self.curve_type {
+            CurveType::Linear => {
+                let vested_amount = period
+                    .checked_mul(self.tokens_per_period(token_quantity)?)
+                    .ok_or(TokenDistributorError::MathOverflow)?;
+                return Ok(vested_amount);
+            },
+            CurveType::Steps => {
+                let basis_points = self.curve_points().iter()
+                    .take_while(|point| point.period as u64 <= period)
+                    .last()
+                    .map_or(0, |point| point.basis_points);
+                (basis_points as u128, 1)
+            },
+            CurveType::PiecewiseLinear => {
+                // the segment between the last point at or before the period and the next one
+                let points = self.curve_points();
+                let next = points.iter().position(|point| point.period as u64 > period)
+                    .ok_or(TokenDistributorError::InvalidCurve)?;
+                let start = if next == 0 { CurvePoint::default() } else { points[next - 1] };
+                let end = points[next];
+                let segment_periods = (end.period - start.period) as u128;
+                let segment_basis_points = (end.basis_points - start.basis_points) as u128;
+                let periods_into_segment = period as u128 - start.period as u128;
+                (start.basis_points as u128 * segment_periods + segment_basis_points * periods_into_segment, segment_periods)
+            },
+        };
+        let vested_amount = token_quantity as u128 * numerator / (denominator * BASIS_POINTS as u128);
+        Ok(vested_amount as u64)
+    }
+
+    // no. whole periods a lockup's redeemed amount covers, every period once it is all redeemed
+    pub fn periods_covered(&self, token_quantity: u64, redeemed_amount: u64) -> Result<u64, ProgramError> {
+        if redeemed_amount >= token_quantity {
+            return Ok(self.number_periods);
+        }
+        if self.curve_type == CurveType::Linear {
+            let tokens_per_period = self.tokens_per_period(token_quantity)?;
+            if tokens_per_period == 0 {
+                return Ok(0);
+            }
+            return Ok(cmp::min(redeemed_amount / tokens_per_period, self.number_periods));
+        }
+        // the last period vested by the redeemed amount - curves never decrease, so binary search
+        let (mut covered, mut uncovered) = (0, self.number_periods);
+        while uncovered - covered > 1 {
+            let period = covered + (uncovered - covered) / 2;
+            if self.vested_at_period(token_quantity, period)? <= redeemed_amount {
+                covered = period;
+            } else {
+                uncovered = period;
+            }
+        }
+        Ok(covered)
+    }
+
+    // timestamp the next period unlocks at, None once every period has unlocked
+    pub fn next_unlock_timestamp(&self, current_timestamp: u64) -> Result<Option<u64>, ProgramError> {
+        let periods_unlocked = self.periods_unlocked(current_timestamp)?;
+        if periods_unlocked == self.number_periods {
+            return Ok(None);
+        }
+        let next_period = periods_unlocked
+            .checked_add(1)
+            .ok_or(TokenDistributorError::MathOverflow)?;
+        Ok(Some(self.period_end(next_period)?))
+    }
 }
 
 impl Sealed for LockupSchedule {}
@@ -29,82 +399,83 @@ impl IsInitialized for LockupSchedule {
 }
 
 impl Pack for LockupSchedule {
-    // is_intialized=1, initializer=32, mint=32, start_timestamp=8, number_periods=8, duration=8, total_quantity=8, quantity_locked=8
-    const LEN: usize = 105;
+    // account_type=1, version=1, is_intialized=1, initializer=32, mint=32, start_timestamp=8, number_periods=8, duration=8, total_quantity=8, quantity_locked=8,
+    // pause_authority=32, paused=1, compliance_authority=32, time_unit=1, period_type=1, curve_type=1, curve_points_len=1,
+    // curve_points=16*4, reserved=28
+    const LEN: usize = 268;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, LockupSchedule::LEN];
-        let (
-            is_initialized,
-            initializer,
-            token_mint,
-            start_timestamp,
-            number_periods,
-            period_duration,
-            total_token_quantity,
-            token_quantity_locked
-        ) = array_refs![src, 1, 32, 32, 8, 8, 8, 8, 8];
-        let is_initialized = match is_initialized {
-            [0] => false,
-            [1] => true,
-            _ => return Err(TokenDistributorError::InvalidLockupScheduleData.into())
-        };
-
-        Ok(LockupSchedule {
-            is_initialized,
-            initializer: Pubkey::new_from_array(*initializer),
-            token_mint: Pubkey::new_from_array(*token_mint),
-            start_timestamp: u64::from_le_bytes(*start_timestamp),
-            number_periods: u64::from_le_bytes(*number_periods),
-            period_duration: u64::from_le_bytes(*period_duration),
-            total_token_quantity: u64::from_le_bytes(*total_token_quantity),
-            token_quantity_locked: u64::from_le_bytes(*token_quantity_locked)
-        })
+        let lockup_schedule = Self::try_from_slice(src).map_err(|_| TokenDistributorError::InvalidLockupScheduleData)?;
+        lockup_schedule.account_type.check(lockup_schedule.version, lockup_schedule.is_initialized, AccountType::LockupSchedule)?;
+        Ok(lockup_schedule)
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, LockupSchedule::LEN];
-        let (
-            is_initialized_dst,
-            initializer_dst,
-            token_mint_dst,
-            start_timestamp_dst,
-            number_periods_dst,
-            period_duration_dst,
-            total_token_quantity_dst,
-            token_quantity_locked_dst
-        ) = mut_array_refs![dst, 1, 32, 32, 8, 8, 8, 8, 8];
-
-        let LockupSchedule {
-            is_initialized,
-            initializer,
-            token_mint,
-            start_timestamp,
-            number_periods,
-            period_duration,
-            total_token_quantity,
-            token_quantity_locked
-        } = self;
-
-        is_initialized_dst[0] = *is_initialized as u8;
-        initializer_dst.copy_from_slice(initializer.as_ref());
-        token_mint_dst.copy_from_slice(token_mint.as_ref());
-        *start_timestamp_dst = start_timestamp.to_le_bytes();
-        *number_periods_dst = number_periods.to_le_bytes();
-        *period_duration_dst = period_duration.to_le_bytes();
-        *total_token_quantity_dst = total_token_quantity.to_le_bytes();
-        *token_quantity_locked_dst = token_quantity_locked.to_le_bytes();
+        let mut dst = dst;
+        self.serialize(&mut dst).unwrap()
     }
 }
 
 // LOCKUP STATE
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Lockup {
+    pub account_type: AccountType,
+    pub version: u8,
     pub is_initialized: bool,
     pub lockup_schedule_state: Pubkey,
     pub receiving_account: Pubkey,
     pub lockup_token_account: Pubkey,
     pub token_quantity: u64,
-    pub periods_redeemed: u64
+    pub periods_redeemed: u64,
+    // set by the schedule's compliance authority to hold redeems of this lockup only
+    pub frozen: bool,
+    // can redeem on behalf of the receiving account, Pubkey::default() if none
+    pub claim_delegate: Pubkey,
+    // token account besides the receiving account's own the delegate may redeem to, Pubkey::default() if none
+    pub delegate_destination: Pubkey,
+    // tokens redeemed so far, worked out from periods_redeemed when a version 0 lockup is migrated
+    pub redeemed_amount: u64,
+    // zeroed space for fields added without another migration
+    pub reserved: [u8; 119]
+}
+
+impl Lockup {
+    // a new lockup of token_quantity, with no redeems, delegate or hold yet
+    pub fn new(lockup_schedule_state: Pubkey, receiving_account: Pubkey, lockup_token_account: Pubkey, token_quantity: u64) -> Self {
+        Lockup {
+            account_type: AccountType::Lockup,
+            version: STATE_VERSION,
+            is_initialized: true,
+            lockup_schedule_state,
+            receiving_account,
+            lockup_token_account,
+            token_quantity,
+            periods_redeemed: 0,
+            frozen: false,
+            claim_delegate: Pubkey::default(),
+            delegate_destination: Pubkey::default(),
+            redeemed_amount: 0,
+            reserved: [0; 119],
+        }
+    }
+
+    // check who may sign a redeem to a token account owned by destination_owner: the receiving
+    // account to anywhere, its claim delegate only to the receiving account's own token accounts
+    // or the delegate destination
+    pub fn check_redeem_authority(&self, signer: &Pubkey, destination: &Pubkey, destination_owner: &Pubkey) -> Result<(), ProgramError> {
+        if *signer == self.receiving_account {
+            return Ok(());
+        }
+        if self.claim_delegate == Pubkey::default() || *signer != self.claim_delegate {
+            return Err(TokenDistributorError::UnauthorizedAccount.into());
+        }
+        if *destination_owner != self.receiving_account
+            && (self.delegate_destination == Pubkey::default() || *destination != self.delegate_destination)
+        {
+            return Err(TokenDistributorError::InvalidDestination.into());
+        }
+        Ok(())
+    }
 }
 
 impl Sealed for Lockup {}
@@ -116,60 +487,152 @@ impl IsInitialized for Lockup {
 }
 
 impl Pack for Lockup {
-    // is_intialized=1, lockup_schedule_state=32, receiving_account=32, lockup_token_account=32, token_quantity=8, periods_redeemed=8
-    const LEN: usize = 113;
+    // account_type=1, version=1, is_intialized=1, lockup_schedule_state=32, receiving_account=32, lockup_token_account=32, token_quantity=8, periods_redeemed=8,
+    // frozen=1, claim_delegate=32, delegate_destination=32, redeemed_amount=8, reserved=119
+    const LEN: usize = 307;
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, Lockup::LEN];
-        let (
-            is_initialized,
-            lockup_schedule_state,
-            receiving_account,
-            lockup_token_account,
-            token_quantity,
-            periods_redeemed
-        ) = array_refs![src, 1, 32, 32, 32, 8, 8];
-        let is_initialized = match is_initialized {
-            [0] => false,
-            [1] => true,
-            _ => return Err(TokenDistributorError::InvalidLockupScheduleData.into())
-        };
+        let lockup = Self::try_from_slice(src).map_err(|_| TokenDistributorError::InvalidLockupScheduleData)?;
+        lockup.account_type.check(lockup.version, lockup.is_initialized, AccountType::Lockup)?;
+        Ok(lockup)
+    }
 
-        Ok(Lockup {
-            is_initialized,
-            lockup_schedule_state: Pubkey::new_from_array(*lockup_schedule_state),
-            receiving_account: Pubkey::new_from_array(*receiving_account),
-            lockup_token_account: Pubkey::new_from_array(*lockup_token_account),
-            token_quantity: u64::from_le_bytes(*token_quantity),
-            periods_redeemed: u64::from_le_bytes(*periods_redeemed)
-        })
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let mut dst = dst;
+        self.serialize(&mut dst).unwrap()
+    }
+}
+
+// MERKLE DISTRIBUTOR STATE
+// Holds the merkle root of (index, wallet, amount) claims against a single funded token account.
+// The account data continues after the header with a bitmap of max_num_nodes bits, one per
+// claim index, set once that index is claimed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MerkleDistributor {
+    pub account_type: AccountType,
+    pub version: u8,
+    pub is_initialized: bool,
+    pub lockup_schedule_state: Pubkey,
+    pub distributor_token_account: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub max_num_nodes: u64,
+    pub total_amount: u64,
+    pub num_nodes_claimed: u64,
+    pub amount_claimed: u64
+}
+
+impl MerkleDistributor {
+    // account size for a distributor of max_num_nodes claims: header + bitmap
+    pub fn space(max_num_nodes: u64) -> usize {
+        Self::LEN + (max_num_nodes as usize).div_ceil(8)
+    }
+
+    // split account data into the header and the claimed bitmap
+    pub fn split_data(data: &mut [u8]) -> Result<(&mut [u8], &mut [u8]), ProgramError> {
+        if data.len() < Self::LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        Ok(data.split_at_mut(Self::LEN))
+    }
+
+    pub fn is_claimed(bitmap: &[u8], index: u64) -> bool {
+        bitmap[(index / 8) as usize] & (1 << (index % 8)) != 0
+    }
+
+    pub fn set_claimed(bitmap: &mut [u8], index: u64) {
+        bitmap[(index / 8) as usize] |= 1 << (index % 8);
+    }
+}
+
+impl Sealed for MerkleDistributor {}
+
+impl IsInitialized for MerkleDistributor {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for MerkleDistributor {
+    // account_type=1, version=1, is_intialized=1, lockup_schedule_state=32, distributor_token_account=32, merkle_root=32, max_num_nodes=8, total_amount=8, num_nodes_claimed=8, amount_claimed=8
+    const LEN: usize = 131;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let distributor = Self::try_from_slice(src).map_err(|_| TokenDistributorError::InvalidLockupScheduleData)?;
+        distributor.account_type.check(distributor.version, distributor.is_initialized, AccountType::MerkleDistributor)?;
+        Ok(distributor)
     }
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, Lockup::LEN];
-        let (
-            is_initialized_dst,
-            lockup_schedule_state_dst,
-            receiving_account_dst,
-            lockup_token_account_dst,
-            token_quantity_dst,
-            periods_redeemed_dst
-        ) = mut_array_refs![dst, 1, 32, 32, 32, 8, 8];
-
-        let Lockup {
-            is_initialized,
-            lockup_schedule_state,
-            receiving_account,
-            lockup_token_account,
-            token_quantity,
-            periods_redeemed
-        } = self;
+        let mut dst = dst;
+        self.serialize(&mut dst).unwrap()
+    }
+}
+
+// LEGACY STATE (version 0)
+// Layouts written by the first deployed version of the program, before the account type tag and
+// version. MigrateAccount reads these and rewrites the account in the current layout, filling new
+// fields from old ones or with their defaults.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockupScheduleV0 {
+    pub is_initialized: bool,
+    pub initializer: Pubkey,
+    pub token_mint: Pubkey,
+    pub start_timestamp: u64,
+    pub number_periods: u64,
+    pub period_duration: u64,
+    pub total_token_quantity: u64,
+    pub token_quantity_locked: u64
+}
+
+impl LockupScheduleV0 {
+    // is_intialized=1, initializer=32, mint=32, start_timestamp=8, number_periods=8, duration=8, total_quantity=8, quantity_locked=8
+    pub const LEN: usize = 105;
 
-        is_initialized_dst[0] = *is_initialized as u8;
-        lockup_schedule_state_dst.copy_from_slice(lockup_schedule_state.as_ref());
-        receiving_account_dst.copy_from_slice(receiving_account.as_ref());
-        lockup_token_account_dst.copy_from_slice(lockup_token_account.as_ref());
-        *token_quantity_dst = token_quantity.to_le_bytes();
-        *periods_redeemed_dst = periods_redeemed.to_le_bytes();
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        Self::try_from_slice(src).map_err(|_| TokenDistributorError::InvalidLockupScheduleData.into())
     }
-}
\ No newline at end of file
+
+    // the current layout of an initialized schedule, without the fields added since
+    pub fn migrate(self) -> LockupSchedule {
+        LockupSchedule {
+            token_quantity_locked: self.token_quantity_locked,
+            ..LockupSchedule::new(
+                self.initializer,
+                self.token_mint,
+                self.start_timestamp,
+                self.number_periods,
+                self.period_duration,
+                self.total_token_quantity,
+            )
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockupV0 {
+    pub is_initialized: bool,
+    pub lockup_schedule_state: Pubkey,
+    pub receiving_account: Pubkey,
+    pub lockup_token_account: Pubkey,
+    pub token_quantity: u64,
+    pub periods_redeemed: u64
+}
+
+impl LockupV0 {
+    // is_intialized=1, lockup_schedule_state=32, receiving_account=32, lockup_token_account=32, token_quantity=8, periods_redeemed=8
+    pub const LEN: usize = 113;
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        Self::try_from_slice(src).map_err(|_| TokenDistributorError::InvalidLockupScheduleData.into())
+    }
+
+    // the current layout of an initialized lockup - redeems paid out whole periods along the schedule
+    // and only counted them, so the amount redeemed is what vested by the last period redeemed
+    pub fn migrate(self, schedule: &LockupSchedule) -> Result<Lockup, ProgramError> {
+        Ok(Lockup {
+            periods_redeemed: self.periods_redeemed,
+            redeemed_amount: schedule.vested_at_period(self.token_quantity, self.periods_redeemed)?,
+            ..Lockup::new(self.lockup_schedule_state, self.receiving_account, self.lockup_token_account, self.token_quantity)
+        })
+    }
+}
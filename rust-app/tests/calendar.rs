@@ -0,0 +1,43 @@
+use bpf_program_template::calendar::{add_months, civil_from_days, days_from_civil, days_in_month, months_elapsed, SECONDS_PER_DAY};
+
+// 2024-01-31T00:00:00Z
+const JAN_31_2024: u64 = 1_706_659_200;
+
+#[test]
+fn test_civil_round_trip() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    assert_eq!(civil_from_days((JAN_31_2024 / SECONDS_PER_DAY) as i64), (2024, 1, 31));
+    for days in (-800_000..800_000).step_by(997) {
+        let (year, month, day) = civil_from_days(days);
+        assert!(day >= 1 && day <= days_in_month(year, month));
+        assert_eq!(days_from_civil(year, month, day), days);
+    }
+    assert_eq!(days_in_month(2024, 2), 29);
+    assert_eq!(days_in_month(2100, 2), 28);
+    assert_eq!(days_in_month(2000, 2), 29);
+}
+
+#[test]
+fn test_add_months() {
+    let time_of_day = 13 * 60 * 60 + 7;
+    let start = JAN_31_2024 + time_of_day;
+    assert_eq!(add_months(start, 0), Some(start));
+    // clamped to the last day of shorter months, keeping the time of day
+    assert_eq!(add_months(start, 1), Some(1_709_164_800 + time_of_day)); // 2024-02-29
+    assert_eq!(add_months(start, 13), Some(1_740_700_800 + time_of_day)); // 2025-02-28
+    // each anniversary is taken from the start, so the day of month comes back
+    assert_eq!(add_months(start, 2), Some(JAN_31_2024 + 60 * SECONDS_PER_DAY + time_of_day)); // 2024-03-31
+    assert_eq!(add_months(start, u64::MAX), None);
+}
+
+#[test]
+fn test_months_elapsed() {
+    let start = JAN_31_2024;
+    assert_eq!(months_elapsed(start, start), 0);
+    assert_eq!(months_elapsed(start, start - 1), 0);
+    assert_eq!(months_elapsed(start, 1_709_164_800 - 1), 0);
+    assert_eq!(months_elapsed(start, 1_709_164_800), 1);
+    assert_eq!(months_elapsed(start, 1_740_700_800), 13);
+    assert_eq!(months_elapsed(start, 1_740_700_800 - 1), 12);
+}
@@ -0,0 +1,314 @@
+#![allow(dead_code)]
+
+use bpf_program_template::{
+    find_token_authority_address,
+    instruction,
+    processor::Processor,
+    state::{Lockup, LockupSchedule, PeriodType, TimeUnit, VestingCurve},
+};
+use solana_program::{
+    clock::Clock,
+    program_pack::{IsInitialized, Pack},
+    pubkey::Pubkey,
+    system_instruction,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::{transfer_fee::instruction::initialize_transfer_fee_config, BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::{Account as TokenAccount, Mint},
+};
+
+pub const PERIOD_DURATION: u64 = 1_000;
+pub const NUMBER_PERIODS: u64 = 4;
+pub const TOTAL_QUANTITY: u64 = 10_000;
+
+pub fn program_test(program_id: Pubkey) -> ProgramTest {
+    ProgramTest::new("bpf_program_template", program_id, processor!(Processor::process))
+}
+
+fn sign(context: &ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair], blockhash: Hash) -> Transaction {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    )
+}
+
+// sign with the payer plus any extra signers and process against a fresh blockhash
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = sign(context, instructions, signers, blockhash);
+    context.banks_client.process_transaction(transaction).await
+}
+
+// process a transaction that must succeed and return its log messages
+pub async fn process_with_logs(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Vec<String> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = sign(context, instructions, signers, blockhash);
+    let result = context.banks_client.process_transaction_with_metadata(transaction).await.unwrap();
+    result.result.unwrap();
+    result.metadata.unwrap().log_messages
+}
+
+pub async fn current_timestamp(context: &mut ProgramTestContext) -> u64 {
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp as u64
+}
+
+// move the Clock sysvar to the given slot, leaving its unix timestamp as is
+pub async fn warp_to_slot_time(context: &mut ProgramTestContext, slot: u64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.slot = slot;
+    context.set_sysvar(&clock);
+}
+
+// move the Clock sysvar to the given unix timestamp
+pub async fn warp_to_timestamp(context: &mut ProgramTestContext, unix_timestamp: u64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp as i64;
+    context.set_sysvar(&clock);
+}
+
+pub async fn create_program_account(
+    context: &mut ProgramTestContext,
+    account: &Keypair,
+    space: usize,
+    owner: &Pubkey,
+) {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let ix = system_instruction::create_account(
+        &context.payer.pubkey(),
+        &account.pubkey(),
+        rent.minimum_balance(space),
+        space as u64,
+        owner,
+    );
+    process(context, &[ix], &[account]).await.unwrap();
+}
+
+pub async fn create_mint(context: &mut ProgramTestContext, mint: &Keypair, authority: &Pubkey) {
+    create_program_account(context, mint, spl_token::state::Mint::LEN, &spl_token::id()).await;
+    let ix = spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), authority, None, 0).unwrap();
+    process(context, &[ix], &[]).await.unwrap();
+}
+
+// Token-2022 mint charging transfer_fee_basis_points on every transfer, up to maximum_fee
+pub async fn create_transfer_fee_mint(
+    context: &mut ProgramTestContext,
+    mint: &Keypair,
+    authority: &Pubkey,
+    transfer_fee_basis_points: u16,
+    maximum_fee: u64,
+) {
+    let space = ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig]).unwrap();
+    create_program_account(context, mint, space, &spl_token_2022::id()).await;
+    let ixs = [
+        initialize_transfer_fee_config(
+            &spl_token_2022::id(),
+            &mint.pubkey(),
+            Some(authority),
+            Some(authority),
+            transfer_fee_basis_points,
+            maximum_fee,
+        ).unwrap(),
+        spl_token_2022::instruction::initialize_mint2(&spl_token_2022::id(), &mint.pubkey(), authority, None, 2).unwrap(),
+    ];
+    process(context, &ixs, &[]).await.unwrap();
+}
+
+// token program owning the mint
+async fn token_program_of(context: &mut ProgramTestContext, mint: &Pubkey) -> Pubkey {
+    context.banks_client.get_account(*mint).await.unwrap().unwrap().owner
+}
+
+// token account of the mint's token program, with room for any extensions the mint requires
+pub async fn create_token_account(
+    context: &mut ProgramTestContext,
+    account: &Keypair,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) {
+    let mint_account = context.banks_client.get_account(*mint).await.unwrap().unwrap();
+    let mint_extensions = StateWithExtensions::<Mint>::unpack(&mint_account.data).unwrap().get_extension_types().unwrap();
+    let account_extensions = ExtensionType::get_required_init_account_extensions(&mint_extensions);
+    let space = ExtensionType::try_calculate_account_len::<TokenAccount>(&account_extensions).unwrap();
+    create_program_account(context, account, space, &mint_account.owner).await;
+    let ix = spl_token_2022::instruction::initialize_account3(&mint_account.owner, &account.pubkey(), mint, owner).unwrap();
+    process(context, &[ix], &[]).await.unwrap();
+}
+
+// mint authority is always the context payer
+pub async fn mint_to(context: &mut ProgramTestContext, mint: &Pubkey, account: &Pubkey, amount: u64) {
+    let payer = context.payer.pubkey();
+    let token_program = token_program_of(context, mint).await;
+    let ix = spl_token_2022::instruction::mint_to(&token_program, mint, account, &payer, &[], amount).unwrap();
+    process(context, &[ix], &[]).await.unwrap();
+}
+
+pub async fn token_balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let account = context.banks_client.get_account(*account).await.unwrap().unwrap();
+    StateWithExtensions::<TokenAccount>::unpack(&account.data).unwrap().base.amount
+}
+
+pub async fn get_state<T: Pack + IsInitialized>(context: &mut ProgramTestContext, account: &Pubkey) -> T {
+    let account = context.banks_client.get_account(*account).await.unwrap().unwrap();
+    T::unpack(&account.data).unwrap()
+}
+
+// a schedule with NUMBER_PERIODS periods of PERIOD_DURATION starting 100s (or slots, or epochs) from now
+pub struct ScheduleFixture {
+    pub program_id: Pubkey,
+    pub initializer: Keypair,
+    pub schedule: Keypair,
+    pub mint: Keypair,
+    pub token_program: Pubkey,
+    pub start_timestamp: u64,
+}
+
+impl ScheduleFixture {
+    // schedule of a new spl-token mint
+    pub async fn new(context: &mut ProgramTestContext, program_id: Pubkey) -> Self {
+        let mint = Keypair::new();
+        let payer = context.payer.pubkey();
+        create_mint(context, &mint, &payer).await;
+        Self::for_mint(context, program_id, mint).await
+    }
+
+    // schedule of an existing spl-token or Token-2022 mint, with the context payer as mint authority
+    pub async fn for_mint(context: &mut ProgramTestContext, program_id: Pubkey, mint: Keypair) -> Self {
+        Self::with_periods(context, program_id, mint, TimeUnit::UnixTimestamp, PeriodType::Fixed, PERIOD_DURATION, VestingCurve::default()).await
+    }
+
+    // schedule of an existing mint with NUMBER_PERIODS periods of period_duration in time_unit or
+    // period_type, vesting along curve
+    pub async fn with_periods(
+        context: &mut ProgramTestContext,
+        program_id: Pubkey,
+        mint: Keypair,
+        time_unit: TimeUnit,
+        period_type: PeriodType,
+        period_duration: u64,
+        curve: VestingCurve,
+    ) -> Self {
+        let initializer = Keypair::new();
+        let schedule = Keypair::new();
+        let token_program = token_program_of(context, &mint.pubkey()).await;
+        create_program_account(context, &schedule, LockupSchedule::LEN, &program_id).await;
+
+        let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+        let start_timestamp = time_unit.current_time(&clock).unwrap() + 100;
+        let ix = instruction::create_lockup_schedule(
+            &program_id,
+            &initializer.pubkey(),
+            &schedule.pubkey(),
+            &mint.pubkey(),
+            start_timestamp,
+            NUMBER_PERIODS,
+            period_duration,
+            TOTAL_QUANTITY,
+            time_unit,
+            period_type,
+            curve,
+        ).unwrap();
+        process(context, &[ix], &[&initializer]).await.unwrap();
+
+        ScheduleFixture { program_id, initializer, schedule, mint, token_program, start_timestamp }
+    }
+
+    // fund a source token account with token_quantity and lock it for a new receiver
+    pub async fn lock(&self, context: &mut ProgramTestContext, token_quantity: u64) -> LockupFixture {
+        let receiver = Keypair::new();
+        let lockup = Keypair::new();
+        let source_token_account = Keypair::new();
+        let lockup_token_account = Keypair::new();
+        let receiving_token_account = Keypair::new();
+        let (token_authority, _bump_seed) = find_token_authority_address(&self.program_id, &lockup.pubkey());
+        create_token_account(context, &source_token_account, &self.mint.pubkey(), &self.initializer.pubkey()).await;
+        create_token_account(context, &lockup_token_account, &self.mint.pubkey(), &token_authority).await;
+        create_token_account(context, &receiving_token_account, &self.mint.pubkey(), &receiver.pubkey()).await;
+        mint_to(context, &self.mint.pubkey(), &source_token_account.pubkey(), token_quantity).await;
+        create_program_account(context, &lockup, Lockup::LEN, &self.program_id).await;
+
+        let ix = self.lock_ix(&lockup.pubkey(), &receiver.pubkey(), &lockup_token_account.pubkey(), &source_token_account.pubkey(), token_quantity);
+        process(context, &[ix], &[&self.initializer]).await.unwrap();
+
+        LockupFixture { receiver, lockup, lockup_token_account, receiving_token_account }
+    }
+
+    pub fn lock_ix(
+        &self,
+        lockup: &Pubkey,
+        receiver: &Pubkey,
+        lockup_token_account: &Pubkey,
+        source_token_account: &Pubkey,
+        token_quantity: u64,
+    ) -> Instruction {
+        instruction::lock_tokens(
+            &self.program_id,
+            &self.initializer.pubkey(),
+            &self.schedule.pubkey(),
+            lockup,
+            receiver,
+            lockup_token_account,
+            &self.token_program,
+            source_token_account,
+            &self.mint.pubkey(),
+            token_quantity,
+        ).unwrap()
+    }
+
+    pub async fn set_paused(&self, context: &mut ProgramTestContext, authority: &Keypair, paused: bool) -> Result<(), BanksClientError> {
+        let ix = instruction::set_paused(&self.program_id, &authority.pubkey(), &self.schedule.pubkey(), paused).unwrap();
+        process(context, &[ix], &[authority]).await
+    }
+}
+
+pub struct LockupFixture {
+    pub receiver: Keypair,
+    pub lockup: Keypair,
+    pub lockup_token_account: Keypair,
+    pub receiving_token_account: Keypair,
+}
+
+impl LockupFixture {
+    pub fn redeem_ix(&self, schedule: &ScheduleFixture) -> Instruction {
+        self.redeem_amount_ix(schedule, None)
+    }
+
+    pub fn redeem_amount_ix(&self, schedule: &ScheduleFixture, amount: Option<u64>) -> Instruction {
+        instruction::redeem_tokens(
+            &schedule.program_id,
+            &self.receiver.pubkey(),
+            &schedule.schedule.pubkey(),
+            &self.lockup.pubkey(),
+            &self.lockup_token_account.pubkey(),
+            &find_token_authority_address(&schedule.program_id, &self.lockup.pubkey()).0,
+            &self.receiving_token_account.pubkey(),
+            &schedule.token_program,
+            &schedule.mint.pubkey(),
+            amount,
+        ).unwrap()
+    }
+
+    pub async fn redeem(&self, context: &mut ProgramTestContext, schedule: &ScheduleFixture) -> Result<(), BanksClientError> {
+        let ix = self.redeem_ix(schedule);
+        process(context, &[ix], &[&self.receiver]).await
+    }
+}
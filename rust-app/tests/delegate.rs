@@ -0,0 +1,116 @@
+mod common;
+
+use bpf_program_template::{
+    error::TokenDistributorError,
+    find_token_authority_address,
+    instruction,
+    state::Lockup,
+};
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+
+fn assert_custom_error(result: Result<(), BanksClientError>, error: TokenDistributorError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32)),
+    );
+}
+
+async fn set_claim_delegate(
+    context: &mut ProgramTestContext,
+    schedule: &ScheduleFixture,
+    lockup: &LockupFixture,
+    claim_delegate: &Pubkey,
+    delegate_destination: &Pubkey,
+) -> Result<(), BanksClientError> {
+    let ix = instruction::set_claim_delegate(
+        &schedule.program_id,
+        &lockup.receiver.pubkey(),
+        &lockup.lockup.pubkey(),
+        claim_delegate,
+        delegate_destination,
+    ).unwrap();
+    process(context, &[ix], &[&lockup.receiver]).await
+}
+
+// redeem signed by the delegate to the given token account
+async fn delegate_redeem(
+    context: &mut ProgramTestContext,
+    schedule: &ScheduleFixture,
+    lockup: &LockupFixture,
+    delegate: &Keypair,
+    destination: &Pubkey,
+) -> Result<(), BanksClientError> {
+    let ix = instruction::redeem_tokens(
+        &schedule.program_id,
+        &delegate.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.lockup.pubkey(),
+        &lockup.lockup_token_account.pubkey(),
+        &find_token_authority_address(&schedule.program_id, &lockup.lockup.pubkey()).0,
+        destination,
+        &schedule.token_program,
+        &schedule.mint.pubkey(),
+        None,
+    ).unwrap();
+    process(context, &[ix], &[delegate]).await
+}
+
+#[tokio::test]
+async fn test_claim_delegate_redeems_to_receiver() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    let delegate = Keypair::new();
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+
+    // not a delegate yet
+    let result = delegate_redeem(&mut context, &schedule, &lockup, &delegate, &lockup.receiving_token_account.pubkey()).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+
+    set_claim_delegate(&mut context, &schedule, &lockup, &delegate.pubkey(), &Pubkey::default()).await.unwrap();
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.claim_delegate, delegate.pubkey());
+
+    delegate_redeem(&mut context, &schedule, &lockup, &delegate, &lockup.receiving_token_account.pubkey()).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 1_000);
+
+    // removing the delegate takes its rights away again
+    set_claim_delegate(&mut context, &schedule, &lockup, &Pubkey::default(), &Pubkey::default()).await.unwrap();
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 2).await;
+    let result = delegate_redeem(&mut context, &schedule, &lockup, &delegate, &lockup.receiving_token_account.pubkey()).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+}
+
+#[tokio::test]
+async fn test_claim_delegate_destination() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    let delegate = Keypair::new();
+    let delegate_token_account = Keypair::new();
+    let custody_token_account = Keypair::new();
+    create_token_account(&mut context, &delegate_token_account, &schedule.mint.pubkey(), &delegate.pubkey()).await;
+    create_token_account(&mut context, &custody_token_account, &schedule.mint.pubkey(), &Pubkey::new_unique()).await;
+    set_claim_delegate(&mut context, &schedule, &lockup, &delegate.pubkey(), &custody_token_account.pubkey()).await.unwrap();
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+
+    // the delegate can't pay itself
+    let result = delegate_redeem(&mut context, &schedule, &lockup, &delegate, &delegate_token_account.pubkey()).await;
+    assert_custom_error(result, TokenDistributorError::InvalidDestination);
+
+    delegate_redeem(&mut context, &schedule, &lockup, &delegate, &custody_token_account.pubkey()).await.unwrap();
+    assert_eq!(token_balance(&mut context, &custody_token_account.pubkey()).await, 1_000);
+
+    // only the receiver sets the delegate
+    let ix = instruction::set_claim_delegate(&program_id, &delegate.pubkey(), &lockup.lockup.pubkey(), &delegate.pubkey(), &Pubkey::default()).unwrap();
+    let result = process(&mut context, &[ix], &[&delegate]).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bpf_program_template::{
+    error::TokenDistributorError,
+    event::{TokenDistributorEvent, EVENT_LOG_PREFIX},
+    instruction,
+    state::Lockup,
+};
+use common::*;
+use solana_program::{instruction::InstructionError, pubkey::Pubkey};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+
+fn assert_custom_error(result: Result<(), BanksClientError>, error: TokenDistributorError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32)),
+    );
+}
+
+fn set_frozen_ix(schedule: &ScheduleFixture, authority: &Keypair, lockup: &LockupFixture, frozen: bool) -> Instruction {
+    instruction::set_frozen(&schedule.program_id, &authority.pubkey(), &schedule.schedule.pubkey(), &lockup.lockup.pubkey(), frozen).unwrap()
+}
+
+async fn set_compliance_authority(context: &mut ProgramTestContext, schedule: &ScheduleFixture, compliance_authority: &Pubkey) {
+    let ix = instruction::set_compliance_authority(
+        &schedule.program_id,
+        &schedule.initializer.pubkey(),
+        &schedule.schedule.pubkey(),
+        compliance_authority,
+    ).unwrap();
+    process(context, &[ix], &[&schedule.initializer]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_frozen_lockup_rejects_redeem_until_thawed() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let frozen_lockup = schedule.lock(&mut context, 4_000).await;
+    let other_lockup = schedule.lock(&mut context, 4_000).await;
+    let compliance_authority = Keypair::new();
+    set_compliance_authority(&mut context, &schedule, &compliance_authority.pubkey()).await;
+
+    let ix = set_frozen_ix(&schedule, &compliance_authority, &frozen_lockup, true);
+    let logs = process_with_logs(&mut context, &[ix], &[&compliance_authority]).await;
+    let event = TokenDistributorEvent::FreezeSet {
+        lockup_schedule_state: schedule.schedule.pubkey(),
+        lockup_state: frozen_lockup.lockup.pubkey(),
+        authority: compliance_authority.pubkey(),
+        frozen: true,
+    };
+    assert!(logs.contains(&format!("Program log: {}{}", EVENT_LOG_PREFIX, STANDARD.encode(event.pack()))));
+    assert!(get_state::<Lockup>(&mut context, &frozen_lockup.lockup.pubkey()).await.frozen);
+
+    // only the frozen lockup is held
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+    assert_custom_error(frozen_lockup.redeem(&mut context, &schedule).await, TokenDistributorError::LockupFrozen);
+    other_lockup.redeem(&mut context, &schedule).await.unwrap();
+
+    // periods kept unlocking while frozen
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 2).await;
+    let ix = set_frozen_ix(&schedule, &compliance_authority, &frozen_lockup, false);
+    process(&mut context, &[ix], &[&compliance_authority]).await.unwrap();
+    frozen_lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &frozen_lockup.receiving_token_account.pubkey()).await, 2_000);
+}
+
+#[tokio::test]
+async fn test_set_frozen_requires_compliance_authority() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    // nobody can freeze before a compliance authority is set, not even the initializer
+    let ix = set_frozen_ix(&schedule, &schedule.initializer, &lockup, true);
+    let result = process(&mut context, &[ix], &[&schedule.initializer]).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+
+    let compliance_authority = Keypair::new();
+    set_compliance_authority(&mut context, &schedule, &compliance_authority.pubkey()).await;
+    let ix = set_frozen_ix(&schedule, &lockup.receiver, &lockup, true);
+    let result = process(&mut context, &[ix], &[&lockup.receiver]).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+}
@@ -0,0 +1,114 @@
+use bpf_program_template::{instruction::TokenDistributorInstruction, state::{CurvePoint, CurveType, PeriodType, TimeUnit, VestingCurve}};
+use solana_program::program_error::ProgramError;
+
+#[test]
+fn test_pack_unpack_round_trip() {
+    let instructions = [
+        TokenDistributorInstruction::CreateLockupSchedule {
+            start_timestamp: 1_700_000_000,
+            total_unlock_periods: 12,
+            period_duration: 2_592_000,
+            total_lockup_quantity: u64::MAX,
+            time_unit: None,
+            period_type: None,
+            curve: None,
+        },
+        TokenDistributorInstruction::CreateLockupSchedule {
+            start_timestamp: 250_000_000,
+            total_unlock_periods: 12,
+            period_duration: 6_480_000,
+            total_lockup_quantity: 1_000,
+            time_unit: Some(TimeUnit::Slot),
+            period_type: None,
+            curve: None,
+        },
+        TokenDistributorInstruction::CreateLockupSchedule {
+            start_timestamp: 1_767_225_600,
+            total_unlock_periods: 8,
+            period_duration: 1,
+            total_lockup_quantity: 1_000,
+            time_unit: Some(TimeUnit::UnixTimestamp),
+            period_type: Some(PeriodType::CalendarQuarter),
+            curve: Some(VestingCurve {
+                curve_type: CurveType::Steps,
+                points: vec![CurvePoint { period: 4, basis_points: 2_000 }, CurvePoint { period: 8, basis_points: 10_000 }],
+            }),
+        },
+        TokenDistributorInstruction::LockTokens { token_quantity: 42 },
+        TokenDistributorInstruction::RedeemTokens { amount: None },
+        TokenDistributorInstruction::RedeemTokens { amount: Some(1_500) },
+        TokenDistributorInstruction::MigrateAccount {},
+        TokenDistributorInstruction::LockLamports { lamports: 2_000_000_000 },
+        TokenDistributorInstruction::RedeemLamports { amount: Some(500_000_000) },
+    ];
+    for instruction in instructions {
+        assert_eq!(TokenDistributorInstruction::unpack(&instruction.pack()).unwrap(), instruction);
+    }
+}
+
+#[test]
+fn test_unpack_legacy_layout() {
+    // tag followed by fixed-offset little-endian u64s
+    let mut data = vec![0];
+    for value in [10u64, 4, 60, 1_000] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    assert_eq!(
+        TokenDistributorInstruction::unpack(&data).unwrap(),
+        TokenDistributorInstruction::CreateLockupSchedule {
+            start_timestamp: 10,
+            total_unlock_periods: 4,
+            period_duration: 60,
+            total_lockup_quantity: 1_000,
+            time_unit: None,
+            period_type: None,
+            curve: None,
+        },
+    );
+
+    let mut data = vec![1];
+    data.extend_from_slice(&7u64.to_le_bytes());
+    assert_eq!(
+        TokenDistributorInstruction::unpack(&data).unwrap(),
+        TokenDistributorInstruction::LockTokens { token_quantity: 7 },
+    );
+
+    // a redeem from before the optional amount was appended redeems everything
+    assert_eq!(
+        TokenDistributorInstruction::unpack(&[2]).unwrap(),
+        TokenDistributorInstruction::RedeemTokens { amount: None },
+    );
+}
+
+#[test]
+fn test_unpack_invalid() {
+    assert!(TokenDistributorInstruction::unpack(&[]).is_err());
+    assert!(TokenDistributorInstruction::unpack(&[1, 0, 0]).is_err());
+    assert!(TokenDistributorInstruction::unpack(&[0xff]).is_err());
+}
+
+#[test]
+fn test_unpack_rejects_malformed_data() {
+    // a truncated Some(amount) must not fall back to redeeming everything
+    let data = TokenDistributorInstruction::RedeemTokens { amount: Some(1_500) }.pack();
+    assert_eq!(TokenDistributorInstruction::unpack(&data[..data.len() - 1]), Err(ProgramError::InvalidInstructionData));
+    // nor must a bad option tag
+    assert_eq!(TokenDistributorInstruction::unpack(&[2, 7]), Err(ProgramError::InvalidInstructionData));
+
+    // trailing garbage after a valid instruction
+    let mut data = TokenDistributorInstruction::RedeemTokens { amount: None }.pack();
+    data.push(0xff);
+    assert_eq!(TokenDistributorInstruction::unpack(&data), Err(ProgramError::InvalidInstructionData));
+
+    // a legacy schedule with corrupt trailing option bytes doesn't decode as a default schedule
+    let mut data = vec![0];
+    for value in [10u64, 4, 60, 1_000] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(&[0xff; 3]);
+    assert_eq!(TokenDistributorInstruction::unpack(&data), Err(ProgramError::InvalidInstructionData));
+    let mut data = vec![1];
+    data.extend_from_slice(&7u64.to_le_bytes());
+    data.push(0);
+    assert_eq!(TokenDistributorInstruction::unpack(&data), Err(ProgramError::InvalidInstructionData));
+}
@@ -0,0 +1,779 @@
+mod common;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bpf_program_template::{
+    error::TokenDistributorError,
+    event::{TokenDistributorEvent, EVENT_LOG_PREFIX},
+    find_pda_address,
+    find_token_authority_address,
+    instruction,
+    state::{AccountType, Lockup, LockupSchedule, LockupScheduleV0, LockupV0, CurvePoint, CurveType, PeriodType, TimeUnit, VestingCurve, STATE_VERSION},
+};
+use common::*;
+use solana_program::{instruction::InstructionError, program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+
+fn assert_custom_error(result: Result<(), BanksClientError>, error: TokenDistributorError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32)),
+    );
+}
+
+#[tokio::test]
+async fn test_create_lockup_schedule() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+
+    let state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert_eq!(state.account_type, AccountType::LockupSchedule);
+    assert_eq!(state.version, STATE_VERSION);
+    assert!(state.is_initialized);
+    assert_eq!(state.initializer, schedule.initializer.pubkey());
+    assert_eq!(state.token_mint, schedule.mint.pubkey());
+    assert_eq!(state.start_timestamp, schedule.start_timestamp);
+    assert_eq!(state.number_periods, NUMBER_PERIODS);
+    assert_eq!(state.period_duration, PERIOD_DURATION);
+    assert_eq!(state.total_token_quantity, TOTAL_QUANTITY);
+    assert_eq!(state.token_quantity_locked, 0);
+    assert_eq!(state.time_unit, TimeUnit::UnixTimestamp);
+}
+
+#[tokio::test]
+async fn test_create_lockup_schedule_invalid_period_config() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let initializer = Keypair::new();
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &mint, &payer).await;
+    let start_timestamp = current_timestamp(&mut context).await + 100;
+
+    for (number_periods, period_duration) in [(0, PERIOD_DURATION), (NUMBER_PERIODS, 0)] {
+        let schedule = Keypair::new();
+        create_program_account(&mut context, &schedule, LockupSchedule::LEN, &program_id).await;
+        let ix = instruction::create_lockup_schedule(
+            &program_id,
+            &initializer.pubkey(),
+            &schedule.pubkey(),
+            &mint.pubkey(),
+            start_timestamp,
+            number_periods,
+            period_duration,
+            TOTAL_QUANTITY,
+            TimeUnit::UnixTimestamp,
+            PeriodType::Fixed,
+            VestingCurve::default(),
+        ).unwrap();
+        let result = process(&mut context, &[ix], &[&initializer]).await;
+        assert_custom_error(result, TokenDistributorError::InvalidPeriodConfig);
+    }
+}
+
+#[tokio::test]
+async fn test_create_lockup_schedule_start_in_past() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let initializer = Keypair::new();
+    let schedule = Keypair::new();
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &mint, &payer).await;
+    create_program_account(&mut context, &schedule, LockupSchedule::LEN, &program_id).await;
+
+    let start_timestamp = current_timestamp(&mut context).await - 1;
+    let ix = instruction::create_lockup_schedule(
+        &program_id,
+        &initializer.pubkey(),
+        &schedule.pubkey(),
+        &mint.pubkey(),
+        start_timestamp,
+        NUMBER_PERIODS,
+        PERIOD_DURATION,
+        TOTAL_QUANTITY,
+        TimeUnit::UnixTimestamp,
+        PeriodType::Fixed,
+        VestingCurve::default(),
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&initializer]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidStartTimestamp);
+}
+
+#[tokio::test]
+async fn test_slot_schedule() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &mint, &payer).await;
+    let schedule = ScheduleFixture::with_periods(&mut context, program_id, mint, TimeUnit::Slot, PeriodType::Fixed, PERIOD_DURATION, VestingCurve::default()).await;
+    let state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert_eq!(state.time_unit, TimeUnit::Slot);
+
+    // only the slot counts - a unix timestamp past the end neither blocks locking nor unlocks anything
+    warp_to_timestamp(&mut context, u32::MAX as u64).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 0);
+
+    warp_to_slot_time(&mut context, schedule.start_timestamp + PERIOD_DURATION * 2).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 2_000);
+}
+
+#[tokio::test]
+async fn test_calendar_month_schedule() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &mint, &payer).await;
+    let schedule = ScheduleFixture::with_periods(&mut context, program_id, mint, TimeUnit::UnixTimestamp, PeriodType::CalendarMonth, 1, VestingCurve::default()).await;
+    let state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert_eq!(state.period_type, PeriodType::CalendarMonth);
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    // the second period unlocks on the second monthly anniversary of the start, not a second earlier
+    let second_anniversary = state.period_end(2).unwrap();
+    warp_to_timestamp(&mut context, second_anniversary - 1).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 1_000);
+
+    warp_to_timestamp(&mut context, second_anniversary).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 2_000);
+}
+
+#[tokio::test]
+async fn test_steps_curve_schedule() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &mint, &payer).await;
+    // back-loaded 5/15/30/50
+    let points = [(1, 500), (2, 2_000), (3, 5_000), (4, 10_000)]
+        .iter()
+        .map(|&(period, basis_points)| CurvePoint { period, basis_points })
+        .collect();
+    let curve = VestingCurve { curve_type: CurveType::Steps, points };
+    let schedule = ScheduleFixture::with_periods(&mut context, program_id, mint, TimeUnit::UnixTimestamp, PeriodType::Fixed, PERIOD_DURATION, curve).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 2).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 800);
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.periods_redeemed, 2);
+
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 4_000);
+}
+
+#[tokio::test]
+async fn test_create_lockup_schedule_invalid_curve() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let initializer = Keypair::new();
+    let schedule = Keypair::new();
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &mint, &payer).await;
+    create_program_account(&mut context, &schedule, LockupSchedule::LEN, &program_id).await;
+
+    // stops at 90%
+    let points = vec![CurvePoint { period: 2, basis_points: 5_000 }, CurvePoint { period: NUMBER_PERIODS as u16, basis_points: 9_000 }];
+    let ix = instruction::create_lockup_schedule(
+        &program_id,
+        &initializer.pubkey(),
+        &schedule.pubkey(),
+        &mint.pubkey(),
+        current_timestamp(&mut context).await + 100,
+        NUMBER_PERIODS,
+        PERIOD_DURATION,
+        TOTAL_QUANTITY,
+        TimeUnit::UnixTimestamp,
+        PeriodType::Fixed,
+        VestingCurve { curve_type: CurveType::PiecewiseLinear, points },
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&initializer]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidCurve);
+}
+
+#[tokio::test]
+async fn test_lock_tokens() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.account_type, AccountType::Lockup);
+    assert_eq!(state.version, STATE_VERSION);
+    assert!(state.is_initialized);
+    assert_eq!(state.lockup_schedule_state, schedule.schedule.pubkey());
+    assert_eq!(state.receiving_account, lockup.receiver.pubkey());
+    assert_eq!(state.lockup_token_account, lockup.lockup_token_account.pubkey());
+    assert_eq!(state.token_quantity, 4_000);
+    assert_eq!(state.periods_redeemed, 0);
+
+    let schedule_state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert_eq!(schedule_state.token_quantity_locked, 4_000);
+
+    // lockup token account is owned by the lockup's program-derived address
+    let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.lockup.pubkey());
+    let account = context.banks_client.get_account(lockup.lockup_token_account.pubkey()).await.unwrap().unwrap();
+    assert_eq!(spl_token::state::Account::unpack(&account.data).unwrap().owner, token_authority);
+}
+
+#[tokio::test]
+async fn test_lock_tokens_wrong_mint() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+
+    let other_mint = Keypair::new();
+    let source_token_account = Keypair::new();
+    let lockup_token_account = Keypair::new();
+    let lockup = Keypair::new();
+    let payer = context.payer.pubkey();
+    let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.pubkey());
+    create_mint(&mut context, &other_mint, &payer).await;
+    create_token_account(&mut context, &source_token_account, &other_mint.pubkey(), &schedule.initializer.pubkey()).await;
+    create_token_account(&mut context, &lockup_token_account, &other_mint.pubkey(), &token_authority).await;
+    mint_to(&mut context, &other_mint.pubkey(), &source_token_account.pubkey(), 1_000).await;
+    create_program_account(&mut context, &lockup, Lockup::LEN, &program_id).await;
+
+    let ix = instruction::lock_tokens(
+        &program_id,
+        &schedule.initializer.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.pubkey(),
+        &Pubkey::new_unique(),
+        &lockup_token_account.pubkey(),
+        &spl_token::id(),
+        &source_token_account.pubkey(),
+        &other_mint.pubkey(),
+        1_000,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&schedule.initializer]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidMint);
+}
+
+#[tokio::test]
+async fn test_lock_tokens_wrong_initializer() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+
+    let impostor = Keypair::new();
+    let source_token_account = Keypair::new();
+    let lockup_token_account = Keypair::new();
+    let lockup = Keypair::new();
+    let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.pubkey());
+    create_token_account(&mut context, &source_token_account, &schedule.mint.pubkey(), &impostor.pubkey()).await;
+    create_token_account(&mut context, &lockup_token_account, &schedule.mint.pubkey(), &token_authority).await;
+    mint_to(&mut context, &schedule.mint.pubkey(), &source_token_account.pubkey(), 1_000).await;
+    create_program_account(&mut context, &lockup, Lockup::LEN, &program_id).await;
+
+    let ix = instruction::lock_tokens(
+        &program_id,
+        &impostor.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.pubkey(),
+        &Pubkey::new_unique(),
+        &lockup_token_account.pubkey(),
+        &spl_token::id(),
+        &source_token_account.pubkey(),
+        &schedule.mint.pubkey(),
+        1_000,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&impostor]).await;
+    assert_custom_error(result, TokenDistributorError::IncorrectOwner);
+}
+
+#[tokio::test]
+async fn test_lock_tokens_invalid_lockup_token_account() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = Keypair::new();
+    create_program_account(&mut context, &lockup, Lockup::LEN, &program_id).await;
+    let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.pubkey());
+    let (shared_pda, _bump_seed) = find_pda_address(&program_id);
+
+    let source_token_account = Keypair::new();
+    create_token_account(&mut context, &source_token_account, &schedule.mint.pubkey(), &schedule.initializer.pubkey()).await;
+    mint_to(&mut context, &schedule.mint.pubkey(), &source_token_account.pubkey(), 1_000).await;
+
+    // a vault the initializer still owns, one of the lockup's PDA that already holds tokens, and
+    // one of the PDA version 0 lockups shared
+    let unowned_vault = Keypair::new();
+    let funded_vault = Keypair::new();
+    let shared_vault = Keypair::new();
+    create_token_account(&mut context, &unowned_vault, &schedule.mint.pubkey(), &schedule.initializer.pubkey()).await;
+    create_token_account(&mut context, &funded_vault, &schedule.mint.pubkey(), &token_authority).await;
+    mint_to(&mut context, &schedule.mint.pubkey(), &funded_vault.pubkey(), 1).await;
+    create_token_account(&mut context, &shared_vault, &schedule.mint.pubkey(), &shared_pda).await;
+
+    for vault in [&unowned_vault, &funded_vault, &shared_vault] {
+        let ix = schedule.lock_ix(&lockup.pubkey(), &Pubkey::new_unique(), &vault.pubkey(), &source_token_account.pubkey(), 1_000);
+        let result = process(&mut context, &[ix], &[&schedule.initializer]).await;
+        assert_custom_error(result, TokenDistributorError::InvalidLockupTokenAccount);
+    }
+
+    // nor the drained vault of a redeemed lockup, which stays owned by that lockup's PDA
+    let redeemed_lockup = schedule.lock(&mut context, 1_000).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    redeemed_lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &redeemed_lockup.lockup_token_account.pubkey()).await, 0);
+    let mint = Keypair::from_bytes(&schedule.mint.to_bytes()).unwrap();
+    let later_schedule = ScheduleFixture::for_mint(&mut context, program_id, mint).await;
+    let vault = redeemed_lockup.lockup_token_account.pubkey();
+    let ix = later_schedule.lock_ix(&lockup.pubkey(), &Pubkey::new_unique(), &vault, &source_token_account.pubkey(), 1_000);
+    let result = process(&mut context, &[ix], &[&later_schedule.initializer]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidLockupTokenAccount);
+}
+
+#[tokio::test]
+async fn test_lock_tokens_existing_lockup() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 1_000).await;
+    let lockup_state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+
+    // locking again into the same state account can't hand the lockup to another receiver
+    let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.lockup.pubkey());
+    let source_token_account = Keypair::new();
+    let lockup_token_account = Keypair::new();
+    create_token_account(&mut context, &source_token_account, &schedule.mint.pubkey(), &schedule.initializer.pubkey()).await;
+    create_token_account(&mut context, &lockup_token_account, &schedule.mint.pubkey(), &token_authority).await;
+    mint_to(&mut context, &schedule.mint.pubkey(), &source_token_account.pubkey(), 1_000).await;
+    let ix = schedule.lock_ix(&lockup.lockup.pubkey(), &Pubkey::new_unique(), &lockup_token_account.pubkey(), &source_token_account.pubkey(), 1_000);
+    let result = process(&mut context, &[ix], &[&schedule.initializer]).await;
+    assert_eq!(result.unwrap_err().unwrap(), TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized));
+    assert_eq!(get_state::<Lockup>(&mut context, &lockup.lockup.pubkey()).await, lockup_state);
+
+    // nor can a schedule be created over an existing one
+    let ix = instruction::create_lockup_schedule(
+        &program_id,
+        &schedule.initializer.pubkey(),
+        &schedule.schedule.pubkey(),
+        &schedule.mint.pubkey(),
+        schedule.start_timestamp,
+        NUMBER_PERIODS,
+        PERIOD_DURATION,
+        TOTAL_QUANTITY,
+        TimeUnit::UnixTimestamp,
+        PeriodType::Fixed,
+        VestingCurve::default(),
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&schedule.initializer]).await;
+    assert_eq!(result.unwrap_err().unwrap(), TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized));
+}
+
+#[tokio::test]
+async fn test_lock_and_redeem_token_2022_transfer_fee() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    // 1% on every transfer
+    create_transfer_fee_mint(&mut context, &mint, &payer, 100, u64::MAX).await;
+    let schedule = ScheduleFixture::for_mint(&mut context, program_id, mint).await;
+    assert_eq!(schedule.token_program, spl_token_2022::id());
+
+    // the fee withheld on the way in is not locked
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.token_quantity, 3_960);
+    let schedule_state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert_eq!(schedule_state.token_quantity_locked, 3_960);
+    assert_eq!(token_balance(&mut context, &lockup.lockup_token_account.pubkey()).await, 3_960);
+
+    // and the fee on the way out is paid by the receiver
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 3_920);
+    assert_eq!(token_balance(&mut context, &lockup.lockup_token_account.pubkey()).await, 0);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_across_periods() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    let receiving = lockup.receiving_token_account.pubkey();
+
+    // before the start nothing is redeemable
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &receiving).await, 0);
+
+    // (expected periods redeemed, expected balance) at each point in time
+    let checkpoints = [
+        (schedule.start_timestamp + PERIOD_DURATION - 1, 0, 0),
+        (schedule.start_timestamp + PERIOD_DURATION, 1, 1_000),
+        (schedule.start_timestamp + PERIOD_DURATION * 2 + 500, 2, 2_000),
+        (schedule.start_timestamp + PERIOD_DURATION * 2 + 999, 2, 2_000),
+        (schedule.start_timestamp + PERIOD_DURATION * 10, 4, 4_000),
+    ];
+    for (timestamp, periods_redeemed, balance) in checkpoints {
+        warp_to_timestamp(&mut context, timestamp).await;
+        lockup.redeem(&mut context, &schedule).await.unwrap();
+        assert_eq!(token_balance(&mut context, &receiving).await, balance);
+        let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+        assert_eq!(state.periods_redeemed, periods_redeemed);
+    }
+    assert_eq!(token_balance(&mut context, &lockup.lockup_token_account.pubkey()).await, 0);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_sends_remainder_after_final_period() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    // 4_003 / 4 periods leaves 3 tokens of rounding dust
+    let lockup = schedule.lock(&mut context, 4_003).await;
+
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 3).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 3_000);
+
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 4).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 4_003);
+    assert_eq!(token_balance(&mut context, &lockup.lockup_token_account.pubkey()).await, 0);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_completed_lockup() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    // tokens sent to the lockup token account go along with the redeem that completes the lockup
+    mint_to(&mut context, &schedule.mint.pubkey(), &lockup.lockup_token_account.pubkey(), 5).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    let ix = lockup.redeem_amount_ix(&schedule, Some(3_999));
+    process(&mut context, &[ix], &[&lockup.receiver]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.lockup_token_account.pubkey()).await, 6);
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 4_005);
+
+    // but once completed the lockup can't be redeemed again, so nothing sent later is swept
+    mint_to(&mut context, &schedule.mint.pubkey(), &lockup.lockup_token_account.pubkey(), 7).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * (NUMBER_PERIODS + 1)).await;
+    let result = lockup.redeem(&mut context, &schedule).await;
+    assert_custom_error(result, TokenDistributorError::LockupRedeemed);
+    assert_eq!(token_balance(&mut context, &lockup.lockup_token_account.pubkey()).await, 7);
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 4_005);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_partial_amount() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_003).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 2).await;
+
+    let ix = lockup.redeem_amount_ix(&schedule, Some(500));
+    process(&mut context, &[ix], &[&lockup.receiver]).await.unwrap();
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.redeemed_amount, 500);
+    assert_eq!(state.periods_redeemed, 0);
+
+    // nothing, or more than the 1_500 left unlocked, is rejected
+    for amount in [0, 1_501] {
+        let ix = lockup.redeem_amount_ix(&schedule, Some(amount));
+        let result = process(&mut context, &[ix], &[&lockup.receiver]).await;
+        assert_custom_error(result, TokenDistributorError::InvalidRedeemAmount);
+    }
+
+    // the rest stays redeemable, in full or in part
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 2_000);
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.redeemed_amount, 2_000);
+    assert_eq!(state.periods_redeemed, 2);
+
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    let ix = lockup.redeem_amount_ix(&schedule, Some(2_003));
+    process(&mut context, &[ix], &[&lockup.receiver]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 4_003);
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.periods_redeemed, NUMBER_PERIODS);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_emits_event() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_003).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 4).await;
+
+    let ix = lockup.redeem_ix(&schedule);
+    let logs = process_with_logs(&mut context, &[ix], &[&lockup.receiver]).await;
+    let event = TokenDistributorEvent::TokensRedeemed {
+        lockup_schedule_state: schedule.schedule.pubkey(),
+        lockup_state: lockup.lockup.pubkey(),
+        receiving_account: lockup.receiver.pubkey(),
+        amount: 4_003,
+        periods: 4,
+        periods_redeemed: 4,
+    };
+    let expected = format!("Program log: {}{}", EVENT_LOG_PREFIX, STANDARD.encode(event.pack()));
+    assert_eq!(logs.iter().filter(|log| log.contains(EVENT_LOG_PREFIX)).collect::<Vec<_>>(), vec![&expected]);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_wrong_signer() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+
+    let impostor = Keypair::new();
+    let ix = instruction::redeem_tokens(
+        &program_id,
+        &impostor.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.lockup.pubkey(),
+        &lockup.lockup_token_account.pubkey(),
+        &find_token_authority_address(&program_id, &lockup.lockup.pubkey()).0,
+        &lockup.receiving_token_account.pubkey(),
+        &spl_token::id(),
+        &schedule.mint.pubkey(),
+        None,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&impostor]).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_wrong_schedule() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let other_schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+
+    let ix = lockup.redeem_ix(&other_schedule);
+    let result = process(&mut context, &[ix], &[&lockup.receiver]).await;
+    assert_custom_error(result, TokenDistributorError::IncorrectSchedule);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_wrong_lockup_token_account() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 1_000).await;
+    let other_lockup = schedule.lock(&mut context, 4_000).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+
+    // signed for by the other lockup's PDA
+    let ix = instruction::redeem_tokens(
+        &program_id,
+        &lockup.receiver.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.lockup.pubkey(),
+        &other_lockup.lockup_token_account.pubkey(),
+        &find_token_authority_address(&program_id, &other_lockup.lockup.pubkey()).0,
+        &lockup.receiving_token_account.pubkey(),
+        &spl_token::id(),
+        &schedule.mint.pubkey(),
+        None,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&lockup.receiver]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidLockupTokenAccount);
+    assert_eq!(token_balance(&mut context, &other_lockup.lockup_token_account.pubkey()).await, 4_000);
+}
+
+#[tokio::test]
+async fn test_redeem_tokens_wrong_mint() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+
+    let other_mint = Keypair::new();
+    let payer = context.payer.pubkey();
+    create_mint(&mut context, &other_mint, &payer).await;
+    let ix = instruction::redeem_tokens(
+        &program_id,
+        &lockup.receiver.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.lockup.pubkey(),
+        &lockup.lockup_token_account.pubkey(),
+        &find_token_authority_address(&program_id, &lockup.lockup.pubkey()).0,
+        &lockup.receiving_token_account.pubkey(),
+        &spl_token::id(),
+        &other_mint.pubkey(),
+        None,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&lockup.receiver]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidMint);
+}
+
+// overwrite a state account with an older layout, funded for the old size only
+async fn set_legacy_account<T: borsh::BorshSerialize>(context: &mut ProgramTestContext, address: &Pubkey, state: &T, program_id: &Pubkey) {
+    let data = borsh::to_vec(state).unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut account = AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), program_id);
+    account.set_data_from_slice(&data);
+    context.set_account(address, &account);
+}
+
+// a schedule as version 0 stored it
+fn legacy_schedule(schedule_state: &LockupSchedule) -> LockupScheduleV0 {
+    LockupScheduleV0 {
+        is_initialized: schedule_state.is_initialized,
+        initializer: schedule_state.initializer,
+        token_mint: schedule_state.token_mint,
+        start_timestamp: schedule_state.start_timestamp,
+        number_periods: schedule_state.number_periods,
+        period_duration: schedule_state.period_duration,
+        total_token_quantity: schedule_state.total_token_quantity,
+        token_quantity_locked: schedule_state.token_quantity_locked,
+    }
+}
+
+// a lockup as version 0 stored it, without the amount redeemed
+fn legacy_lockup(lockup_state: &Lockup) -> LockupV0 {
+    LockupV0 {
+        is_initialized: lockup_state.is_initialized,
+        lockup_schedule_state: lockup_state.lockup_schedule_state,
+        receiving_account: lockup_state.receiving_account,
+        lockup_token_account: lockup_state.lockup_token_account,
+        token_quantity: lockup_state.token_quantity,
+        periods_redeemed: lockup_state.periods_redeemed,
+    }
+}
+
+#[tokio::test]
+async fn test_migrate_legacy_accounts() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    let schedule_state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    let lockup_state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    set_legacy_account(&mut context, &schedule.schedule.pubkey(), &legacy_schedule(&schedule_state), &program_id).await;
+    set_legacy_account(&mut context, &lockup.lockup.pubkey(), &legacy_lockup(&lockup_state), &program_id).await;
+
+    // version 0 lockup token accounts were owned by the PDA every lockup shared
+    let (shared_pda, _bump_seed) = find_pda_address(&program_id);
+    let mut token_account = context.banks_client.get_account(lockup.lockup_token_account.pubkey()).await.unwrap().unwrap();
+    let mut token_account_state = spl_token::state::Account::unpack(&token_account.data).unwrap();
+    token_account_state.owner = shared_pda;
+    spl_token::state::Account::pack(token_account_state, &mut token_account.data).unwrap();
+    context.set_account(&lockup.lockup_token_account.pubkey(), &AccountSharedData::from(token_account));
+
+    // old layouts are rejected until migrated
+    let result = lockup.redeem(&mut context, &schedule).await;
+    assert!(result.is_err());
+
+    // anyone can migrate, paying the extra rent
+    let payer = Keypair::new();
+    let fund = solana_program::system_instruction::transfer(&context.payer.pubkey(), &payer.pubkey(), 1_000_000_000);
+    process(&mut context, &[fund], &[]).await.unwrap();
+    let ixs = [
+        instruction::migrate_account(&program_id, &payer.pubkey(), &schedule.schedule.pubkey(), None).unwrap(),
+        instruction::migrate_account(&program_id, &payer.pubkey(), &lockup.lockup.pubkey(), Some(&schedule.schedule.pubkey())).unwrap(),
+    ];
+    process(&mut context, &ixs, &[&payer]).await.unwrap();
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account = context.banks_client.get_account(lockup.lockup.pubkey()).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), Lockup::LEN);
+    assert!(rent.is_exempt(account.lamports, Lockup::LEN));
+    assert_eq!(get_state::<LockupSchedule>(&mut context, &schedule.schedule.pubkey()).await, schedule_state);
+    assert_eq!(get_state::<Lockup>(&mut context, &lockup.lockup.pubkey()).await, lockup_state);
+
+    // migrating again is a no-op
+    let ix = instruction::migrate_account(&program_id, &payer.pubkey(), &lockup.lockup.pubkey(), Some(&schedule.schedule.pubkey())).unwrap();
+    process(&mut context, &[ix], &[&payer]).await.unwrap();
+
+    // and the shared PDA still signs their redeems
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+    let ix = instruction::redeem_tokens(
+        &program_id,
+        &lockup.receiver.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.lockup.pubkey(),
+        &lockup.lockup_token_account.pubkey(),
+        &shared_pda,
+        &lockup.receiving_token_account.pubkey(),
+        &spl_token::id(),
+        &schedule.mint.pubkey(),
+        None,
+    ).unwrap();
+    process(&mut context, &[ix], &[&lockup.receiver]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 1_000);
+}
+
+#[tokio::test]
+async fn test_migrate_redeemed_lockup() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_003).await;
+    let other_schedule = ScheduleFixture::new(&mut context, program_id).await;
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+
+    // a version 0 lockup only counted the period it paid out
+    let lockup_state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    set_legacy_account(&mut context, &lockup.lockup.pubkey(), &legacy_lockup(&lockup_state), &program_id).await;
+
+    // the lockup's own schedule is needed to work out the amount redeemed
+    let payer = context.payer.pubkey();
+    let ix = instruction::migrate_account(&program_id, &payer, &lockup.lockup.pubkey(), None).unwrap();
+    let result = process(&mut context, &[ix], &[]).await;
+    assert_eq!(result.unwrap_err().unwrap(), TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys));
+    let ix = instruction::migrate_account(&program_id, &payer, &lockup.lockup.pubkey(), Some(&other_schedule.schedule.pubkey())).unwrap();
+    let result = process(&mut context, &[ix], &[]).await;
+    assert_custom_error(result, TokenDistributorError::IncorrectSchedule);
+
+    let ix = instruction::migrate_account(&program_id, &payer, &lockup.lockup.pubkey(), Some(&schedule.schedule.pubkey())).unwrap();
+    process(&mut context, &[ix], &[]).await.unwrap();
+    assert_eq!(get_state::<Lockup>(&mut context, &lockup.lockup.pubkey()).await, lockup_state);
+
+    // only the second period is left to redeem, in part or in full
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 2).await;
+    let ix = lockup.redeem_amount_ix(&schedule, Some(1_001));
+    let result = process(&mut context, &[ix], &[&lockup.receiver]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidRedeemAmount);
+    let ix = lockup.redeem_amount_ix(&schedule, Some(400));
+    process(&mut context, &[ix], &[&lockup.receiver]).await.unwrap();
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 2_000);
+}
+
+#[tokio::test]
+async fn test_migrate_account_wrong_owner() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let account = Keypair::new();
+    create_program_account(&mut context, &account, LockupScheduleV0::LEN, &Pubkey::new_unique()).await;
+
+    let ix = instruction::migrate_account(&program_id, &context.payer.pubkey(), &account.pubkey(), None).unwrap();
+    let result = process(&mut context, &[ix], &[]).await;
+    assert_custom_error(result, TokenDistributorError::IncorrectOwner);
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use bpf_program_template::{
+    error::TokenDistributorError,
+    find_token_authority_address,
+    instruction,
+    merkle::{self, MerkleTree},
+    state::{AccountType, Lockup, LockupSchedule, MerkleDistributor, STATE_VERSION},
+};
+use common::*;
+use solana_program::{instruction::InstructionError, program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+
+fn assert_custom_error(result: Result<(), BanksClientError>, error: TokenDistributorError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32)),
+    );
+}
+
+// a distributor funded for CLAIMS under a ScheduleFixture
+struct DistributorFixture {
+    schedule: ScheduleFixture,
+    distributor: Keypair,
+    distributor_token_account: Keypair,
+    wallets: Vec<Keypair>,
+    tree: MerkleTree,
+}
+
+const CLAIMS: [u64; 3] = [4_000, 1_000, 2_003];
+
+impl DistributorFixture {
+    async fn new(context: &mut ProgramTestContext, program_id: Pubkey, token_quantity: u64) -> Self {
+        let schedule = ScheduleFixture::new(context, program_id).await;
+        let distributor = Keypair::new();
+        let distributor_token_account = Keypair::new();
+        let source_token_account = Keypair::new();
+        let wallets: Vec<Keypair> = CLAIMS.iter().map(|_| Keypair::new()).collect();
+        let leaves = wallets.iter().zip(CLAIMS).enumerate()
+            .map(|(index, (wallet, amount))| merkle::leaf_hash(index as u64, &wallet.pubkey(), amount))
+            .collect();
+        let tree = MerkleTree::new(leaves);
+
+        let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &distributor.pubkey());
+        let mint = schedule.mint.pubkey();
+        create_token_account(context, &source_token_account, &mint, &schedule.initializer.pubkey()).await;
+        create_token_account(context, &distributor_token_account, &mint, &token_authority).await;
+        mint_to(context, &mint, &source_token_account.pubkey(), token_quantity).await;
+        let max_num_nodes = CLAIMS.len() as u64;
+        create_program_account(context, &distributor, MerkleDistributor::space(max_num_nodes), &program_id).await;
+
+        let ix = instruction::create_merkle_distributor(
+            &program_id,
+            &schedule.initializer.pubkey(),
+            &schedule.schedule.pubkey(),
+            &distributor.pubkey(),
+            &distributor_token_account.pubkey(),
+            &schedule.token_program,
+            &source_token_account.pubkey(),
+            &mint,
+            tree.root(),
+            max_num_nodes,
+            token_quantity,
+        ).unwrap();
+        process(context, &[ix], &[&schedule.initializer]).await.unwrap();
+
+        DistributorFixture { schedule, distributor, distributor_token_account, wallets, tree }
+    }
+
+    // claim `amount` for the wallet at `index` into a new lockup, with the proof of `proof_index`
+    async fn claim(
+        &self,
+        context: &mut ProgramTestContext,
+        index: usize,
+        amount: u64,
+        proof_index: usize,
+    ) -> (Result<(), BanksClientError>, LockupFixture) {
+        let program_id = self.schedule.program_id;
+        let wallet = &self.wallets[index];
+        let lockup = Keypair::new();
+        let lockup_token_account = Keypair::new();
+        let receiving_token_account = Keypair::new();
+        let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.pubkey());
+        let mint = self.schedule.mint.pubkey();
+        create_token_account(context, &lockup_token_account, &mint, &token_authority).await;
+        create_token_account(context, &receiving_token_account, &mint, &wallet.pubkey()).await;
+        create_program_account(context, &lockup, Lockup::LEN, &program_id).await;
+
+        let ix = instruction::claim_tokens(
+            &program_id,
+            &wallet.pubkey(),
+            &self.distributor.pubkey(),
+            &self.schedule.schedule.pubkey(),
+            &lockup.pubkey(),
+            &lockup_token_account.pubkey(),
+            &self.distributor_token_account.pubkey(),
+            &self.schedule.token_program,
+            &mint,
+            index as u64,
+            amount,
+            self.tree.proof(proof_index).unwrap(),
+        ).unwrap();
+        let result = process(context, &[ix], &[wallet]).await;
+        let receiver = Keypair::from_bytes(&wallet.to_bytes()).unwrap();
+        (result, LockupFixture { receiver, lockup, lockup_token_account, receiving_token_account })
+    }
+
+    async fn state(&self, context: &mut ProgramTestContext) -> (MerkleDistributor, Vec<u8>) {
+        let account = context.banks_client.get_account(self.distributor.pubkey()).await.unwrap().unwrap();
+        let (header, bitmap) = account.data.split_at(MerkleDistributor::LEN);
+        (MerkleDistributor::unpack(header).unwrap(), bitmap.to_vec())
+    }
+}
+
+#[test]
+fn test_merkle_tree_proofs() {
+    // every leaf of odd and even sized trees, including a leaf carried up unchanged
+    for size in 1..=9u64 {
+        let leaves: Vec<_> = (0..size).map(|index| merkle::leaf_hash(index, &Pubkey::new_unique(), 100 + index)).collect();
+        let tree = MerkleTree::new(leaves.clone());
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = tree.proof(index).unwrap();
+            assert!(merkle::verify_proof(&proof, &tree.root(), *leaf));
+            // a proof only verifies its own leaf
+            let other = leaves[(index + 1) % leaves.len()];
+            assert_eq!(merkle::verify_proof(&proof, &tree.root(), other), other == *leaf);
+        }
+        assert!(tree.proof(size as usize).is_none());
+    }
+    assert_eq!(MerkleTree::new(vec![]).root(), [0; 32]);
+}
+
+#[tokio::test]
+async fn test_create_merkle_distributor() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let distributor = DistributorFixture::new(&mut context, program_id, 7_003).await;
+
+    let (state, bitmap) = distributor.state(&mut context).await;
+    assert_eq!(state.account_type, AccountType::MerkleDistributor);
+    assert_eq!(state.lockup_schedule_state, distributor.schedule.schedule.pubkey());
+    assert_eq!(state.distributor_token_account, distributor.distributor_token_account.pubkey());
+    assert_eq!(state.merkle_root, distributor.tree.root());
+    assert_eq!(state.max_num_nodes, 3);
+    assert_eq!(state.total_amount, 7_003);
+    assert_eq!(state.num_nodes_claimed, 0);
+    assert_eq!(bitmap, vec![0]);
+    assert_eq!(token_balance(&mut context, &distributor.distributor_token_account.pubkey()).await, 7_003);
+}
+
+#[tokio::test]
+async fn test_claim_tokens_and_redeem() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let distributor = DistributorFixture::new(&mut context, program_id, 7_003).await;
+
+    let mut lockups = vec![];
+    for (index, amount) in CLAIMS.iter().enumerate() {
+        let (result, lockup) = distributor.claim(&mut context, index, *amount, index).await;
+        result.unwrap();
+        let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+        assert_eq!(state.lockup_schedule_state, distributor.schedule.schedule.pubkey());
+        assert_eq!(state.receiving_account, distributor.wallets[index].pubkey());
+        assert_eq!(state.lockup_token_account, lockup.lockup_token_account.pubkey());
+        assert_eq!(state.token_quantity, *amount);
+        lockups.push(lockup);
+    }
+
+    let (state, bitmap) = distributor.state(&mut context).await;
+    assert_eq!(state.num_nodes_claimed, 3);
+    assert_eq!(state.amount_claimed, 7_003);
+    assert_eq!(bitmap, vec![0b111]);
+    let schedule_state: LockupSchedule = get_state(&mut context, &distributor.schedule.schedule.pubkey()).await;
+    assert_eq!(schedule_state.token_quantity_locked, 7_003);
+    assert_eq!(token_balance(&mut context, &distributor.distributor_token_account.pubkey()).await, 0);
+
+    // claimed lockups vest and redeem like any other
+    warp_to_timestamp(&mut context, distributor.schedule.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    lockups[2].redeem(&mut context, &distributor.schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockups[2].receiving_token_account.pubkey()).await, 2_003);
+}
+
+#[tokio::test]
+async fn test_claim_tokens_twice() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let distributor = DistributorFixture::new(&mut context, program_id, 7_003).await;
+
+    distributor.claim(&mut context, 1, 1_000, 1).await.0.unwrap();
+    let (result, _) = distributor.claim(&mut context, 1, 1_000, 1).await;
+    assert_custom_error(result, TokenDistributorError::AlreadyClaimed);
+}
+
+#[tokio::test]
+async fn test_claim_tokens_invalid_proof() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let distributor = DistributorFixture::new(&mut context, program_id, 7_003).await;
+
+    // more than the leaf's amount
+    let (result, _) = distributor.claim(&mut context, 1, 1_001, 1).await;
+    assert_custom_error(result, TokenDistributorError::InvalidMerkleProof);
+    // another leaf's proof
+    let (result, _) = distributor.claim(&mut context, 1, 1_000, 0).await;
+    assert_custom_error(result, TokenDistributorError::InvalidMerkleProof);
+}
+
+#[tokio::test]
+async fn test_claim_tokens_exceeds_distributor() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    // funded for the first two claims only
+    let distributor = DistributorFixture::new(&mut context, program_id, 5_000).await;
+
+    distributor.claim(&mut context, 0, 4_000, 0).await.0.unwrap();
+    let (result, _) = distributor.claim(&mut context, 2, 2_003, 2).await;
+    assert_custom_error(result, TokenDistributorError::ClaimExceedsDistributor);
+    distributor.claim(&mut context, 1, 1_000, 1).await.0.unwrap();
+}
+
+#[tokio::test]
+async fn test_migrate_distributor_is_noop() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let distributor = DistributorFixture::new(&mut context, program_id, 7_003).await;
+
+    // distributors never had a version 0 layout, so there is nothing to migrate
+    let before = context.banks_client.get_account(distributor.distributor.pubkey()).await.unwrap().unwrap();
+    let ix = instruction::migrate_account(&program_id, &context.payer.pubkey(), &distributor.distributor.pubkey(), None).unwrap();
+    process(&mut context, &[ix], &[]).await.unwrap();
+    let after = context.banks_client.get_account(distributor.distributor.pubkey()).await.unwrap().unwrap();
+    assert_eq!(after.data, before.data);
+    let (state, _bitmap) = distributor.state(&mut context).await;
+    assert_eq!(state.version, STATE_VERSION);
+    distributor.claim(&mut context, 1, 1_000, 1).await.0.unwrap();
+}
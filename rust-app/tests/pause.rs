@@ -0,0 +1,96 @@
+mod common;
+
+use bpf_program_template::{
+    error::TokenDistributorError,
+    find_token_authority_address,
+    instruction,
+    state::{Lockup, LockupSchedule},
+};
+use common::*;
+use solana_program::{instruction::InstructionError, program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+
+fn assert_custom_error(result: Result<(), BanksClientError>, error: TokenDistributorError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32)),
+    );
+}
+
+#[tokio::test]
+async fn test_pause_blocks_redeem_and_resume_pays_accrued() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = schedule.lock(&mut context, 4_000).await;
+
+    schedule.set_paused(&mut context, &schedule.initializer, true).await.unwrap();
+    let state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert!(state.paused);
+
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION).await;
+    assert_custom_error(lockup.redeem(&mut context, &schedule).await, TokenDistributorError::SchedulePaused);
+
+    // periods kept unlocking while paused
+    warp_to_timestamp(&mut context, schedule.start_timestamp + PERIOD_DURATION * 3).await;
+    schedule.set_paused(&mut context, &schedule.initializer, false).await.unwrap();
+    lockup.redeem(&mut context, &schedule).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup.receiving_token_account.pubkey()).await, 3_000);
+    let state: Lockup = get_state(&mut context, &lockup.lockup.pubkey()).await;
+    assert_eq!(state.periods_redeemed, 3);
+}
+
+#[tokio::test]
+async fn test_pause_blocks_lock() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    schedule.set_paused(&mut context, &schedule.initializer, true).await.unwrap();
+
+    let lockup = Keypair::new();
+    let source_token_account = Keypair::new();
+    let lockup_token_account = Keypair::new();
+    let (token_authority, _bump_seed) = find_token_authority_address(&program_id, &lockup.pubkey());
+    create_token_account(&mut context, &source_token_account, &schedule.mint.pubkey(), &schedule.initializer.pubkey()).await;
+    create_token_account(&mut context, &lockup_token_account, &schedule.mint.pubkey(), &token_authority).await;
+    mint_to(&mut context, &schedule.mint.pubkey(), &source_token_account.pubkey(), 4_000).await;
+    create_program_account(&mut context, &lockup, Lockup::LEN, &program_id).await;
+
+    let receiver = Pubkey::new_unique();
+    let lock_ix = || schedule.lock_ix(&lockup.pubkey(), &receiver, &lockup_token_account.pubkey(), &source_token_account.pubkey(), 4_000);
+    let result = process(&mut context, &[lock_ix()], &[&schedule.initializer]).await;
+    assert_custom_error(result, TokenDistributorError::SchedulePaused);
+
+    schedule.set_paused(&mut context, &schedule.initializer, false).await.unwrap();
+    process(&mut context, &[lock_ix()], &[&schedule.initializer]).await.unwrap();
+    assert_eq!(token_balance(&mut context, &lockup_token_account.pubkey()).await, 4_000);
+}
+
+#[tokio::test]
+async fn test_pause_authority() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let pause_authority = Keypair::new();
+
+    // nobody else can pause until made the pause authority
+    let result = schedule.set_paused(&mut context, &pause_authority, true).await;
+    assert_custom_error(result, TokenDistributorError::UnauthorizedAccount);
+
+    let ix = instruction::set_pause_authority(&program_id, &schedule.initializer.pubkey(), &schedule.schedule.pubkey(), &pause_authority.pubkey()).unwrap();
+    process(&mut context, &[ix], &[&schedule.initializer]).await.unwrap();
+    schedule.set_paused(&mut context, &pause_authority, true).await.unwrap();
+    let state: LockupSchedule = get_state(&mut context, &schedule.schedule.pubkey()).await;
+    assert_eq!(state.pause_authority, pause_authority.pubkey());
+    assert!(state.paused);
+
+    // the initializer can still resume, but the pause authority can't hand itself on
+    schedule.set_paused(&mut context, &schedule.initializer, false).await.unwrap();
+    let ix = instruction::set_pause_authority(&program_id, &pause_authority.pubkey(), &schedule.schedule.pubkey(), &Pubkey::new_unique()).unwrap();
+    let result = process(&mut context, &[ix], &[&pause_authority]).await;
+    assert_custom_error(result, TokenDistributorError::IncorrectOwner);
+}
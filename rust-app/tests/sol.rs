@@ -0,0 +1,265 @@
+mod common;
+
+use bpf_program_template::{
+    error::TokenDistributorError,
+    find_sol_vault_address,
+    instruction,
+    native_mint,
+    state::{Lockup, LockupSchedule, PeriodType, TimeUnit, VestingCurve},
+};
+use common::*;
+use solana_program::{instruction::InstructionError, program_pack::Pack, pubkey::Pubkey, system_instruction};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+    transaction::TransactionError,
+};
+
+const LOCKED_LAMPORTS: u64 = 4 * LAMPORTS_PER_SOL;
+
+fn assert_custom_error(result: Result<(), BanksClientError>, error: TokenDistributorError) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(error as u32)),
+    );
+}
+
+async fn lamports(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    context.banks_client.get_balance(*account).await.unwrap()
+}
+
+// a native SOL schedule of LOCKED_LAMPORTS and a lockup in it
+struct SolLockup {
+    program_id: Pubkey,
+    initializer: Keypair,
+    schedule: Keypair,
+    start_timestamp: u64,
+    receiver: Keypair,
+    lockup: Keypair,
+    sol_vault: Pubkey,
+}
+
+impl SolLockup {
+    async fn new(context: &mut ProgramTestContext, program_id: Pubkey) -> Self {
+        Self::with_lamports(context, program_id, LOCKED_LAMPORTS, 0).await
+    }
+
+    // lockup of lamports, with vault_prefund lamports sent to the vault's address before locking
+    async fn with_lamports(context: &mut ProgramTestContext, program_id: Pubkey, lamports: u64, vault_prefund: u64) -> Self {
+        let initializer = Keypair::new();
+        let schedule = Keypair::new();
+        let receiver = Keypair::new();
+        let lockup = Keypair::new();
+        create_program_account(context, &schedule, LockupSchedule::LEN, &program_id).await;
+        create_program_account(context, &lockup, Lockup::LEN, &program_id).await;
+        let (sol_vault, _bump_seed) = find_sol_vault_address(&program_id, &lockup.pubkey());
+        if vault_prefund > 0 {
+            let prefund = system_instruction::transfer(&context.payer.pubkey(), &sol_vault, vault_prefund);
+            process(context, &[prefund], &[]).await.unwrap();
+        }
+        let fund_initializer = system_instruction::transfer(&context.payer.pubkey(), &initializer.pubkey(), LOCKED_LAMPORTS + LAMPORTS_PER_SOL);
+        process(context, &[fund_initializer], &[]).await.unwrap();
+
+        let start_timestamp = current_timestamp(context).await + 100;
+        let ixs = [
+            instruction::create_lockup_schedule(
+                &program_id,
+                &initializer.pubkey(),
+                &schedule.pubkey(),
+                &native_mint::id(),
+                start_timestamp,
+                NUMBER_PERIODS,
+                PERIOD_DURATION,
+                LOCKED_LAMPORTS,
+                TimeUnit::UnixTimestamp,
+                PeriodType::Fixed,
+                VestingCurve::default(),
+            ).unwrap(),
+            instruction::lock_lamports(
+                &program_id,
+                &initializer.pubkey(),
+                &schedule.pubkey(),
+                &lockup.pubkey(),
+                &receiver.pubkey(),
+                lamports,
+            ).unwrap(),
+        ];
+        process(context, &ixs, &[&initializer]).await.unwrap();
+
+        SolLockup { program_id, initializer, schedule, start_timestamp, receiver, lockup, sol_vault }
+    }
+
+    async fn redeem(
+        &self,
+        context: &mut ProgramTestContext,
+        destination: &Pubkey,
+        token_program: Option<&Pubkey>,
+        amount: Option<u64>,
+    ) -> Result<(), BanksClientError> {
+        let ix = instruction::redeem_lamports(
+            &self.program_id,
+            &self.receiver.pubkey(),
+            &self.schedule.pubkey(),
+            &self.lockup.pubkey(),
+            destination,
+            token_program,
+            amount,
+        ).unwrap();
+        process(context, &[ix], &[&self.receiver]).await
+    }
+}
+
+#[tokio::test]
+async fn test_lock_and_redeem_lamports() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let sol = SolLockup::new(&mut context, program_id).await;
+    let rent = context.banks_client.get_rent().await.unwrap().minimum_balance(0);
+
+    // the vault holds the lamports on top of its rent and is the lockup's token account
+    let vault = context.banks_client.get_account(sol.sol_vault).await.unwrap().unwrap();
+    assert_eq!(vault.owner, program_id);
+    assert_eq!(vault.lamports, rent + LOCKED_LAMPORTS);
+    let state: Lockup = get_state(&mut context, &sol.lockup.pubkey()).await;
+    assert_eq!(state.lockup_token_account, sol.sol_vault);
+    assert_eq!(state.token_quantity, LOCKED_LAMPORTS);
+    let schedule: LockupSchedule = get_state(&mut context, &sol.schedule.pubkey()).await;
+    assert_eq!(schedule.token_quantity_locked, LOCKED_LAMPORTS);
+
+    // a period in, a quarter is sent to the receiver's wallet
+    let receiver = sol.receiver.pubkey();
+    warp_to_timestamp(&mut context, sol.start_timestamp + PERIOD_DURATION).await;
+    sol.redeem(&mut context, &receiver, None, None).await.unwrap();
+    assert_eq!(lamports(&mut context, &receiver).await, LAMPORTS_PER_SOL);
+    let state: Lockup = get_state(&mut context, &sol.lockup.pubkey()).await;
+    assert_eq!(state.periods_redeemed, 1);
+
+    // the last redeem closes the vault, so its rent and lamports sent to it go to the receiver too
+    let transfer_to_vault = system_instruction::transfer(&context.payer.pubkey(), &sol.sol_vault, 5_000);
+    process(&mut context, &[transfer_to_vault], &[]).await.unwrap();
+    warp_to_timestamp(&mut context, sol.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    sol.redeem(&mut context, &receiver, None, None).await.unwrap();
+    assert_eq!(lamports(&mut context, &receiver).await, LOCKED_LAMPORTS + 5_000 + rent);
+    assert!(context.banks_client.get_account(sol.sol_vault).await.unwrap().is_none());
+    let state: Lockup = get_state(&mut context, &sol.lockup.pubkey()).await;
+    assert_eq!(state.redeemed_amount, LOCKED_LAMPORTS);
+    assert_eq!(state.periods_redeemed, NUMBER_PERIODS);
+}
+
+#[tokio::test]
+async fn test_redeem_lamports_uneven_periods() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let sol = SolLockup::with_lamports(&mut context, program_id, 10, 0).await;
+    let rent = context.banks_client.get_rent().await.unwrap().minimum_balance(0);
+    let receiver = sol.receiver.pubkey();
+    let fund_receiver = system_instruction::transfer(&context.payer.pubkey(), &receiver, rent);
+    process(&mut context, &[fund_receiver], &[]).await.unwrap();
+
+    // every period has unlocked, but redeeming part of the lamports leaves the vault open
+    warp_to_timestamp(&mut context, sol.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    sol.redeem(&mut context, &receiver, None, Some(9)).await.unwrap();
+    assert_eq!(lamports(&mut context, &receiver).await, rent + 9);
+    assert_eq!(lamports(&mut context, &sol.sol_vault).await, rent + 1);
+    let state: Lockup = get_state(&mut context, &sol.lockup.pubkey()).await;
+    assert_eq!(state.redeemed_amount, 9);
+
+    // redeeming the rest closes it
+    sol.redeem(&mut context, &receiver, None, None).await.unwrap();
+    assert_eq!(lamports(&mut context, &receiver).await, rent + 10 + rent);
+    assert!(context.banks_client.get_account(sol.sol_vault).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_lock_lamports_prefunded_vault() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+
+    let rent = context.banks_client.get_rent().await.unwrap().minimum_balance(0);
+
+    // lamports sent to the vault's address beforehand don't block the lock, only what's missing is topped up
+    let sol = SolLockup::with_lamports(&mut context, program_id, LOCKED_LAMPORTS, rent).await;
+    let vault = context.banks_client.get_account(sol.sol_vault).await.unwrap().unwrap();
+    assert_eq!(vault.owner, program_id);
+    assert_eq!(vault.lamports, rent + LOCKED_LAMPORTS);
+
+    // more than the vault needs is left there, and goes to the receiver with the last redeem
+    let sol = SolLockup::with_lamports(&mut context, program_id, 1_000, rent + 5_000).await;
+    assert_eq!(lamports(&mut context, &sol.sol_vault).await, rent + 5_000);
+    warp_to_timestamp(&mut context, sol.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    let receiver = sol.receiver.pubkey();
+    sol.redeem(&mut context, &receiver, None, None).await.unwrap();
+    assert_eq!(lamports(&mut context, &receiver).await, rent + 5_000);
+}
+
+#[tokio::test]
+async fn test_redeem_wrapped_lamports() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let sol = SolLockup::new(&mut context, program_id).await;
+    let wrapped_sol_account = Keypair::new();
+    create_token_account(&mut context, &wrapped_sol_account, &native_mint::id(), &sol.receiver.pubkey()).await;
+    warp_to_timestamp(&mut context, sol.start_timestamp + PERIOD_DURATION).await;
+
+    // a token account only gets lamports with the token program to sync it
+    let result = sol.redeem(&mut context, &wrapped_sol_account.pubkey(), None, None).await;
+    assert_custom_error(result, TokenDistributorError::InvalidDestination);
+
+    sol.redeem(&mut context, &wrapped_sol_account.pubkey(), Some(&spl_token::id()), Some(LAMPORTS_PER_SOL / 2)).await.unwrap();
+    assert_eq!(token_balance(&mut context, &wrapped_sol_account.pubkey()).await, LAMPORTS_PER_SOL / 2);
+
+    // more than has unlocked
+    let result = sol.redeem(&mut context, &wrapped_sol_account.pubkey(), Some(&spl_token::id()), Some(LAMPORTS_PER_SOL)).await;
+    assert_custom_error(result, TokenDistributorError::InvalidRedeemAmount);
+
+    // the vault's rent is wrapped along with the rest when the last redeem closes it
+    let rent = context.banks_client.get_rent().await.unwrap().minimum_balance(0);
+    warp_to_timestamp(&mut context, sol.start_timestamp + PERIOD_DURATION * NUMBER_PERIODS).await;
+    sol.redeem(&mut context, &wrapped_sol_account.pubkey(), Some(&spl_token::id()), None).await.unwrap();
+    assert_eq!(token_balance(&mut context, &wrapped_sol_account.pubkey()).await, LOCKED_LAMPORTS + rent);
+    assert!(context.banks_client.get_account(sol.sol_vault).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_lock_lamports_requires_native_mint() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let schedule = ScheduleFixture::new(&mut context, program_id).await;
+    let lockup = Keypair::new();
+    create_program_account(&mut context, &lockup, Lockup::LEN, &program_id).await;
+    let fund_initializer = system_instruction::transfer(&context.payer.pubkey(), &schedule.initializer.pubkey(), LAMPORTS_PER_SOL);
+    process(&mut context, &[fund_initializer], &[]).await.unwrap();
+
+    let ix = instruction::lock_lamports(
+        &program_id,
+        &schedule.initializer.pubkey(),
+        &schedule.schedule.pubkey(),
+        &lockup.pubkey(),
+        &Pubkey::new_unique(),
+        1_000,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&schedule.initializer]).await;
+    assert_custom_error(result, TokenDistributorError::InvalidMint);
+}
+
+#[tokio::test]
+async fn test_lock_lamports_existing_lockup() {
+    let program_id = Pubkey::new_unique();
+    let mut context = program_test(program_id).start_with_context().await;
+    let sol = SolLockup::new(&mut context, program_id).await;
+    let state: Lockup = get_state(&mut context, &sol.lockup.pubkey()).await;
+
+    // locking again into the same state account can't hand the lockup to another receiver
+    let ix = instruction::lock_lamports(
+        &program_id,
+        &sol.initializer.pubkey(),
+        &sol.schedule.pubkey(),
+        &sol.lockup.pubkey(),
+        &Pubkey::new_unique(),
+        1_000,
+    ).unwrap();
+    let result = process(&mut context, &[ix], &[&sol.initializer]).await;
+    assert_eq!(result.unwrap_err().unwrap(), TransactionError::InstructionError(0, InstructionError::AccountAlreadyInitialized));
+    assert_eq!(get_state::<Lockup>(&mut context, &sol.lockup.pubkey()).await, state);
+}
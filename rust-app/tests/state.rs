@@ -0,0 +1,196 @@
+use bpf_program_template::{
+    error::TokenDistributorError,
+    state::{AccountType, CurvePoint, CurveType, Lockup, LockupSchedule, LockupScheduleV0, LockupV0, PeriodType, TimeUnit, VestingCurve, MAX_PERIOD_DURATION, MAX_PERIOD_DURATION_MONTHS, STATE_VERSION},
+};
+use solana_program::{clock::Clock, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
+
+fn lockup_schedule() -> LockupSchedule {
+    LockupSchedule {
+        token_quantity_locked: 4_000,
+        pause_authority: Pubkey::new_unique(),
+        paused: true,
+        ..LockupSchedule::new(Pubkey::new_unique(), Pubkey::new_unique(), 1_000, 4, 100, 10_000)
+    }
+}
+
+#[test]
+fn test_pack_unpack_round_trip() {
+    let lockup_schedule = lockup_schedule();
+    let mut data = vec![0; LockupSchedule::LEN];
+    LockupSchedule::pack(lockup_schedule.clone(), &mut data).unwrap();
+    assert_eq!(data[0], AccountType::LockupSchedule as u8);
+    assert_eq!(data[1], STATE_VERSION);
+    assert_eq!(LockupSchedule::unpack(&data).unwrap(), lockup_schedule);
+}
+
+#[test]
+fn test_unpack_empty_account() {
+    let lockup = Lockup::unpack_unchecked(&[0; Lockup::LEN]).unwrap();
+    assert_eq!(lockup.account_type, AccountType::Uninitialized);
+    assert!(Lockup::unpack(&[0; Lockup::LEN]).is_err());
+}
+
+#[test]
+fn test_unpack_rejects_wrong_account_type() {
+    let mut data = vec![0; LockupSchedule::LEN];
+    LockupSchedule::pack(lockup_schedule(), &mut data).unwrap();
+    data[0] = AccountType::Lockup as u8;
+    assert_eq!(
+        LockupSchedule::unpack_unchecked(&data).unwrap_err(),
+        ProgramError::from(TokenDistributorError::InvalidAccountType),
+    );
+}
+
+#[test]
+fn test_unpack_rejects_unknown_version() {
+    let mut data = vec![0; LockupSchedule::LEN];
+    LockupSchedule::pack(lockup_schedule(), &mut data).unwrap();
+    data[1] = STATE_VERSION + 1;
+    assert_eq!(
+        LockupSchedule::unpack_unchecked(&data).unwrap_err(),
+        ProgramError::from(TokenDistributorError::InvalidStateVersion),
+    );
+}
+
+#[test]
+fn test_version_0_schedule_migrates_unpaused() {
+    let schedule = lockup_schedule();
+    let legacy = LockupScheduleV0 {
+        is_initialized: true,
+        initializer: schedule.initializer,
+        token_mint: schedule.token_mint,
+        start_timestamp: schedule.start_timestamp,
+        number_periods: schedule.number_periods,
+        period_duration: schedule.period_duration,
+        total_token_quantity: schedule.total_token_quantity,
+        token_quantity_locked: schedule.token_quantity_locked,
+    };
+    let data = borsh::to_vec(&legacy).unwrap();
+    assert_eq!(data.len(), LockupScheduleV0::LEN);
+    let migrated = LockupScheduleV0::unpack(&data).unwrap().migrate();
+    assert_eq!(migrated, LockupSchedule { pause_authority: Pubkey::default(), paused: false, ..schedule });
+}
+
+#[test]
+fn test_migrate_redeemed_amount() {
+    let schedule = lockup_schedule();
+    let legacy = LockupV0 {
+        is_initialized: true,
+        lockup_schedule_state: Pubkey::new_unique(),
+        receiving_account: Pubkey::new_unique(),
+        lockup_token_account: Pubkey::new_unique(),
+        token_quantity: 4_003,
+        periods_redeemed: 3,
+    };
+
+    // lockups redeemed before redeemed_amount was tracked were paid whole periods
+    let lockup = legacy.clone().migrate(&schedule).unwrap();
+    assert_eq!(lockup.version, STATE_VERSION);
+    assert_eq!(lockup.redeemed_amount, 3_000);
+    let lockup = LockupV0 { periods_redeemed: 4, ..legacy }.migrate(&schedule).unwrap();
+    assert_eq!(lockup.redeemed_amount, 4_003);
+    assert_eq!(schedule.periods_covered(4_003, 1_999).unwrap(), 1);
+    assert_eq!(schedule.periods_covered(4_003, 4_003).unwrap(), 4);
+}
+
+#[test]
+fn test_time_unit() {
+    let clock = Clock { slot: 250_000_000, epoch: 580, unix_timestamp: 1_700_000_000, ..Clock::default() };
+    assert_eq!(TimeUnit::UnixTimestamp.current_time(&clock).unwrap(), 1_700_000_000);
+    assert_eq!(TimeUnit::Slot.current_time(&clock).unwrap(), 250_000_000);
+    assert_eq!(TimeUnit::Epoch.current_time(&clock).unwrap(), 580);
+
+    // a 100 year period in seconds is far too long in epochs
+    let schedule = |time_unit| LockupSchedule { time_unit, start_timestamp: 0, period_duration: MAX_PERIOD_DURATION, ..lockup_schedule() };
+    assert!(schedule(TimeUnit::UnixTimestamp).validate_period_config().is_ok());
+    assert!(schedule(TimeUnit::Slot).validate_period_config().is_ok());
+    assert_eq!(
+        schedule(TimeUnit::Epoch).validate_period_config().unwrap_err(),
+        ProgramError::from(TokenDistributorError::InvalidPeriodConfig),
+    );
+}
+
+#[test]
+fn test_calendar_month_periods() {
+    // 2026-01-31T12:00:00Z, so later periods end on the last day of shorter months
+    let schedule = LockupSchedule {
+        start_timestamp: 1_769_860_800,
+        period_duration: 1,
+        period_type: PeriodType::CalendarMonth,
+        ..lockup_schedule()
+    };
+    assert!(schedule.validate_period_config().is_ok());
+    assert_eq!(schedule.period_end(1).unwrap(), 1_772_280_000); // 2026-02-28T12:00:00Z
+    assert_eq!(schedule.period_end(2).unwrap(), 1_774_958_400); // 2026-03-31T12:00:00Z
+    assert_eq!(schedule.period_end(4).unwrap(), 1_780_228_800); // 2026-05-31T12:00:00Z
+
+    assert_eq!(schedule.periods_unlocked(1_772_280_000 - 1).unwrap(), 0);
+    assert_eq!(schedule.periods_unlocked(1_772_280_000).unwrap(), 1);
+    assert_eq!(schedule.periods_unlocked(1_774_958_400 - 1).unwrap(), 1);
+    assert_eq!(schedule.periods_unlocked(1_774_958_400).unwrap(), 2);
+    assert_eq!(schedule.periods_unlocked(u32::MAX as u64).unwrap(), 4);
+    assert_eq!(schedule.vested_amount(4_003, 1_774_958_400).unwrap(), 2_000);
+    assert_eq!(schedule.next_unlock_timestamp(1_772_280_000).unwrap(), Some(1_774_958_400));
+
+    // a quarter is three months
+    let quarterly = LockupSchedule { period_type: PeriodType::CalendarQuarter, ..schedule.clone() };
+    assert_eq!(quarterly.period_end(1).unwrap(), 1_777_550_400); // 2026-04-30T12:00:00Z
+    assert_eq!(quarterly.periods_unlocked(1_777_550_400 - 1).unwrap(), 0);
+    assert_eq!(quarterly.periods_unlocked(1_777_550_400).unwrap(), 1);
+
+    // calendar periods need unix timestamps and at most 100 years per period
+    for invalid in [
+        LockupSchedule { time_unit: TimeUnit::Slot, ..schedule.clone() },
+        LockupSchedule { period_duration: MAX_PERIOD_DURATION_MONTHS + 1, ..schedule.clone() },
+        LockupSchedule { period_duration: MAX_PERIOD_DURATION_MONTHS / 3 + 1, ..quarterly },
+    ] {
+        assert_eq!(
+            invalid.validate_period_config().unwrap_err(),
+            ProgramError::from(TokenDistributorError::InvalidPeriodConfig),
+        );
+    }
+}
+
+fn curve_schedule(curve_type: CurveType, points: &[(u16, u16)]) -> Result<LockupSchedule, ProgramError> {
+    let mut schedule = lockup_schedule();
+    let points = points.iter().map(|&(period, basis_points)| CurvePoint { period, basis_points }).collect();
+    schedule.set_curve(&VestingCurve { curve_type, points })?;
+    schedule.validate_curve()?;
+    Ok(schedule)
+}
+
+#[test]
+fn test_curves() {
+    // back-loaded 5/15/30/50 over the 4 periods
+    let steps = curve_schedule(CurveType::Steps, &[(1, 500), (2, 2_000), (3, 5_000), (4, 10_000)]).unwrap();
+    let vested: Vec<u64> = (0..=4).map(|period| steps.vested_at_period(10_000, period).unwrap()).collect();
+    assert_eq!(vested, [0, 500, 2_000, 5_000, 10_000]);
+    assert_eq!(steps.vested_at_period(4_003, 1).unwrap(), 200);
+    assert_eq!(steps.vested_amount(10_000, 1_250).unwrap(), 2_000);
+    assert_eq!(steps.periods_covered(10_000, 1_999).unwrap(), 1);
+    assert_eq!(steps.periods_covered(10_000, 2_000).unwrap(), 2);
+    assert_eq!(steps.periods_covered(10_000, 10_000).unwrap(), 4);
+
+    // a cliff at period 2 then linear to the end
+    let piecewise = curve_schedule(CurveType::PiecewiseLinear, &[(2, 2_000), (4, 10_000)]).unwrap();
+    let vested: Vec<u64> = (0..=4).map(|period| piecewise.vested_at_period(10_000, period).unwrap()).collect();
+    assert_eq!(vested, [0, 1_000, 2_000, 6_000, 10_000]);
+    assert_eq!(piecewise.periods_covered(10_000, 5_999).unwrap(), 2);
+
+    assert!(curve_schedule(CurveType::Linear, &[]).is_ok());
+    for (curve_type, points) in [
+        (CurveType::Linear, &[(4, 10_000)][..]),
+        (CurveType::Steps, &[][..]),
+        (CurveType::Steps, &[(2, 5_000), (1, 6_000), (4, 10_000)][..]),
+        (CurveType::Steps, &[(1, 6_000), (2, 5_000), (4, 10_000)][..]),
+        (CurveType::Steps, &[(0, 0), (4, 10_000)][..]),
+        (CurveType::PiecewiseLinear, &[(1, 5_000), (3, 10_000)][..]),
+        (CurveType::PiecewiseLinear, &[(1, 5_000), (4, 9_999)][..]),
+        (CurveType::PiecewiseLinear, &[(1, 5_000); 17][..]),
+    ] {
+        assert_eq!(
+            curve_schedule(curve_type, points).unwrap_err(),
+            ProgramError::from(TokenDistributorError::InvalidCurve),
+        );
+    }
+}